@@ -4,14 +4,256 @@ use proc_macro::TokenStream;
 use quote::{quote, ToTokens};
 use syn::{parse_macro_input, ItemFn};
 
+/// Doc sections an AI function prompt is made of, in the order they are rendered
+#[derive(Clone, Copy, PartialEq)]
+enum Section {
+    Input,
+    Function,
+    Important,
+    Output,
+    Example,
+}
+
+impl Section {
+    /// Sections every AI function must document
+    const REQUIRED: [Section; 3] = [Section::Input, Section::Function, Section::Output];
+
+    /// Reads a section heading such as `Input:`, `IMPORTANT:` or `Example 2:`
+    fn from_heading(heading: &str) -> Option<Section> {
+        let heading: String = heading.trim().to_ascii_lowercase();
+        match heading.as_str() {
+            "input" => Some(Section::Input),
+            "function" => Some(Section::Function),
+            "important" => Some(Section::Important),
+            "output" => Some(Section::Output),
+            "example" | "examples" => Some(Section::Example),
+            _ => heading
+                .strip_prefix("example ")
+                .filter(|number| number.chars().all(|c| c.is_ascii_digit()))
+                .map(|_| Section::Example),
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Section::Input => "Input",
+            Section::Function => "Function",
+            Section::Important => "Important",
+            Section::Output => "Output",
+            Section::Example => "Example",
+        }
+    }
+}
+
+/// AI function docs split into their sections, with the prompt rendered from them
+struct Prompt {
+    signature: String,
+    input: String,
+    function: String,
+    important: Vec<String>,
+    output: String,
+    examples: Vec<String>,
+    rendered: String,
+}
+
+impl Prompt {
+
+    /// Parses the doc comments in the body of an AI function into its sections
+    fn parse(input_fn: &ItemFn) -> syn::Result<Prompt> {
+        let fn_name: String = input_fn.sig.ident.to_string();
+
+        // Doc comments in the body are attributes of the statement they precede
+        let mut doc_lines: Vec<String> = vec![];
+        for stmt in &input_fn.block.stmts {
+            let attrs: &[syn::Attribute] = match stmt {
+                syn::Stmt::Macro(stmt_macro) => &stmt_macro.attrs,
+                syn::Stmt::Expr(syn::Expr::Macro(expr_macro), _) => &expr_macro.attrs,
+                _ => &[],
+            };
+            for attr in attrs.iter().filter(|attr| attr.path().is_ident("doc")) {
+                let syn::Meta::NameValue(syn::MetaNameValue {
+                    value: syn::Expr::Lit(syn::ExprLit { lit: syn::Lit::Str(doc), .. }),
+                    ..
+                }) = &attr.meta
+                else {
+                    continue;
+                };
+                for line in doc.value().lines() {
+                    doc_lines.push(line.strip_prefix(' ').unwrap_or(line).trim_end().to_string());
+                }
+            }
+        }
+
+        // A section runs from its heading up to the next one
+        let mut sections: Vec<(Section, String, Vec<String>)> = vec![];
+        for line in doc_lines {
+            let heading: Option<(Section, &str)> = match line.split_once(':') {
+                Some((heading, rest)) if !line.starts_with(char::is_whitespace) => {
+                    Section::from_heading(heading).map(|section| (section, rest.trim()))
+                }
+                _ => None,
+            };
+            match (heading, sections.last_mut()) {
+                (Some((section, first_line)), _) => {
+                    sections.push((section, first_line.to_string(), vec![]))
+                }
+                (None, Some((_, _, rest))) => rest.push(line),
+                (None, None) if line.trim().is_empty() => {}
+                (None, None) => {
+                    return Err(syn::Error::new_spanned(
+                        &input_fn.block,
+                        format!("AI function `{}` has doc text before its first section: {:?}", fn_name, line),
+                    ))
+                }
+            }
+        }
+
+        let missing: Vec<&str> = Section::REQUIRED
+            .iter()
+            .filter(|required| !sections.iter().any(|(section, _, _)| section == *required))
+            .map(|required| required.name())
+            .collect();
+        if !missing.is_empty() {
+            return Err(syn::Error::new_spanned(
+                &input_fn.sig.ident,
+                format!("AI function `{}` is missing the doc section(s): {}", fn_name, missing.join(", ")),
+            ));
+        }
+        for required in Section::REQUIRED {
+            if sections.iter().filter(|(section, _, _)| *section == required).count() > 1 {
+                return Err(syn::Error::new_spanned(
+                    &input_fn.sig.ident,
+                    format!("AI function `{}` has more than one {} doc section", fn_name, required.name()),
+                ));
+            }
+        }
+
+        let signature: String = signature(&input_fn.sig);
+        let mut rendered: Vec<String> = vec![signature.clone()];
+        for order in [Section::Input, Section::Function, Section::Important, Section::Output, Section::Example] {
+            for (section, first_line, rest) in sections.iter().filter(|(section, _, _)| *section == order) {
+                let label: String = section.name().to_uppercase();
+                rendered.push(match first_line.is_empty() {
+                    true => format!("{}:", label),
+                    false => format!("{}: {}", label, first_line),
+                });
+                rendered.extend(rest.iter().cloned());
+            }
+        }
+
+        let bodies = |wanted: Section| -> Vec<String> {
+            sections
+                .iter()
+                .filter(|(section, _, _)| *section == wanted)
+                .map(|(_, first_line, rest)| {
+                    let mut lines: Vec<&str> = vec![first_line.as_str()];
+                    lines.extend(rest.iter().map(String::as_str));
+                    lines.join("\n").trim_matches('\n').trim_end().to_string()
+                })
+                .collect()
+        };
+        Ok(Prompt {
+            signature,
+            input: bodies(Section::Input).remove(0),
+            function: bodies(Section::Function).remove(0),
+            important: bodies(Section::Important),
+            output: bodies(Section::Output).remove(0),
+            examples: bodies(Section::Example),
+            rendered: rendered.join("\n").trim_end().to_string(),
+        })
+    }
+
+    /// `<NAME>_PROMPT` constant holding the prompt metadata of an AI function
+    fn metadata(&self, input_fn: &ItemFn) -> (syn::Ident, proc_macro2::TokenStream) {
+        let fn_ident: &syn::Ident = &input_fn.sig.ident;
+        let fn_name: String = fn_ident.to_string();
+        let fn_vis: &syn::Visibility = &input_fn.vis;
+        let const_ident: syn::Ident = syn::Ident::new(
+            &format!("{}_PROMPT", fn_name.trim_start_matches("r#").to_uppercase()),
+            fn_ident.span(),
+        );
+        let Prompt { signature, input, function, important, output, examples, rendered } = self;
+        let doc: String = format!(" Prompt sections and rendered prompt of `{}`", fn_name);
+
+        let tokens: proc_macro2::TokenStream = quote! {
+            #[doc = #doc]
+            #fn_vis const #const_ident: ::rust_backend_gen_agents::PromptMetadata = ::rust_backend_gen_agents::PromptMetadata {
+                name: #fn_name,
+                signature: #signature,
+                input: #input,
+                function: #function,
+                important: &[#(#important),*],
+                output: #output,
+                examples: &[#(#examples),*],
+                rendered: #rendered,
+            };
+        };
+        (const_ident, tokens)
+    }
+}
+
+/// `fn name(arg: Type) -> Ret` with the token spacing of `quote` cleaned up
+fn signature(sig: &syn::Signature) -> String {
+    let inputs: Vec<String> = sig
+        .inputs
+        .iter()
+        .map(|input| match input {
+            syn::FnArg::Typed(typed) => {
+                let name: String = match typed.pat.as_ref() {
+                    syn::Pat::Ident(pat_ident) => pat_ident.ident.to_string().trim_start_matches('_').to_string(),
+                    pat => tidy_tokens(pat),
+                };
+                format!("{}: {}", name, tidy_tokens(&typed.ty))
+            }
+            syn::FnArg::Receiver(receiver) => tidy_tokens(receiver),
+        })
+        .collect();
+    let output: String = match &sig.output {
+        syn::ReturnType::Type(_, return_type) => format!(" -> {}", tidy_tokens(return_type)),
+        syn::ReturnType::Default => String::new(),
+    };
+    format!("fn {}({}){}", sig.ident, inputs.join(", "), output)
+}
+
+fn tidy_tokens(tokens: &impl ToTokens) -> String {
+    let mut tidy: String = tokens.to_token_stream().to_string();
+    for (spaced, joined) in [
+        (" :: ", "::"),
+        (":: ", "::"),
+        (" < ", "<"),
+        ("< ", "<"),
+        (" <", "<"),
+        (" >", ">"),
+        (" ,", ","),
+        ("& '", "&'"),
+        ("& ", "&"),
+        ("( ", "("),
+        (" )", ")"),
+        ("[ ", "["),
+        (" ]", "]"),
+        (" ;", ";"),
+    ] {
+        tidy = tidy.replace(spaced, joined);
+    }
+    tidy
+}
+
+/// Turns an AI function into a `fn` returning its prompt, rendered from the doc sections.
+///
+/// Also emits a `<NAME>_PROMPT` constant with each section. The Input, Function and Output
+/// sections are required, Important and Example(s) are optional.
 #[proc_macro_attribute]
 pub fn function_to_string(_attr: TokenStream, item: TokenStream) -> TokenStream {
 
     // Parse the input function
     let input_fn: ItemFn = parse_macro_input!(item as ItemFn);
 
-    // Create string representation of function
-    let function_str: String = format!("{}", input_fn.to_token_stream());
+    // Split the docs into sections
+    let prompt: Prompt = match Prompt::parse(&input_fn) {
+        Ok(prompt) => prompt,
+        Err(error) => return error.to_compile_error().into(),
+    };
+    let (const_ident, metadata) = prompt.metadata(&input_fn);
 
     // Define a new function with the same signature as the input function.
     let fn_ident: proc_macro2::Ident = input_fn.sig.ident;
@@ -20,9 +262,151 @@ pub fn function_to_string(_attr: TokenStream, item: TokenStream) -> TokenStream
 
     // Generate output function
     let output: proc_macro2::TokenStream = quote! {
+        #metadata
+
         pub fn #fn_ident #fn_generics(#fn_inputs) -> &'static str {
-            #function_str
+            #const_ident.rendered
         }
     };
     output.into()
-}
\ No newline at end of file
+}
+
+/// Settings given to `#[ai_function(...)]`
+#[derive(Default)]
+struct AiFunctionArgs {
+    model: Option<syn::LitStr>,
+    temperature: Option<syn::LitFloat>,
+    retries: Option<syn::LitInt>,
+}
+
+/// Turns an AI function into a typed `async fn` that calls the model and decodes its answer.
+///
+/// The generated function takes the agent position first, then the declared inputs, each
+/// formatted as `NAME: value`. Inputs marked `#[trusted]` come from the application and are
+/// sent as the CONTEXT of the prompt, the others are delimited as untrusted data. A `String`
+/// return type gets the raw answer, any other type is decoded from JSON. Accepts optional
+/// `model = "..."`, `temperature = 0.2` and `retries = 2`.
+#[proc_macro_attribute]
+pub fn ai_function(attr: TokenStream, item: TokenStream) -> TokenStream {
+
+    // Parse the attribute settings
+    let mut args: AiFunctionArgs = AiFunctionArgs::default();
+    let args_parser = syn::meta::parser(|meta| {
+        if meta.path.is_ident("model") {
+            args.model = Some(meta.value()?.parse()?);
+        } else if meta.path.is_ident("temperature") {
+            args.temperature = Some(meta.value()?.parse()?);
+        } else if meta.path.is_ident("retries") {
+            args.retries = Some(meta.value()?.parse()?);
+        } else {
+            return Err(meta.error("expected `model`, `temperature` or `retries`"));
+        }
+        Ok(())
+    });
+    parse_macro_input!(attr with args_parser);
+
+    // Parse the input function
+    let input_fn: ItemFn = parse_macro_input!(item as ItemFn);
+    match expand_ai_function(args, input_fn) {
+        Ok(output) => output.into(),
+        Err(error) => error.to_compile_error().into(),
+    }
+}
+
+fn expand_ai_function(args: AiFunctionArgs, input_fn: ItemFn) -> syn::Result<proc_macro2::TokenStream> {
+
+    // Split the docs into sections
+    let prompt: Prompt = Prompt::parse(&input_fn)?;
+    let (const_ident, metadata) = prompt.metadata(&input_fn);
+
+    let return_type: Box<syn::Type> = match &input_fn.sig.output {
+        syn::ReturnType::Type(_, return_type) => return_type.clone(),
+        syn::ReturnType::Default => {
+            return Err(syn::Error::new_spanned(
+                &input_fn.sig,
+                "ai_function needs a return type to decode the answer into",
+            ))
+        }
+    };
+
+    // Each input is given to the model as `NAME: value`, trusted ones as the CONTEXT
+    let mut trusted_idents: Vec<syn::Ident> = vec![];
+    let mut trusted_labels: Vec<String> = vec![];
+    let mut input_idents: Vec<syn::Ident> = vec![];
+    let mut input_labels: Vec<String> = vec![];
+    let mut fn_inputs: syn::punctuated::Punctuated<syn::FnArg, syn::token::Comma> = input_fn.sig.inputs.clone();
+    for input in fn_inputs.iter_mut() {
+        let syn::FnArg::Typed(typed) = input else {
+            return Err(syn::Error::new_spanned(input, "ai_function inputs cannot take self"));
+        };
+        let syn::Pat::Ident(pat_ident) = typed.pat.as_ref() else {
+            return Err(syn::Error::new_spanned(&typed.pat, "ai_function inputs must be plain names"));
+        };
+        let is_trusted: bool = typed.attrs.iter().any(|attr| attr.path().is_ident("trusted"));
+        typed.attrs.retain(|attr| !attr.path().is_ident("trusted"));
+
+        let label: String = pat_ident.ident.to_string().trim_start_matches('_').to_uppercase();
+        match is_trusted {
+            true => {
+                trusted_labels.push(label);
+                trusted_idents.push(pat_ident.ident.clone());
+            }
+            false => {
+                input_labels.push(label);
+                input_idents.push(pat_ident.ident.clone());
+            }
+        }
+    }
+
+    let fn_ident: &proc_macro2::Ident = &input_fn.sig.ident;
+    let fn_name: String = fn_ident.to_string();
+    let fn_vis: &syn::Visibility = &input_fn.vis;
+    let fn_attrs: &Vec<syn::Attribute> = &input_fn.attrs;
+
+    let model: proc_macro2::TokenStream = match &args.model {
+        Some(model) => quote! { ::core::option::Option::Some(#model) },
+        None => quote! { ::core::option::Option::None },
+    };
+    let temperature: proc_macro2::TokenStream = match &args.temperature {
+        Some(temperature) => quote! { ::core::option::Option::Some(#temperature) },
+        None => quote! { ::core::option::Option::None },
+    };
+    let retries: proc_macro2::TokenStream = match &args.retries {
+        Some(retries) => quote! { #retries },
+        None => quote! { 1 },
+    };
+
+    // A String answer is returned as is, anything else is decoded from JSON
+    let is_string: bool = matches!(
+        return_type.as_ref(),
+        syn::Type::Path(type_path) if type_path.qself.is_none() && type_path.path.is_ident("String")
+    );
+    let request: proc_macro2::TokenStream = if is_string {
+        quote! { AI_FUNCTION.request(&instructions, &func_input, agent_position).await }
+    } else {
+        quote! { AI_FUNCTION.request_decoded::<#return_type>(&instructions, &func_input, agent_position).await }
+    };
+
+    // Generate typed async function
+    Ok(quote! {
+        #metadata
+
+        #(#fn_attrs)*
+        #fn_vis async fn #fn_ident(agent_position: &str, #fn_inputs) -> #return_type {
+            const AI_FUNCTION: ::rust_backend_gen_agents::AiFunctionCall = ::rust_backend_gen_agents::AiFunctionCall {
+                function_name: #fn_name,
+                function_str: #const_ident.rendered,
+                model: #model,
+                temperature: #temperature,
+                retries: #retries,
+            };
+            let instructions: ::std::string::String = ::rust_backend_gen_agents::AiFunctionCall::format_inputs(&[
+                #((#trusted_labels, &#trusted_idents)),*
+            ]);
+            let func_input: ::std::string::String = ::rust_backend_gen_agents::AiFunctionCall::format_inputs(&[
+                #((#input_labels, &#input_idents)),*
+            ]);
+            #request
+        }
+    })
+}
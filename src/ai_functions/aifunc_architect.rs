@@ -1,37 +1,73 @@
-use proc_macro::function_to_string;
+use crate::models::agents::agent_traits::ProjectScope;
+use proc_macro::ai_function;
 
-#[function_to_string]
-pub fn print_project_scope(_project_description: &str) {
+#[ai_function]
+pub fn print_project_scope(project_description: &str) -> ProjectScope {
     /// Input: Takes in a user request to build a website project description
     /// Function: Converts user request into JSON response of information items required for a website build.
     /// Important: At least one of the bool results must be true
+    /// Important: "entities" lists every domain object the backend stores or returns, with typed fields and relations.
+    ///   Field types are one of: string, integer, float, bool, datetime, id, list<type>
+    ///   Relation kinds are one of: one_to_one, one_to_many, many_to_one, many_to_many
     /// Output: Prints an object response in the following format:
     ///   {
     ///     "is_crud_required": bool, // true if site needs CRUD functionality
     ///     "is_user_login_and_logout": bool // true if site needs users to be able to log in and log out
     ///     "is_external_urls_required": bool // true if site needs to fetch data from third part providers
+    ///     "entities": [{ "name": string, "fields": [{ "name": string, "type": string, "is_optional": bool }], "relations": [{ "target": string, "kind": string }] }],
+    ///     "features": {
+    ///       "pagination": bool, // true if lists must be paged
+    ///       "search": bool, // true if users search or filter records
+    ///       "file_upload": bool, // true if users upload files or images
+    ///       "realtime": bool, // true if clients need live updates
+    ///       "admin_roles": bool, // true if some users have elevated permissions
+    ///       "scheduled_jobs": bool // true if work must run on a timer
+    ///     }
     ///   }
     /// Example 1:
     ///   user_request = "I need a full stack website that accepts users and gets stock price data"
     ///   prints:
     ///   {
-    ///     "is_crud_required": true
-    ///     "is_user_login_and_logout": true
-    ///     "is_external_urls_required": bool true
+    ///     "is_crud_required": true,
+    ///     "is_user_login_and_logout": true,
+    ///     "is_external_urls_required": true,
+    ///     "entities": [
+    ///       { "name": "User", "fields": [{ "name": "id", "type": "id" }, { "name": "username", "type": "string" }], "relations": [{ "target": "Watchlist", "kind": "one_to_many" }] },
+    ///       { "name": "Watchlist", "fields": [{ "name": "id", "type": "id" }, { "name": "symbols", "type": "list<string>" }], "relations": [] }
+    ///     ],
+    ///     "features": { "pagination": false, "search": true, "file_upload": false, "realtime": true, "admin_roles": false, "scheduled_jobs": false }
     ///   }
     /// Example 2:
     ///   user_request = "I need a simple TODO app"
     ///   prints:
     ///   {
-    ///     "is_crud_required": true
-    ///     "is_user_login_and_logout": false
-    ///     "is_external_urls_required": bool false
+    ///     "is_crud_required": true,
+    ///     "is_user_login_and_logout": false,
+    ///     "is_external_urls_required": false,
+    ///     "entities": [
+    ///       { "name": "Todo", "fields": [{ "name": "id", "type": "id" }, { "name": "title", "type": "string" }, { "name": "completed", "type": "bool" }], "relations": [] }
+    ///     ],
+    ///     "features": { "pagination": false, "search": false, "file_upload": false, "realtime": false, "admin_roles": false, "scheduled_jobs": false }
     ///   }
     println!(OUTPUT)
 }
 
-#[function_to_string]
-pub fn print_site_urls(_project_description: &str) {
+#[ai_function]
+pub fn print_non_functional_requirements(project_description: &str) -> Vec<String> {
+    /// Input: Takes in a project description of a website build
+    /// Function: Lists the non-functional requirements of the backend such as performance, security, reliability or data retention
+    /// Important: Only lists requirements that are stated or clearly implied by the description. Prints an empty list if there are none
+    /// Output: Prints a list response of short requirement statements in the following format:
+    /// ["requirement1", "requirement2", ...]
+    /// Example:
+    ///   website_team_spec = "build a website for a clinic that stores patient appointments and must stay fast with thousands of users"
+    ///   prints:
+    /// ["Patient data must only be visible to authenticated staff", "Appointment listings must stay responsive with thousands of concurrent users"]
+    println!(OUTPUT)
+}
+
+#[ai_function]
+pub fn print_site_urls(project_description: &str) -> Vec<String> {
     /// Input: Takes in a project description of a website build
     /// Function: Outputs a list of external public API endpoints that should be used in the building of the website
     /// Important: Only selects url endpoint(s) which do not require any API Keys at all
@@ -1,50 +1,109 @@
-use proc_macro::function_to_string;
+use crate::models::general::dependencies::CrateRequest;
+use proc_macro::ai_function;
 
-#[function_to_string]
-pub fn print_backend_webserver_code(_project_description_and_template: &str) {
-    /// INPUT: Takes in a PROJECT_DESCRIPTION and CODE_TEMPLATE for a website backend build
+#[ai_function]
+pub fn print_crate_requests(
+    #[trusted] framework: &str,
+    #[trusted] allowed_crates: &str,
+    project_description: &str,
+) -> Vec<CrateRequest> {
+    /// INPUT: Takes in a PROJECT_DESCRIPTION and the ALLOWED_CRATES that may be added on top of AVAILABLE_LIBRARIES, one "name: purpose" per line
+    /// FUNCTION: Picks the ALLOWED_CRATES the backend genuinely needs instead of hand-rolling the same functionality, e.g. password hashing, unique ids or dates
+    /// IMPORTANT: Only names crates from ALLOWED_CRATES. Prints an empty array if none are needed
+    /// OUTPUT: Prints ONLY a JSON array of objects with the keys "name" and "reason", e.g.
+    ///   [{"name": "argon2", "reason": "hash user passwords"}]
+    println!(OUTPUT)
+}
+
+#[ai_function]
+pub fn print_backend_webserver_code(
+    #[trusted] framework: &str,
+    code_template: &str,
+    project_spec: &str,
+    project_description: &str,
+) -> String {
+    /// INPUT: Takes in a PROJECT_DESCRIPTION, the PROJECT_SPEC worked out for it and CODE_TEMPLATE for a website backend build
+    /// IMPORTANT: The code is written for the web framework named in TARGET_FRAMEWORK and follows its FRAMEWORK_CONVENTIONS
     /// IMPORTANT: The backend code is ONLY an example. If the Project Description requires it, make as many changes as you like.
     /// IMPORTANT: You do not need to follow the backend code exactly. Write functions that make sense for the users request if required.
     /// FUNCTION: Takes an existing set of code marked as CODE_TEMPLATE and updates or re-writes it to work for the purpose in the PROJECT_DESCRIPTION
-    /// IMPORTANT: The following libraries are already installed
-    ///   reqwest, serde, serde_json, tokio, actix-web, async-trait, actix_cors
+    /// IMPORTANT: Only the libraries listed in AVAILABLE_LIBRARIES are installed.
     /// No other external libraries should be used. Write functions that fit with the description from the PROJECT_DESCRIPTION
+    /// IMPORTANT: If the PROJECT_DESCRIPTION contains EXTERNAL API SAMPLES, deserialize third-party data using exactly the FIELDS and SCHEMA recorded for that URL
+    /// IMPORTANT: Never hardcode the scheme and host of an external URL. Read it from its BASE URL ENV VAR and fall back to the real host, e.g.
+    ///   std::env::var("HP_API_ONRENDER_COM_BASE_URL").unwrap_or_else(|_| "https://hp-api.onrender.com".to_string())
     /// OUTPUT: Print ONLY the code, nothing else. This function ONLY prints code.
     println!(OUTPUT)
 }
 
-#[function_to_string]
-pub fn print_improved_webserver_code(_project_description_and_template: &str) {
-    /// INPUT: Takes in a PROJECT_DESCRIPTION and CODE_TEMPLATE for a website backend build
+#[ai_function]
+pub fn print_improved_webserver_code(
+    #[trusted] framework: &str,
+    code_template: &str,
+    project_spec: &str,
+    #[trusted] user_instruction: &str,
+    project_description: &str,
+) -> String {
+    /// INPUT: Takes in a PROJECT_DESCRIPTION, the PROJECT_SPEC worked out for it and CODE_TEMPLATE for a website backend build
     /// FUNCTION: Performs the following tasks:
     ///   1. Removes any bugs in the code and adds minor additional functionality
     ///   2. Makes sure everything requested in the spec from a backend standpoint was followed. If not, add the feature. No code should be implemented later. Everything should be written now.
     ///   3. ONLY writes the code. No commentary.
-    /// IMPORTANT: The following libraries are already installed. Does not use ANY libraries other than what was provided in the template
-    ///   reqwest, serde, serde_json, tokio, actix-web, async-trait
+    /// IMPORTANT: Keeps using the web framework named in TARGET_FRAMEWORK and its FRAMEWORK_CONVENTIONS
+    /// IMPORTANT: Only the libraries listed in AVAILABLE_LIBRARIES are installed. Does not use ANY other libraries
+    /// IMPORTANT: If the PROJECT_DESCRIPTION contains EXTERNAL API SAMPLES, make sure third-party data is deserialized using exactly the FIELDS and SCHEMA recorded for that URL
+    /// IMPORTANT: External hosts must be read from their BASE URL ENV VAR with the real host as fallback, never hardcoded
+    /// IMPORTANT: If a USER_INSTRUCTION is given, it comes from the person reviewing the code and must be followed
+    /// OUTPUT: Prints ONLY the improved code, nothing else
     println!(OUTPUT)
 }
 
-#[function_to_string]
-pub fn print_fixed_code(_broken_code_with_bugs: &str) {
-    /// INPUT: Takes in Rust BROKEN_CODE and the ERROR_BUGS found
-    /// FUNCTION: Removes bugs from code
-    /// IMPORTANT: Only prints out the new and improved code. No commentary or anything else
+#[ai_function]
+pub fn print_fixed_code(
+    #[trusted] framework: &str,
+    broken_code: &str,
+    error_bugs: &str,
+    #[trusted] user_instruction: &str,
+) -> String {
+    /// INPUT: Takes in Rust BROKEN_CODE and the ERROR_BUGS found, written for the web framework named in TARGET_FRAMEWORK
+    /// FUNCTION: Removes bugs from code without switching framework or adding libraries missing from AVAILABLE_LIBRARIES
+    /// IMPORTANT: If a USER_INSTRUCTION is given, it comes from the person reviewing the code and must be followed
+    /// OUTPUT: Prints only the new and improved code. No commentary or anything else
     println!(OUTPUT)
 }
 
-#[function_to_string]
-pub fn print_rest_api_endpoints(_code_input: &str) {
-    /// INPUT: Takes in Rust webserver CODE_INPUT based on actix-web
+#[ai_function]
+pub fn print_lint_fixed_code(
+    #[trusted] framework: &str,
+    code: &str,
+    lint_warnings: &str,
+    #[trusted] user_instruction: &str,
+) -> String {
+    /// INPUT: Takes in Rust CODE that compiles and the LINT_WARNINGS clippy reported for it, written for the web framework named in TARGET_FRAMEWORK
+    /// FUNCTION: Rewrites the code so the LINT_WARNINGS no longer apply, keeping every route and its behaviour unchanged
+    /// IMPORTANT: Does not switch framework or add libraries missing from AVAILABLE_LIBRARIES. Does not silence lints with allow attributes
+    /// IMPORTANT: If a USER_INSTRUCTION is given, it comes from the person reviewing the code and must be followed
+    /// OUTPUT: Prints only the new and improved code. No commentary or anything else
+    println!(OUTPUT)
+}
+
+#[ai_function]
+pub fn print_rest_api_endpoints(#[trusted] framework: &str, code_input: &str) -> String {
+    /// INPUT: Takes in Rust webserver CODE_INPUT based on the web framework named in TARGET_FRAMEWORK
     /// FUNCTION: Prints out the JSON schema for url endpoints and their respective types
     /// LOGIC: Script analyses all code and can categorize into the following object keys:
-    ///   "route": This represents the url path of the endpoint
-    ///   "is_route_dynamic": if a route has curly braces in it such as {symbol} or {id} as an example, then this will be set to true
-    ///   "method": This represents the method being called
-    ///   "request_body": This represents the body of a post method request
-    ///   "response": This represents the output based upon the structs in the code and understanding the functions
-    /// IMPORTANT: Only prints out the JSON schema. No commentary or anything else.
-    /// MUST READ: All keys are strings. Even bool should be wrapped in double quotes as "bool"
+    ///   "route": This represents the url path of the endpoint, without any query string. Dynamic segments are always written as {name}, also for :name or <name> syntax
+    ///   "is_route_dynamic": true if a route has curly braces in it such as {symbol} or {id}, otherwise false
+    ///   "method": This represents the method being called in lowercase: get, post, put, patch or delete
+    ///   "path_params": The names of the dynamic segments of the route, e.g. ["id"]
+    ///   "query_params": The names of any query parameters read by the handler, e.g. ["page"]
+    ///   "status_codes": The status codes the handler can return, e.g. [200, 404]
+    ///   "request_body": This represents the body of a post method request, or null if there is none
+    ///   "response": This represents the output based upon the structs in the code and understanding the functions, or null if there is none
+    /// MUST READ: Field types inside "request_body" and "response" are written as "string", "number" or "bool".
+    ///   Lists are written as an array with a single element describing the item type, e.g. ["string"].
+    ///   If a body exists but its shape cannot be known from the code, print "not_provided".
+    /// OUTPUT: Prints ONLY the JSON schema, a list with one object per endpoint using the keys above. No commentary or anything else.
     /// EXAMPLE:
     /// INPUT_CODE:
     /// ...
@@ -72,38 +131,50 @@ pub fn print_rest_api_endpoints(_code_input: &str) {
     /// [
     ///   {
     ///     "route": "/item/{id}",
-    ///     "is_route_dynamic": "true",
-    ///     "method": "get"
-    ///     "request_body": "None",
+    ///     "is_route_dynamic": true,
+    ///     "method": "get",
+    ///     "path_params": ["id"],
+    ///     "query_params": [],
+    ///     "status_codes": [200, 404],
+    ///     "request_body": null,
     ///     "response": {
     ///       "id": "number",
     ///       "name": "string",
-    ///       "completed": "bool",
+    ///       "completed": "bool"
     ///     }
     ///   },
     ///   {
     ///     "route": "/item",
-    ///     "is_route_dynamic": "false",
+    ///     "is_route_dynamic": false,
     ///     "method": "post",
+    ///     "path_params": [],
+    ///     "query_params": [],
+    ///     "status_codes": [200],
     ///     "request_body": {
     ///       "id": "number",
     ///       "name": "string",
-    ///       "completed": "bool",
+    ///       "completed": "bool"
     ///     },
-    ///     "response": "None"
+    ///     "response": null
     ///   },
     ///   {
     ///     "route": "/item/{id}",
-    ///     "is_route_dynamic": "true",
+    ///     "is_route_dynamic": true,
     ///     "method": "delete",
-    ///     "request_body": "None",
-    ///     "response": "None"
+    ///     "path_params": ["id"],
+    ///     "query_params": [],
+    ///     "status_codes": [200, 404],
+    ///     "request_body": null,
+    ///     "response": null
     ///   },
     ///   {
     ///     "route": "/crypto",
-    ///     "is_route_dynamic": "false",
+    ///     "is_route_dynamic": false,
     ///     "method": "get",
-    ///     "request_body": "None",
+    ///     "path_params": [],
+    ///     "query_params": [],
+    ///     "status_codes": [200, 500],
+    ///     "request_body": null,
     ///     "response": "not_provided"
     ///   },
     ///   ... // etc
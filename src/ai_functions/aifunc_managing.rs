@@ -1,7 +1,8 @@
-use proc_macro::function_to_string;
+use crate::models::general::clarification::ClarifiedGoal;
+use proc_macro::ai_function;
 
-#[function_to_string]
-pub fn convert_user_input_to_goal(_user_request: &str) {
+#[ai_function]
+pub fn convert_user_input_to_goal(user_request: &str) -> String {
     /// Input: Takes in a user request
     /// Function: Converts user request into a short summarized goal
     /// Output: Prints goal. All outputs start with "build a website that ..."
@@ -12,4 +13,38 @@ pub fn convert_user_input_to_goal(_user_request: &str) {
     ///   user_request = "Create something that stores crypto price data in a database using supabase and retrieves prices on the frontend."
     ///   OUTPUT = "build a website that fetches and stores crypto price data within a supabase setup including a frontend UI to fetch the data."
     println!(OUTPUT)
+}
+
+#[ai_function]
+pub fn print_clarifying_questions(#[trusted] max_questions: usize, user_request: &str) -> Vec<String> {
+    /// Input: Takes in a user request and the maximum number of questions as MAX_QUESTIONS
+    /// Function: Decides whether the request is too vague to build a backend from and lists the questions whose answers would change the backend the most
+    /// Important: Only asks about missing information such as who the users are, what data is stored, how users log in or which external services are used. Prints an empty list if the request is clear. Never prints more than MAX_QUESTIONS questions
+    /// Output: Prints a list response of short questions in the following format:
+    /// ["question1", "question2", ...]
+    /// Example:
+    ///   max_questions = 2, user_request = "I need a website for my gym"
+    ///   prints:
+    /// ["What should members be able to do on the website, for example book classes or track workouts?", "Do members need to log in?"]
+    println!(OUTPUT)
+}
+
+#[ai_function]
+pub fn print_clarified_goal(clarified_request: &str) -> ClarifiedGoal {
+    /// Input: Takes in a user request and the user's answers to clarifying questions
+    /// Function: Converts the request and answers into a summarized goal and lists the requirements the answers add
+    /// Important: The goal starts with "build a website that ..." and includes the details from the answers. Each requirement is one short statement taken from the answers. Answers the user skipped are ignored
+    /// Output: Prints a json response in the following format:
+    /// {
+    ///   "project_description": "build a website that ...",
+    ///   "requirements": ["requirement1", "requirement2", ...]
+    /// }
+    /// Example:
+    ///   clarified_request = "USER_REQUEST: I need a website for my gym CLARIFICATIONS: Q: What should members be able to do? A: Book classes Q: Do members need to log in? A: Yes, with email and password"
+    ///   prints:
+    /// {
+    ///   "project_description": "build a website that lets gym members log in with email and password and book classes",
+    ///   "requirements": ["Members log in with email and password", "Members can book classes"]
+    /// }
+    println!(OUTPUT)
 }
\ No newline at end of file
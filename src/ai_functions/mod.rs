@@ -1,3 +1,80 @@
 pub mod aifunc_architect;
 pub mod aifunc_backend;
 pub mod aifunc_managing;
+
+/// Prompt of an AI function split into its doc sections, emitted as `<NAME>_PROMPT`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PromptMetadata {
+    pub name: &'static str,
+    /// Signature shown to the model, e.g. `fn print_site_urls(project_description: &str) -> Vec<String>`
+    pub signature: &'static str,
+    pub input: &'static str,
+    pub function: &'static str,
+    pub important: &'static [&'static str],
+    pub output: &'static str,
+    pub examples: &'static [&'static str],
+    /// Signature followed by the sections in a fixed order, sent to the model
+    pub rendered: &'static str,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ai_functions::aifunc_architect::{
+        PRINT_PROJECT_SCOPE_PROMPT, PRINT_SITE_URLS_PROMPT,
+    };
+    use crate::ai_functions::aifunc_backend::{
+        PRINT_BACKEND_WEBSERVER_CODE_PROMPT, PRINT_FIXED_CODE_PROMPT,
+    };
+    use crate::ai_functions::aifunc_managing::CONVERT_USER_INPUT_TO_GOAL_PROMPT;
+
+    #[test]
+    fn tests_parses_prompt_sections() {
+        let prompt: PromptMetadata = PRINT_SITE_URLS_PROMPT;
+        assert_eq!(prompt.name, "print_site_urls");
+        assert_eq!(
+            prompt.signature,
+            "fn print_site_urls(project_description: &str) -> Vec<String>"
+        );
+        assert_eq!(
+            prompt.input,
+            "Takes in a project description of a website build"
+        );
+        assert_eq!(
+            prompt.important,
+            ["Only selects url endpoint(s) which do not require any API Keys at all"]
+        );
+        assert_eq!(
+            prompt.output,
+            "Prints a list response of external urls in the following format:\n[\"url1\", \"url2\", \"url3\", ...]"
+        );
+        assert_eq!(prompt.examples.len(), 1);
+        assert!(prompt.examples[0].starts_with("  website_team_spec = "));
+
+        // Continuation lines stay with their section and numbered examples are kept apart
+        assert_eq!(PRINT_PROJECT_SCOPE_PROMPT.important.len(), 2);
+        assert!(PRINT_PROJECT_SCOPE_PROMPT.important[1].ends_with("many_to_many"));
+        assert_eq!(CONVERT_USER_INPUT_TO_GOAL_PROMPT.examples.len(), 2);
+    }
+
+    #[test]
+    fn tests_renders_prompt_in_section_order() {
+        let lines: Vec<&str> = PRINT_FIXED_CODE_PROMPT.rendered.lines().collect();
+        assert_eq!(
+            lines[0],
+            "fn print_fixed_code(framework: &str, broken_code: &str, error_bugs: &str, user_instruction: &str) -> String"
+        );
+        assert!(lines[1].starts_with("INPUT: Takes in Rust BROKEN_CODE"));
+        assert!(lines[2].starts_with("FUNCTION: Removes bugs"));
+        assert!(lines[3].starts_with("IMPORTANT: If a USER_INSTRUCTION"));
+        assert_eq!(
+            lines[4],
+            "OUTPUT: Prints only the new and improved code. No commentary or anything else"
+        );
+        assert_eq!(lines.len(), 5);
+
+        // FUNCTION comes before IMPORTANT even where the docs list them the other way round
+        let rendered: &str = PRINT_BACKEND_WEBSERVER_CODE_PROMPT.rendered;
+        assert!(rendered.find("\nFUNCTION: ").unwrap() < rendered.find("\nIMPORTANT: ").unwrap());
+    }
+}
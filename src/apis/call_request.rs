@@ -1,31 +1,137 @@
-use crate::models::general::llm::{APIResponse, ChatCompletion, Message};
+use crate::models::general::llm::{APIResponse, ChatCompletion, LlmUsage, Message};
 use dotenv::dotenv;
 use reqwest::header::{HeaderMap, HeaderValue};
 use reqwest::Client;
 use std::env;
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+use tracing::Span;
+
+/// Model used when neither the provider nor the pipeline picks one
+pub const DEFAULT_MODEL: &str = "o4-mini";
+
+const OPEN_AI_CHAT_URL: &str = "https://api.openai.com/v1/chat/completions";
+
+/// Chat completions endpoint and credentials every LLM call goes to
+#[derive(Debug, Clone, PartialEq)]
+pub struct ProviderConfig {
+    pub api_key: String,
+    pub organization: Option<String>,
+    pub url: String,
+    /// Model for agents whose pipeline stage does not pick one
+    pub model: String,
+}
+
+impl ProviderConfig {
+    /// OpenAI with the given key and the default model
+    pub fn open_ai(api_key: &str) -> Self {
+        Self {
+            api_key: api_key.to_string(),
+            organization: None,
+            url: OPEN_AI_CHAT_URL.to_string(),
+            model: DEFAULT_MODEL.to_string(),
+        }
+    }
+
+    /// OpenAI with OPEN_AI_KEY and OPEN_AI_ORG from the environment or .env
+    pub fn from_env() -> Self {
+        dotenv().ok();
+
+        let api_key: String = env::var("OPEN_AI_KEY").expect("OPEN_AI_KEY not found in enviornment variables");
+        let mut provider: ProviderConfig = ProviderConfig::open_ai(&api_key);
+        provider.organization = env::var("OPEN_AI_ORG").ok();
+        provider
+    }
+}
+
+/// Provider of the running project, OPEN_AI_KEY and OPEN_AI_ORG outside one
+fn provider() -> ProviderConfig {
+    current_provider().unwrap_or_else(ProviderConfig::from_env)
+}
+
+tokio::task_local! {
+    /// Provider set by the embedding application for the project that is running
+    static PROVIDER: ProviderConfig;
+
+    /// Model picked by the pipeline for the agent that is running
+    static AGENT_MODEL: String;
+
+    /// Token spend of the pipeline stage that is running
+    static STAGE_USAGE: Arc<Mutex<LlmUsage>>;
+}
+
+/// Sends every LLM call made by the future to `provider`, keeping the current one when None
+pub async fn with_provider<F: Future>(provider: Option<ProviderConfig>, future: F) -> F::Output {
+    match provider {
+        Some(provider) => PROVIDER.scope(provider, future).await,
+        None => future.await,
+    }
+}
+
+/// Provider of the running project, None when the environment is used
+pub fn current_provider() -> Option<ProviderConfig> {
+    PROVIDER.try_with(ProviderConfig::clone).ok()
+}
+
+/// Runs an agent's work against its own model, keeping the current one when None
+pub async fn with_model<F: Future>(model: Option<String>, future: F) -> F::Output {
+    match model {
+        Some(model) => AGENT_MODEL.scope(model, future).await,
+        None => future.await,
+    }
+}
+
+/// Model picked for the running agent, None outside a pipeline stage
+pub fn current_model() -> Option<String> {
+    AGENT_MODEL.try_with(String::clone).ok()
+}
+
+/// Adds the token spend of every LLM call made by the future to `usage`, keeping the current tally when None
+pub async fn with_usage<F: Future>(usage: Option<Arc<Mutex<LlmUsage>>>, future: F) -> F::Output {
+    match usage {
+        Some(usage) => STAGE_USAGE.scope(usage, future).await,
+        None => future.await,
+    }
+}
+
+/// Tally of the running stage, None outside a pipeline stage
+pub fn current_usage() -> Option<Arc<Mutex<LlmUsage>>> {
+    STAGE_USAGE.try_with(Arc::clone).ok()
+}
 
 /// Call Large language model
 pub async fn call_gpt(messages: Vec<Message>) -> Result<String, Box<dyn std::error::Error + Send>> {
-    dotenv().ok();
-
-    let api_key: String = env::var("OPEN_AI_KEY").expect("OPEN_AI_KEY not found in enviornment variables");
-    let api_org: String = env::var("OPEN_AI_ORG").expect("OPEN_AI_ORG not found in enviornment variables");
+    call_gpt_with_temperature(messages, None).await
+}
 
-    let url: &str = "https://api.openai.com/v1/chat/completions";
+/// Call Large language model, sampling at `temperature` instead of the default 1.0 when given
+#[tracing::instrument(
+    name = "llm_call",
+    skip_all,
+    fields(model, latency_ms, prompt_tokens, completion_tokens, total_tokens)
+)]
+pub async fn call_gpt_with_temperature(
+    messages: Vec<Message>,
+    temperature: Option<f32>,
+) -> Result<String, Box<dyn std::error::Error + Send>> {
+    let provider: ProviderConfig = provider();
 
     let mut headers: HeaderMap = HeaderMap::new();
 
     headers.insert(
         "authorization",
-        HeaderValue::from_str(&format!("Bearer {}", api_key))
+        HeaderValue::from_str(&format!("Bearer {}", provider.api_key))
             .map_err(|e| -> Box<dyn std::error::Error + Send> { Box::new(e) })?,
     );
 
-    headers.insert(
-        "OpenAI-Organization",
-        HeaderValue::from_str(api_org.as_str())
-            .map_err(|e| -> Box<dyn std::error::Error + Send> { Box::new(e) })?,
-    );
+    if let Some(api_org) = &provider.organization {
+        headers.insert(
+            "OpenAI-Organization",
+            HeaderValue::from_str(api_org.as_str())
+                .map_err(|e| -> Box<dyn std::error::Error + Send> { Box::new(e) })?,
+        );
+    }
 
     let client: Client = Client::builder()
         .default_headers(headers)
@@ -33,14 +139,18 @@ pub async fn call_gpt(messages: Vec<Message>) -> Result<String, Box<dyn std::err
         .map_err(|e| -> Box<dyn std::error::Error + Send> { Box::new(e) })?;
 
     let chat_completion: ChatCompletion = ChatCompletion {
-        model: "o4-mini".to_string(),
+        model: current_model().unwrap_or(provider.model),
         messages,
-        temperature: 1.0,
+        temperature: temperature.unwrap_or(1.0),
     };
 
+    let span: Span = Span::current();
+    span.record("model", chat_completion.model.as_str());
+    let started_at: Instant = Instant::now();
+
     // Extract API Response
     let res: APIResponse = client
-        .post(url)
+        .post(&provider.url)
         .json(&chat_completion)
         .send()
         .await
@@ -49,6 +159,20 @@ pub async fn call_gpt(messages: Vec<Message>) -> Result<String, Box<dyn std::err
         .await
         .map_err(|e| -> Box<dyn std::error::Error + Send> { Box::new(e) })?;
 
+    span.record("latency_ms", started_at.elapsed().as_millis() as u64);
+    if let Some(usage) = res.usage {
+        span.record("prompt_tokens", usage.prompt_tokens);
+        span.record("completion_tokens", usage.completion_tokens);
+        span.record("total_tokens", usage.total_tokens);
+    }
+    if let Some(stage_usage) = current_usage() {
+        stage_usage
+            .lock()
+            .unwrap()
+            .record(&chat_completion.model, res.usage);
+    }
+    tracing::info!("LLM call completed");
+
     Ok(res.choices[0].message.content.clone())
 }
 
@@ -67,12 +191,56 @@ mod tests {
 
         let res: Result<String, Box<dyn std::error::Error + Send>> = call_gpt(messages).await;
 
-        if let Ok(res_str) = res {
-            dbg!(res_str);
-            assert!(true)
-        } else if let Err(reason) = res {
-            dbg!(reason.as_ref());
-            assert!(false)
+        match res {
+            Ok(res_str) => {
+                dbg!(res_str);
+            }
+            Err(reason) => {
+                dbg!(reason.as_ref());
+                panic!("Failed to call OpenAI");
+            }
         }
     }
+
+    #[tokio::test]
+    async fn tests_scopes_model_to_agent() {
+        assert_eq!(current_model(), None);
+
+        let model: Option<String> = with_model(Some("gpt-4o".to_string()), async {
+            with_model(None, async { current_model() }).await
+        })
+        .await;
+
+        assert_eq!(model.as_deref(), Some("gpt-4o"));
+        assert_eq!(current_model(), None);
+    }
+
+    #[tokio::test]
+    async fn tests_tallies_usage_of_the_running_stage() {
+        let usage: Arc<Mutex<LlmUsage>> = Arc::new(Mutex::new(LlmUsage::default()));
+
+        let scoped: Option<Arc<Mutex<LlmUsage>>> =
+            with_usage(Some(usage.clone()), async { with_usage(None, async { current_usage() }).await })
+                .await;
+
+        assert!(scoped.is_some_and(|scoped| Arc::ptr_eq(&scoped, &usage)));
+        assert!(current_usage().is_none());
+    }
+
+    #[tokio::test]
+    async fn tests_keeps_concurrent_projects_on_their_own_provider() {
+        let first = with_provider(Some(ProviderConfig::open_ai("first-key")), async {
+            tokio::task::yield_now().await;
+            current_provider().map(|provider| provider.api_key)
+        });
+        let second = with_provider(Some(ProviderConfig::open_ai("second-key")), async {
+            current_provider().map(|provider| provider.api_key)
+        });
+
+        let (first, second) = tokio::join!(first, second);
+
+        assert_eq!(first.as_deref(), Some("first-key"));
+        assert_eq!(second.as_deref(), Some("second-key"));
+        assert_eq!(current_provider(), None);
+    }
 }
\ No newline at end of file
@@ -0,0 +1,204 @@
+use crate::dashboard::state::DashboardState;
+use crate::helpers::telemetry::AGENT_MESSAGE_TARGET;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::sync::{Arc, Mutex};
+use tracing::field::{Field, Visit};
+use tracing::span::{Attributes, Id, Record};
+use tracing::{Event, Subscriber};
+use tracing_subscriber::layer::{Context, Layer};
+use tracing_subscriber::registry::LookupSpan;
+
+/// Field values of a span, kept in its extensions so they can be read when it closes
+#[derive(Debug, Default)]
+struct FieldValues(BTreeMap<&'static str, String>);
+
+impl FieldValues {
+    fn get(&self, name: &str) -> Option<&str> {
+        self.0.get(name).map(String::as_str)
+    }
+
+    fn number(&self, name: &str) -> Option<u64> {
+        self.get(name).and_then(|value| value.parse().ok())
+    }
+}
+
+impl Visit for FieldValues {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        self.0.insert(field.name(), value.to_string());
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        let value: String = format!("{:?}", value);
+        self.0
+            .insert(field.name(), value.trim_matches('"').to_string());
+    }
+}
+
+/// Feeds agent messages, state transitions, token usage and build/test results into the dashboard
+pub struct DashboardLayer {
+    state: Arc<Mutex<DashboardState>>,
+}
+
+impl DashboardLayer {
+    pub fn new(state: Arc<Mutex<DashboardState>>) -> Self {
+        Self { state }
+    }
+
+    /// Agent named on the span or the closest parent span that has one
+    fn span_agent<S>(id: &Id, ctx: &Context<'_, S>) -> Option<String>
+    where
+        S: Subscriber + for<'a> LookupSpan<'a>,
+    {
+        ctx.span(id)?.scope().find_map(|span| {
+            span.extensions()
+                .get::<FieldValues>()
+                .and_then(|values| values.get("agent").map(str::to_string))
+        })
+    }
+}
+
+impl<S> Layer<S> for DashboardLayer
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    fn on_new_span(&self, attrs: &Attributes<'_>, id: &Id, ctx: Context<'_, S>) {
+        let mut values: FieldValues = FieldValues::default();
+        attrs.record(&mut values);
+        if let Some(span) = ctx.span(id) {
+            span.extensions_mut().insert(values);
+        }
+    }
+
+    fn on_record(&self, id: &Id, record: &Record<'_>, ctx: Context<'_, S>) {
+        if let Some(span) = ctx.span(id)
+            && let Some(values) = span.extensions_mut().get_mut::<FieldValues>()
+        {
+            record.record(values);
+        }
+    }
+
+    fn on_enter(&self, id: &Id, ctx: Context<'_, S>) {
+        let Some(span) = ctx.span(id) else {
+            return;
+        };
+        if span.name() != "ai_task_request" {
+            return;
+        }
+
+        let extensions = span.extensions();
+        let Some(values) = extensions.get::<FieldValues>() else {
+            return;
+        };
+        if let (Some(agent), Some(operation)) = (values.get("agent"), values.get("agent_operation"))
+        {
+            self.state.lock().unwrap().pane_mut(agent).operation = Some(operation.to_string());
+        }
+    }
+
+    fn on_event(&self, event: &Event<'_>, ctx: Context<'_, S>) {
+        let mut values: FieldValues = FieldValues::default();
+        event.record(&mut values);
+
+        let agent: Option<String> = values.get("agent").map(str::to_string).or_else(|| {
+            ctx.lookup_current()
+                .and_then(|span| Self::span_agent(&span.id(), &ctx))
+        });
+        let Some(agent) = agent else {
+            return;
+        };
+
+        let mut state = self.state.lock().unwrap();
+        let pane = state.pane_mut(&agent);
+        if event.metadata().target() == AGENT_MESSAGE_TARGET {
+            pane.push_log(
+                values.get("kind").unwrap_or_default(),
+                values.get("message").unwrap_or_default(),
+            );
+        } else if values.get("message") == Some("State transition")
+            && let Some(new_state) = values.get("to")
+        {
+            pane.state = new_state.to_string();
+        }
+    }
+
+    fn on_close(&self, id: Id, ctx: Context<'_, S>) {
+        let Some(span) = ctx.span(&id) else {
+            return;
+        };
+        let Some(agent) = Self::span_agent(&id, &ctx) else {
+            return;
+        };
+
+        let extensions = span.extensions();
+        let Some(values) = extensions.get::<FieldValues>() else {
+            return;
+        };
+
+        let mut state = self.state.lock().unwrap();
+        let pane = state.pane_mut(&agent);
+        match span.name() {
+            "ai_task_request" => pane.operation = None,
+            "llm_call" => pane.tokens += values.number("total_tokens").unwrap_or_default(),
+            "build_project" => {
+                pane.build_errors = values.number("error_count").map(|count| count as usize)
+            }
+            "run_endpoint_tests" => {
+                pane.endpoints_passed = values.number("passed").map(|count| count as usize);
+                pane.endpoints_failed = values.number("failed").map(|count| count as usize);
+            }
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::helpers::command_line::PrintCommand;
+    use tracing_subscriber::layer::SubscriberExt;
+
+    #[test]
+    fn tests_collects_agent_progress() {
+        let state: Arc<Mutex<DashboardState>> = Arc::new(Mutex::new(DashboardState::default()));
+        let subscriber =
+            tracing_subscriber::registry().with(DashboardLayer::new(Arc::clone(&state)));
+
+        tracing::subscriber::with_default(subscriber, || {
+            let _execution =
+                tracing::info_span!("agent_execution", agent = "Backend Developer").entered();
+            tracing::info!(agent = "Backend Developer", from = ?"Discovery", to = ?"Working", "State transition");
+
+            {
+                let _request = tracing::info_span!(
+                    "ai_task_request",
+                    agent = "Backend Developer",
+                    agent_operation = ?"print_fixed_code"
+                )
+                .entered();
+                assert_eq!(
+                    state.lock().unwrap().panes[0].operation.as_deref(),
+                    Some("print_fixed_code")
+                );
+
+                let llm_call = tracing::info_span!("llm_call", total_tokens = tracing::field::Empty);
+                llm_call.record("total_tokens", 1200);
+                drop(llm_call.entered());
+            }
+
+            let build = tracing::info_span!("build_project", error_count = 3);
+            drop(build.entered());
+            PrintCommand::Issue.print_agent_message("Backend Developer", "Build failed");
+        });
+
+        let state = state.lock().unwrap();
+        let pane = &state.panes[0];
+        assert_eq!(pane.agent, "Backend Developer");
+        assert_eq!(pane.state, "Working");
+        assert_eq!(pane.operation, None);
+        assert_eq!(pane.tokens, 1200);
+        assert_eq!(pane.build_errors, Some(3));
+        assert_eq!(pane.log.back().unwrap().text, "Build failed");
+        assert_eq!(pane.log.back().unwrap().kind, "Issue");
+    }
+}
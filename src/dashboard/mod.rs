@@ -0,0 +1,186 @@
+pub mod layer;
+pub mod state;
+pub mod view;
+
+use crate::helpers::command_line::{edit_in_editor, ReviewOutcome};
+use crate::helpers::general::EXEC_MAIN_PATH;
+use crossterm::event::{self, Event, KeyCode, KeyEventKind};
+use ratatui::DefaultTerminal;
+use state::{DashboardState, PendingApproval, PendingReview};
+use std::fs;
+use std::future::Future;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc;
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+use std::time::Duration;
+use tokio::sync::Notify;
+
+tokio::task_local! {
+    /// State of the dashboard the running project asks for approvals
+    static ACTIVE_DASHBOARD: Arc<Mutex<DashboardState>>;
+}
+
+/// Sends the approvals asked by the future to this dashboard, keeping the current one when None
+pub async fn with_dashboard<F: Future>(
+    state: Option<Arc<Mutex<DashboardState>>>,
+    future: F,
+) -> F::Output {
+    match state {
+        Some(state) => ACTIVE_DASHBOARD.scope(state, future).await,
+        None => future.await,
+    }
+}
+
+/// Dashboard of the running project, None when approvals go to the console
+pub fn current_dashboard() -> Option<Arc<Mutex<DashboardState>>> {
+    ACTIVE_DASHBOARD.try_with(Arc::clone).ok()
+}
+
+/// Full-screen view of the agents, drawn on its own thread until stopped
+pub struct Dashboard {
+    stop: Arc<AtomicBool>,
+    handle: JoinHandle<()>,
+}
+
+impl Dashboard {
+    /// Takes over the terminal and starts redrawing from the shared state
+    ///
+    /// Pressing `q` refuses the pending approval and notifies `quit`, the signal that stops the project.
+    pub fn start(state: Arc<Mutex<DashboardState>>, quit: Arc<Notify>) -> Self {
+        let stop: Arc<AtomicBool> = Arc::new(AtomicBool::new(false));
+        let thread_stop: Arc<AtomicBool> = Arc::clone(&stop);
+        let handle: JoinHandle<()> = std::thread::spawn(move || run(state, thread_stop, quit));
+        Self { stop, handle }
+    }
+
+    /// Stops redrawing and gives the terminal back
+    pub fn stop(self) {
+        self.stop.store(true, Ordering::Relaxed);
+        let _ = self.handle.join();
+    }
+}
+
+/// Asks for approval in the dashboard, None when the dashboard is not running
+pub fn request_approval(prompt: &str) -> Option<bool> {
+    ask(prompt, None).map(|outcome| outcome.approved)
+}
+
+/// Asks the user to review code in the dashboard, None when the dashboard is not running
+pub fn request_review(approved_code: &str, code: &str) -> Option<ReviewOutcome> {
+    let review: PendingReview = PendingReview {
+        approved_code: approved_code.to_string(),
+        code: code.to_string(),
+    };
+    ask(
+        "WARNING: You are about to run code written entirely by AI. \
+         Review the changes on the right and confirm you wish to continue.",
+        Some(review),
+    )
+}
+
+fn ask(prompt: &str, review: Option<PendingReview>) -> Option<ReviewOutcome> {
+    let state: Arc<Mutex<DashboardState>> = current_dashboard()?;
+    let (reply, answer) = mpsc::channel();
+    state.lock().unwrap().pending_approval = Some(PendingApproval::new(prompt, review, reply));
+    Some(answer.recv().unwrap_or_default())
+}
+
+fn run(state: Arc<Mutex<DashboardState>>, stop: Arc<AtomicBool>, quit: Arc<Notify>) {
+    let mut terminal: DefaultTerminal = ratatui::init();
+
+    while !stop.load(Ordering::Relaxed) {
+        let code: String = fs::read_to_string(EXEC_MAIN_PATH).unwrap_or_default();
+        let drawn = terminal.draw(|frame| view::render(frame, &state.lock().unwrap(), &code));
+        if drawn.is_err() {
+            break;
+        }
+
+        if !event::poll(Duration::from_millis(200)).unwrap_or(false) {
+            continue;
+        }
+        let Ok(Event::Key(key)) = event::read() else {
+            continue;
+        };
+        if key.kind != KeyEventKind::Press {
+            continue;
+        }
+
+        let mut dashboard_state = state.lock().unwrap();
+        let Some(pending_approval) = dashboard_state.pending_approval.as_mut() else {
+            if handle_navigation(&mut dashboard_state, key.code) {
+                quit.notify_one();
+                break;
+            }
+            continue;
+        };
+
+        // Typing an instruction for the next iteration
+        if let Some(instruction) = pending_approval.typing_instruction.as_mut() {
+            match key.code {
+                KeyCode::Char(c) => instruction.push(c),
+                KeyCode::Backspace => {
+                    instruction.pop();
+                }
+                KeyCode::Enter => {
+                    let instruction: String = instruction.trim().to_string();
+                    pending_approval.outcome.instruction =
+                        (!instruction.is_empty()).then_some(instruction);
+                    pending_approval.typing_instruction = None;
+                }
+                KeyCode::Esc => pending_approval.typing_instruction = None,
+                _ => {}
+            }
+            continue;
+        }
+
+        match (key.code, pending_approval.current_code().map(str::to_string)) {
+            (KeyCode::Char('y') | KeyCode::Char('1'), _) => dashboard_state.answer_approval(true),
+            (KeyCode::Char('n') | KeyCode::Char('2'), _) => dashboard_state.answer_approval(false),
+            (KeyCode::Char('i'), Some(_)) => {
+                pending_approval.typing_instruction =
+                    Some(pending_approval.outcome.instruction.clone().unwrap_or_default());
+            }
+            (KeyCode::Char('e'), Some(reviewed_code)) => {
+                // Hand the terminal to the editor without blocking the agents' logging
+                drop(dashboard_state);
+                ratatui::restore();
+                let edited = edit_in_editor(&reviewed_code);
+                terminal = ratatui::init();
+
+                if let (Ok(edited_code), Some(pending_approval)) =
+                    (edited, state.lock().unwrap().pending_approval.as_mut())
+                {
+                    pending_approval.set_edited_code(edited_code);
+                }
+            }
+            (key_code, _) => {
+                if handle_navigation(&mut dashboard_state, key_code) {
+                    // Unblocks the agent waiting on the approval before the project stops
+                    dashboard_state.answer_approval(false);
+                    quit.notify_one();
+                    break;
+                }
+            }
+        }
+    }
+
+    ratatui::restore();
+}
+
+/// Scrolls the code view, returning true on `q`
+fn handle_navigation(dashboard_state: &mut DashboardState, key_code: KeyCode) -> bool {
+    match key_code {
+        KeyCode::Down => dashboard_state.code_scroll = dashboard_state.code_scroll.saturating_add(1),
+        KeyCode::Up => dashboard_state.code_scroll = dashboard_state.code_scroll.saturating_sub(1),
+        KeyCode::PageDown => {
+            dashboard_state.code_scroll = dashboard_state.code_scroll.saturating_add(20)
+        }
+        KeyCode::PageUp => {
+            dashboard_state.code_scroll = dashboard_state.code_scroll.saturating_sub(20)
+        }
+        KeyCode::Char('q') => return true,
+        _ => {}
+    }
+    false
+}
@@ -0,0 +1,126 @@
+use crate::helpers::command_line::ReviewOutcome;
+use std::collections::VecDeque;
+use std::sync::mpsc::Sender;
+
+/// Log lines kept per agent pane
+const MAX_LOG_LINES: usize = 200;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct LogLine {
+    pub kind: String,
+    pub text: String,
+}
+
+/// Everything the dashboard shows about one agent
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct AgentPane {
+    pub agent: String,
+    pub state: String,
+    /// AI function the agent is currently waiting on
+    pub operation: Option<String>,
+    pub log: VecDeque<LogLine>,
+    pub tokens: u64,
+    pub build_errors: Option<usize>,
+    pub endpoints_passed: Option<usize>,
+    pub endpoints_failed: Option<usize>,
+}
+
+impl AgentPane {
+    fn new(agent: &str) -> Self {
+        Self {
+            agent: agent.to_string(),
+            state: "Discovery".to_string(),
+            ..Self::default()
+        }
+    }
+
+    pub fn push_log(&mut self, kind: &str, text: &str) {
+        if self.log.len() == MAX_LOG_LINES {
+            self.log.pop_front();
+        }
+        self.log.push_back(LogLine {
+            kind: kind.to_string(),
+            text: text.to_string(),
+        });
+    }
+}
+
+/// Code up for review next to the version the user last approved
+#[derive(Debug, Clone, PartialEq)]
+pub struct PendingReview {
+    pub approved_code: String,
+    pub code: String,
+}
+
+/// Approval the agents are blocked on until the user answers in the dashboard
+#[derive(Debug)]
+pub struct PendingApproval {
+    pub prompt: String,
+    /// Set when the approval comes with code to review and edit
+    pub review: Option<PendingReview>,
+    /// Edits and instruction collected so far
+    pub outcome: ReviewOutcome,
+    /// Instruction being typed, None when not typing
+    pub typing_instruction: Option<String>,
+    pub reply: Sender<ReviewOutcome>,
+}
+
+impl PendingApproval {
+    pub fn new(prompt: &str, review: Option<PendingReview>, reply: Sender<ReviewOutcome>) -> Self {
+        Self {
+            prompt: prompt.to_string(),
+            review,
+            outcome: ReviewOutcome::default(),
+            typing_instruction: None,
+            reply,
+        }
+    }
+
+    /// Code under review including the user's edits
+    pub fn current_code(&self) -> Option<&str> {
+        let review: &PendingReview = self.review.as_ref()?;
+        Some(self.outcome.edited_code.as_deref().unwrap_or(&review.code))
+    }
+
+    pub fn set_edited_code(&mut self, edited_code: String) {
+        let unchanged: bool = self
+            .review
+            .as_ref()
+            .is_some_and(|review| review.code == edited_code);
+        self.outcome.edited_code = (!unchanged).then_some(edited_code);
+    }
+}
+
+/// Shared between the tracing layer that fills it and the render thread
+#[derive(Debug, Default)]
+pub struct DashboardState {
+    /// Panes in the order the agents first showed up
+    pub panes: Vec<AgentPane>,
+    pub pending_approval: Option<PendingApproval>,
+    pub code_scroll: u16,
+}
+
+impl DashboardState {
+    pub fn pane_mut(&mut self, agent: &str) -> &mut AgentPane {
+        let index: usize = match self.panes.iter().position(|pane| pane.agent == agent) {
+            Some(index) => index,
+            None => {
+                self.panes.push(AgentPane::new(agent));
+                self.panes.len() - 1
+            }
+        };
+        &mut self.panes[index]
+    }
+
+    pub fn total_tokens(&self) -> u64 {
+        self.panes.iter().map(|pane| pane.tokens).sum()
+    }
+
+    /// Answers the pending approval with the edits and instruction collected so far
+    pub fn answer_approval(&mut self, approved: bool) {
+        if let Some(mut pending_approval) = self.pending_approval.take() {
+            pending_approval.outcome.approved = approved;
+            let _ = pending_approval.reply.send(pending_approval.outcome);
+        }
+    }
+}
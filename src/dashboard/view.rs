@@ -0,0 +1,228 @@
+use crate::dashboard::state::{AgentPane, DashboardState, PendingApproval};
+use crate::helpers::code_history::unified_diff;
+use ratatui::layout::{Constraint, Layout, Rect};
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{Block, Clear, Paragraph, Wrap};
+use ratatui::Frame;
+
+/// Draws the agent panes on the left and the generated code on the right
+pub fn render(frame: &mut Frame, state: &DashboardState, code: &str) {
+    let [body, footer] =
+        Layout::vertical([Constraint::Min(0), Constraint::Length(1)]).areas(frame.area());
+    let [agents_area, code_area] =
+        Layout::horizontal([Constraint::Percentage(45), Constraint::Percentage(55)]).areas(body);
+
+    if !state.panes.is_empty() {
+        let pane_areas = Layout::vertical(
+            state
+                .panes
+                .iter()
+                .map(|_| Constraint::Ratio(1, state.panes.len() as u32)),
+        )
+        .split(agents_area);
+        for (pane, area) in state.panes.iter().zip(pane_areas.iter()) {
+            render_agent_pane(frame, pane, *area);
+        }
+    }
+
+    let review_diff: Option<String> = state.pending_approval.as_ref().and_then(|pending| {
+        let review = pending.review.as_ref()?;
+        Some(unified_diff(
+            &review.approved_code,
+            pending.current_code()?,
+            "last-approved",
+            "current",
+        ))
+    });
+    let code_view: Paragraph = match &review_diff {
+        Some(diff) => Paragraph::new(diff_lines(diff)).block(
+            Block::bordered().title(" Changes since last approved version (↑/↓ PgUp/PgDn) "),
+        ),
+        None => Paragraph::new(code)
+            .block(Block::bordered().title(" Generated code (↑/↓ PgUp/PgDn) ")),
+    };
+    frame.render_widget(code_view.scroll((state.code_scroll, 0)), code_area);
+
+    let footer_text: String = format!(
+        " Total tokens: {}   q: quit",
+        state.total_tokens()
+    );
+    frame.render_widget(Paragraph::new(footer_text), footer);
+
+    if let Some(pending_approval) = &state.pending_approval {
+        render_approval(frame, pending_approval);
+    }
+}
+
+fn diff_lines(diff: &str) -> Vec<Line<'_>> {
+    if diff.is_empty() {
+        return vec![Line::from("No changes since the last approved version")];
+    }
+    diff.lines()
+        .map(|line| {
+            let color: Color = match line.chars().next() {
+                Some('+') => Color::Green,
+                Some('-') => Color::Red,
+                Some('@') => Color::Cyan,
+                _ => Color::Reset,
+            };
+            Line::styled(line, Style::new().fg(color))
+        })
+        .collect()
+}
+
+fn render_agent_pane(frame: &mut Frame, pane: &AgentPane, area: Rect) {
+    let mut stats: Vec<Span> = vec![
+        Span::styled(pane.state.as_str(), Style::new().fg(Color::Green)),
+        Span::raw(format!("   tokens: {}", pane.tokens)),
+    ];
+    if let Some(build_errors) = pane.build_errors {
+        let color: Color = if build_errors == 0 { Color::Green } else { Color::Red };
+        stats.push(Span::raw("   build errors: "));
+        stats.push(Span::styled(build_errors.to_string(), Style::new().fg(color)));
+    }
+    if let (Some(passed), Some(failed)) = (pane.endpoints_passed, pane.endpoints_failed) {
+        stats.push(Span::raw(format!("   endpoints: {} passed, {} failed", passed, failed)));
+    }
+
+    let mut lines: Vec<Line> = vec![
+        Line::from(stats),
+        Line::from(format!(
+            "Operation: {}",
+            pane.operation.as_deref().unwrap_or("-")
+        ))
+        .style(Style::new().fg(Color::Cyan)),
+    ];
+
+    // Show the newest log lines that fit below the stats
+    let visible: usize = (area.height as usize).saturating_sub(4);
+    let skipped: usize = pane.log.len().saturating_sub(visible);
+    lines.extend(pane.log.iter().skip(skipped).map(|log_line| {
+        let color: Color = match log_line.kind.as_str() {
+            "Issue" => Color::Red,
+            "UnitTest" => Color::Magenta,
+            _ => Color::Cyan,
+        };
+        Line::styled(log_line.text.as_str(), Style::new().fg(color))
+    }));
+
+    let title: Line = Line::styled(
+        format!(" {} ", pane.agent),
+        Style::new().add_modifier(Modifier::BOLD),
+    );
+    frame.render_widget(Paragraph::new(lines).block(Block::bordered().title(title)), area);
+}
+
+fn render_approval(frame: &mut Frame, pending_approval: &PendingApproval) {
+    let [_, row, _] = Layout::vertical([
+        Constraint::Fill(1),
+        Constraint::Length(10),
+        Constraint::Fill(1),
+    ])
+    .areas(frame.area());
+    let [_, area, _] = Layout::horizontal([
+        Constraint::Fill(1),
+        Constraint::Percentage(60),
+        Constraint::Fill(1),
+    ])
+    .areas(row);
+
+    let mut lines: Vec<Line> = vec![
+        Line::from(pending_approval.prompt.as_str()),
+        Line::default(),
+        Line::from(vec![
+            Span::styled("[y] All good", Style::new().fg(Color::Green)),
+            Span::raw("    "),
+            Span::styled("[n] Lets stop this project", Style::new().fg(Color::Red)),
+        ]),
+    ];
+    if pending_approval.review.is_some() {
+        lines.push(Line::styled(
+            "[e] Edit in $EDITOR    [i] Add instruction for the next iteration",
+            Style::new().fg(Color::Yellow),
+        ));
+    }
+    if pending_approval.outcome.edited_code.is_some() {
+        lines.push(Line::from("Manual edits will be kept"));
+    }
+    match (
+        &pending_approval.typing_instruction,
+        &pending_approval.outcome.instruction,
+    ) {
+        (Some(typing), _) => lines.push(Line::from(format!(
+            "Instruction (Enter to save, Esc to cancel): {}_",
+            typing
+        ))),
+        (None, Some(instruction)) => lines.push(Line::from(format!("Instruction: {}", instruction))),
+        (None, None) => {}
+    }
+    let approval: Paragraph = Paragraph::new(lines)
+        .wrap(Wrap { trim: true })
+        .block(
+            Block::bordered()
+                .title(" Approval needed ")
+                .border_style(Style::new().fg(Color::Yellow)),
+        );
+    frame.render_widget(Clear, area);
+    frame.render_widget(approval, area);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dashboard::state::PendingReview;
+    use ratatui::backend::TestBackend;
+    use ratatui::Terminal;
+    use std::sync::mpsc;
+
+    #[test]
+    fn tests_renders_panes_code_and_approval() {
+        let mut state: DashboardState = DashboardState::default();
+        let pane: &mut AgentPane = state.pane_mut("Backend Developer");
+        pane.state = "UnitTesting".to_string();
+        pane.tokens = 1500;
+        pane.build_errors = Some(2);
+        pane.push_log("Issue", "Build failed");
+
+        let (reply, _answers) = mpsc::channel();
+        let mut pending_approval: PendingApproval = PendingApproval::new(
+            "Run the generated code?",
+            Some(PendingReview {
+                approved_code: "fn main() {}\n".to_string(),
+                code: "fn main() {}\n".to_string(),
+            }),
+            reply,
+        );
+        pending_approval.set_edited_code("fn main() {\n    serve();\n}\n".to_string());
+        pending_approval.outcome.instruction = Some("Add a health route".to_string());
+        state.pending_approval = Some(pending_approval);
+
+        let mut terminal: Terminal<TestBackend> = Terminal::new(TestBackend::new(120, 30)).unwrap();
+        terminal
+            .draw(|frame| render(frame, &state, "fn main() {}"))
+            .unwrap();
+
+        let screen: String = terminal
+            .backend()
+            .buffer()
+            .content()
+            .iter()
+            .map(|cell| cell.symbol())
+            .collect();
+        for expected in [
+            "Backend Developer",
+            "UnitTesting",
+            "build errors: 2",
+            "Build failed",
+            "+    serve();",
+            "Total tokens: 1500",
+            "Run the generated code?",
+            "[e] Edit in $EDITOR",
+            "Manual edits will be kept",
+            "Instruction: Add a health route",
+        ] {
+            assert!(screen.contains(expected), "missing '{}'", expected);
+        }
+    }
+}
@@ -0,0 +1,221 @@
+use crate::apis::call_request::ProviderConfig;
+use crate::helpers::template_registry::{TemplateRegistry, TemplateTag};
+use crate::models::general::framework::TargetFramework;
+use axum::http::StatusCode;
+use axum::routing::post;
+use axum::{Json, Router};
+use serde_json::{Value, json};
+use std::str::FromStr;
+use tokio::net::TcpListener;
+use tokio::task::JoinHandle;
+
+/// Model name the mock reports, no price is known for it
+pub const MOCK_MODEL: &str = "mock";
+
+/// Tags of the built-in template the code-writing AI functions answer with
+const MOCK_TEMPLATE_TAGS: [TemplateTag; 2] = [TemplateTag::Crud, TemplateTag::Auth];
+
+const MOCK_PROJECT_DESCRIPTION: &str = "Build a JSON REST API to create, read, update and delete tasks, with user registration and login.";
+
+/// Local chat completions server answering every AI function with a fixed, valid response
+///
+/// Runs the whole pipeline without an API key, so the eval harness itself can be tested
+/// and timed. The answers ignore the project description.
+#[derive(Debug)]
+pub struct MockProvider {
+    pub url: String,
+    handle: JoinHandle<()>,
+}
+
+impl MockProvider {
+    pub async fn start() -> std::io::Result<Self> {
+        let listener: TcpListener = TcpListener::bind("127.0.0.1:0").await?;
+        let url: String = format!("http://{}/v1/chat/completions", listener.local_addr()?);
+        let router: Router = Router::new().route("/v1/chat/completions", post(complete));
+
+        let handle: JoinHandle<()> = tokio::spawn(async move {
+            let _ = axum::serve(listener, router).await;
+        });
+        Ok(Self { url, handle })
+    }
+
+    /// Provider sending every LLM call to the mock
+    pub fn config(&self) -> ProviderConfig {
+        let mut provider: ProviderConfig = ProviderConfig::open_ai("mock-key");
+        provider.url = self.url.clone();
+        provider.model = MOCK_MODEL.to_string();
+        provider
+    }
+
+    pub fn shutdown(self) {
+        self.handle.abort();
+    }
+}
+
+async fn complete(Json(request): Json<Value>) -> Result<Json<Value>, (StatusCode, String)> {
+    let prompt: String = request["messages"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|message| message["content"].as_str())
+        .collect::<Vec<&str>>()
+        .join("\n");
+    let function_name: &str = ai_function_name(&prompt).ok_or((
+        StatusCode::BAD_REQUEST,
+        "No AI function found in the prompt".to_string(),
+    ))?;
+    let content: String = mock_response(function_name, &prompt).ok_or_else(|| {
+        (
+            StatusCode::BAD_REQUEST,
+            format!("The mock provider has no answer for {}", function_name),
+        )
+    })?;
+
+    // Roughly four characters per token, enough to compare runs against each other
+    let prompt_tokens: usize = prompt.len() / 4;
+    let completion_tokens: usize = content.len() / 4;
+    Ok(Json(json!({
+        "choices": [{ "message": { "content": content } }],
+        "usage": {
+            "prompt_tokens": prompt_tokens,
+            "completion_tokens": completion_tokens,
+            "total_tokens": prompt_tokens + completion_tokens
+        }
+    })))
+}
+
+/// Name of the first function in the prompt, the AI function being called
+fn ai_function_name(prompt: &str) -> Option<&str> {
+    // Long signatures are wrapped, so the name may follow `fn` on the next line
+    let mut words = prompt.split_whitespace();
+    words.find(|word| *word == "fn")?;
+    let name: &str = words
+        .next()?
+        .split(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+        .next()?;
+    (!name.is_empty()).then_some(name)
+}
+
+/// Full built-in template of the framework named in the prompt, TARGET_FRAMEWORK when it names none
+fn mock_backend_code(prompt: &str) -> Option<String> {
+    let framework: TargetFramework = prompt
+        .lines()
+        .find_map(|line| line.split_once("TARGET_FRAMEWORK:"))
+        .and_then(|(_, name)| TargetFramework::from_str(name.trim()).ok())
+        .unwrap_or_else(TargetFramework::from_env);
+    TemplateRegistry::with_directories(&[])
+        .ok()?
+        .select(framework, &MOCK_TEMPLATE_TAGS)
+        .map(|selection| selection.primary.contents)
+}
+
+fn mock_response(function_name: &str, prompt: &str) -> Option<String> {
+    let response: Value = match function_name {
+        "convert_user_input_to_goal" => return Some(MOCK_PROJECT_DESCRIPTION.to_string()),
+        "print_backend_webserver_code"
+        | "print_improved_webserver_code"
+        | "print_fixed_code"
+        | "print_lint_fixed_code" => return mock_backend_code(prompt),
+        "print_clarified_goal" => json!({
+            "project_description": MOCK_PROJECT_DESCRIPTION,
+            "requirements": []
+        }),
+        "print_project_scope" => json!({
+            "is_crud_required": true,
+            "is_user_login_and_logout": true,
+            "is_external_urls_required": false
+        }),
+        "print_clarifying_questions"
+        | "print_non_functional_requirements"
+        | "print_site_urls"
+        | "print_crate_requests" => json!([]),
+        "print_rest_api_endpoints" => json!([
+            { "route": "/task", "method": "get", "status_codes": [200] },
+            { "route": "/task/{id}", "method": "get", "status_codes": [200] },
+            { "route": "/task", "method": "post", "status_codes": [200] },
+            { "route": "/task/", "method": "put", "status_codes": [200] },
+            { "route": "/task/{id}", "method": "delete", "status_codes": [200] },
+            { "route": "/register", "method": "post", "status_codes": [200] },
+            { "route": "/login", "method": "post", "status_codes": [200, 400] }
+        ]),
+        _ => return None,
+    };
+    Some(response.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::agents::agent_traits::{ProjectScope, RouteObject};
+
+    async fn call_mock(provider: &MockProvider, prompt: &str) -> reqwest::Response {
+        reqwest::Client::new()
+            .post(&provider.url)
+            .json(&json!({
+                "model": MOCK_MODEL,
+                "messages": [{ "role": "system", "content": prompt }],
+                "temperature": 1.0
+            }))
+            .send()
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn tests_answers_ai_functions() {
+        let provider: MockProvider = MockProvider::start().await.unwrap();
+        assert_eq!(provider.config().model, MOCK_MODEL);
+
+        let response: Value = call_mock(
+            &provider,
+            "FUNCTION: pub fn print_project_scope (_project_description : & str) {}",
+        )
+        .await
+        .json()
+        .await
+        .unwrap();
+        let content: &str = response["choices"][0]["message"]["content"]
+            .as_str()
+            .unwrap();
+        let scope: ProjectScope = serde_json::from_str(content).unwrap();
+        assert!(scope.is_crud_required && !scope.is_external_urls_required);
+        assert!(response["usage"]["total_tokens"].as_u64().unwrap() > 0);
+
+        let response: Value = call_mock(
+            &provider,
+            "FUNCTION: pub fn print_rest_api_endpoints(code: &str)",
+        )
+        .await
+        .json()
+        .await
+        .unwrap();
+        let routes: Vec<RouteObject> = RouteObject::parse_list(
+            response["choices"][0]["message"]["content"]
+                .as_str()
+                .unwrap(),
+        )
+        .unwrap();
+        assert_eq!(
+            routes
+                .iter()
+                .filter(|route| route.is_testable_without_input())
+                .count(),
+            1
+        );
+
+        // Code answers use the template of the framework named in the prompt
+        for (framework, crate_path) in [("actix-web", "actix_web::"), ("axum", "axum::"), ("rocket", "rocket::")] {
+            let prompt: String = format!(
+                "FUNCTION: pub fn\nprint_fixed_code(framework: &str)\n  CONTEXT: FRAMEWORK: TARGET_FRAMEWORK: {}",
+                framework
+            );
+            let response: Value = call_mock(&provider, &prompt).await.json().await.unwrap();
+            let code: &str = response["choices"][0]["message"]["content"].as_str().unwrap();
+            assert!(code.contains(crate_path), "{} answered with another framework", framework);
+        }
+
+        let unknown = call_mock(&provider, "FUNCTION: pub fn print_poem()").await;
+        assert_eq!(unknown.status(), StatusCode::BAD_REQUEST);
+        provider.shutdown();
+    }
+}
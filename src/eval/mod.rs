@@ -0,0 +1,519 @@
+pub mod mock_provider;
+
+use crate::apis::call_request::with_usage;
+use crate::eval::mock_provider::MockProvider;
+use crate::models::agents_manager::pipeline::PipelineDefinition;
+use crate::models::general::framework::TargetFramework;
+use crate::models::general::llm::LlmUsage;
+use crate::models::general::settings::ApprovalPolicy;
+use crate::project::{ProjectBuilder, ProjectResult};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+
+pub const DEFAULT_SUITE_PATH: &str = "./eval/suite.toml";
+
+pub const DEFAULT_BASELINE_PATH: &str = "./eval/baseline.json";
+
+pub const DEFAULT_RESULTS_PATH: &str = "./runs/eval_results.json";
+
+/// Relative change a metric may get worse by before it counts as a regression
+pub const DEFAULT_REGRESSION_THRESHOLD: f64 = 0.1;
+
+/// Project description run through the pipeline by `eval`
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct EvalCase {
+    pub name: String,
+    pub request: String,
+    /// TARGET_FRAMEWORK when missing
+    #[serde(default)]
+    pub framework: Option<TargetFramework>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct EvalSuite {
+    pub cases: Vec<EvalCase>,
+}
+
+impl EvalSuite {
+    pub fn parse(text: &str) -> Result<Self, toml::de::Error> {
+        toml::from_str(text)
+    }
+
+    pub fn from_file(path: &Path) -> Result<Self, String> {
+        let text: String = fs::read_to_string(path)
+            .map_err(|e| format!("Failed to read eval suite {}: {}", path.display(), e))?;
+        Self::parse(&text)
+            .map_err(|e| format!("Failed to parse eval suite {}: {}", path.display(), e))
+    }
+}
+
+/// What one case measured
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Default)]
+pub struct CaseMetrics {
+    pub name: String,
+    /// Every stage finished without issues
+    pub succeeded: bool,
+    /// The first version of the backend code sent to the compiler built without errors
+    pub compiled_first_try: bool,
+    /// Versions written to fix build errors
+    pub fix_iterations: usize,
+    /// Share of the testable endpoints answering 200, 0 when the run never got that far
+    pub endpoint_pass_rate: f64,
+    /// Lints left on the final code
+    pub lint_count: usize,
+    pub tokens: u64,
+    pub duration_ms: u64,
+    /// Why the run stopped early
+    #[serde(default)]
+    pub error: Option<String>,
+}
+
+impl CaseMetrics {
+    fn from_result(name: &str, result: &ProjectResult, tokens: u64, duration_ms: u64) -> Self {
+        let iterations = result
+            .report
+            .stages
+            .iter()
+            .flat_map(|stage| &stage.iterations);
+        Self {
+            name: name.to_string(),
+            succeeded: result.report.succeeded(),
+            compiled_first_try: iterations
+                .clone()
+                .find_map(|iteration| iteration.compiled)
+                .unwrap_or(false),
+            fix_iterations: iterations
+                .filter(|iteration| iteration.label.starts_with("fix-"))
+                .count(),
+            endpoint_pass_rate: result
+                .factsheet
+                .endpoint_test_report
+                .as_ref()
+                .map_or(0.0, |report| f64::from(report.pass_rate())),
+            lint_count: result
+                .factsheet
+                .lint_counts
+                .as_ref()
+                .map_or(0, |lint_counts| lint_counts.total()),
+            tokens,
+            duration_ms,
+            error: None,
+        }
+    }
+
+    fn failed(name: &str, error: String, duration_ms: u64) -> Self {
+        Self {
+            name: name.to_string(),
+            duration_ms,
+            error: Some(error),
+            ..Self::default()
+        }
+    }
+}
+
+/// Averages over every case of a suite, compared against the baseline
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Default)]
+pub struct EvalSummary {
+    pub cases: usize,
+    pub success_rate: f64,
+    pub compile_first_try_rate: f64,
+    pub mean_fix_iterations: f64,
+    pub mean_endpoint_pass_rate: f64,
+    pub mean_lint_count: f64,
+    pub mean_tokens: f64,
+    pub mean_duration_ms: f64,
+}
+
+impl EvalSummary {
+    pub fn from_cases(cases: &[CaseMetrics]) -> Self {
+        let count: f64 = cases.len().max(1) as f64;
+        let mean = |metric: fn(&CaseMetrics) -> f64| cases.iter().map(metric).sum::<f64>() / count;
+        Self {
+            cases: cases.len(),
+            success_rate: mean(|case| f64::from(u8::from(case.succeeded))),
+            compile_first_try_rate: mean(|case| f64::from(u8::from(case.compiled_first_try))),
+            mean_fix_iterations: mean(|case| case.fix_iterations as f64),
+            mean_endpoint_pass_rate: mean(|case| case.endpoint_pass_rate),
+            mean_lint_count: mean(|case| case.lint_count as f64),
+            mean_tokens: mean(|case| case.tokens as f64),
+            mean_duration_ms: mean(|case| case.duration_ms as f64),
+        }
+    }
+
+    /// Every compared metric with whether a higher value is better
+    fn metrics(&self) -> [(&'static str, f64, bool); 7] {
+        [
+            ("success_rate", self.success_rate, true),
+            ("compile_first_try_rate", self.compile_first_try_rate, true),
+            ("mean_fix_iterations", self.mean_fix_iterations, false),
+            (
+                "mean_endpoint_pass_rate",
+                self.mean_endpoint_pass_rate,
+                true,
+            ),
+            ("mean_lint_count", self.mean_lint_count, false),
+            ("mean_tokens", self.mean_tokens, false),
+            ("mean_duration_ms", self.mean_duration_ms, false),
+        ]
+    }
+}
+
+/// Metric that got worse than the baseline by more than the threshold
+#[derive(Debug, Clone, PartialEq)]
+pub struct Regression {
+    pub metric: &'static str,
+    pub baseline: f64,
+    pub current: f64,
+}
+
+/// Metrics worse than the baseline by more than `threshold` times the baseline value
+///
+/// Values below 1, such as rates, are compared against `threshold` itself, so a rate
+/// dropping from 0.9 to 0.75 regresses at a threshold of 0.1.
+pub fn find_regressions(
+    baseline: &EvalSummary,
+    current: &EvalSummary,
+    threshold: f64,
+) -> Vec<Regression> {
+    baseline
+        .metrics()
+        .into_iter()
+        .zip(current.metrics())
+        .filter_map(|((metric, baseline, higher_is_better), (_, current, _))| {
+            let worse_by: f64 = if higher_is_better {
+                baseline - current
+            } else {
+                current - baseline
+            };
+            (worse_by > threshold * baseline.abs().max(1.0)).then_some(Regression {
+                metric,
+                baseline,
+                current,
+            })
+        })
+        .collect()
+}
+
+/// Results of one `eval` run, also the format of the baseline file
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct EvalResults {
+    pub summary: EvalSummary,
+    pub cases: Vec<CaseMetrics>,
+}
+
+impl EvalResults {
+    pub fn save(&self, path: &Path) -> std::io::Result<()> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let results_str: String =
+            serde_json::to_string_pretty(self).expect("Failed to serialize eval results");
+        fs::write(path, results_str)
+    }
+
+    /// Reads saved results, None when the file does not exist
+    pub fn load(path: &Path) -> Result<Option<Self>, String> {
+        if !path.exists() {
+            return Ok(None);
+        }
+        let text: String = fs::read_to_string(path)
+            .map_err(|e| format!("Failed to read baseline {}: {}", path.display(), e))?;
+        serde_json::from_str(&text)
+            .map(Some)
+            .map_err(|e| format!("Failed to parse baseline {}: {}", path.display(), e))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct EvalSettings {
+    pub suite: PathBuf,
+    /// Answer every LLM call with the mock provider instead of OPEN_AI_KEY
+    pub mock: bool,
+    pub baseline: PathBuf,
+    pub threshold: f64,
+    /// Overwrite the baseline with this run's results
+    pub save_baseline: bool,
+    pub output: PathBuf,
+}
+
+impl Default for EvalSettings {
+    fn default() -> Self {
+        Self {
+            suite: PathBuf::from(DEFAULT_SUITE_PATH),
+            mock: false,
+            baseline: PathBuf::from(DEFAULT_BASELINE_PATH),
+            threshold: DEFAULT_REGRESSION_THRESHOLD,
+            save_baseline: false,
+            output: PathBuf::from(DEFAULT_RESULTS_PATH),
+        }
+    }
+}
+
+impl EvalSettings {
+    /// Reads `eval [suite] [--mock] [--baseline <path>] [--threshold <n>] [--save-baseline] [--output <path>]`
+    pub fn from_args(args: &[String]) -> Result<Self, String> {
+        let mut settings: EvalSettings = EvalSettings::default();
+        let mut arg_iter = args.iter().skip_while(|arg| *arg != "eval").skip(1);
+
+        while let Some(arg) = arg_iter.next() {
+            match arg.as_str() {
+                "--mock" => settings.mock = true,
+                "--save-baseline" => settings.save_baseline = true,
+                "--baseline" => {
+                    settings.baseline = arg_iter.next().ok_or("--baseline needs a path")?.into();
+                }
+                "--output" => {
+                    settings.output = arg_iter.next().ok_or("--output needs a path")?.into();
+                }
+                "--threshold" => {
+                    settings.threshold = arg_iter
+                        .next()
+                        .and_then(|threshold| threshold.parse().ok())
+                        .filter(|threshold: &f64| *threshold >= 0.0)
+                        .ok_or("--threshold needs a number of at least 0")?;
+                }
+                flag if flag.starts_with("--") => {
+                    return Err(format!("Unknown eval option {}", flag));
+                }
+                suite => settings.suite = suite.into(),
+            }
+        }
+        Ok(settings)
+    }
+}
+
+/// Runs every case of the suite, returning false when a metric regressed against the baseline
+pub async fn run_eval(settings: &EvalSettings) -> Result<bool, Box<dyn std::error::Error>> {
+    let suite: EvalSuite = EvalSuite::from_file(&settings.suite)?;
+    let mock: Option<MockProvider> = if settings.mock {
+        Some(MockProvider::start().await?)
+    } else {
+        None
+    };
+
+    // Nobody answers prompts during an eval
+    let mut pipeline: PipelineDefinition = PipelineDefinition::from_env()?;
+    pipeline.set_approval(ApprovalPolicy::Auto);
+
+    let mut cases: Vec<CaseMetrics> = vec![];
+    for case in &suite.cases {
+        println!("Eval case {}: {}", case.name, case.request);
+        let mut project: ProjectBuilder = ProjectBuilder::new(&case.request)
+            .answers(vec![])
+            .pipeline(pipeline.clone());
+        if let Some(framework) = case.framework {
+            project = project.framework(framework);
+        }
+        if let Some(mock) = &mock {
+            project = project.provider(mock.config());
+        }
+        let metrics: CaseMetrics = run_case(&case.name, project).await;
+        print_case(&metrics);
+        cases.push(metrics);
+    }
+    if let Some(mock) = mock {
+        mock.shutdown();
+    }
+
+    let results: EvalResults = EvalResults {
+        summary: EvalSummary::from_cases(&cases),
+        cases,
+    };
+    results.save(&settings.output)?;
+    println!("Eval results saved to {}", settings.output.display());
+
+    let baseline: Option<EvalResults> = EvalResults::load(&settings.baseline)?;
+    if settings.save_baseline {
+        results.save(&settings.baseline)?;
+        println!("Baseline saved to {}", settings.baseline.display());
+    }
+    let Some(baseline) = baseline else {
+        println!(
+            "No baseline at {}, run with --save-baseline to create one",
+            settings.baseline.display()
+        );
+        return Ok(true);
+    };
+
+    let regressions: Vec<Regression> =
+        find_regressions(&baseline.summary, &results.summary, settings.threshold);
+    for (metric, current, _) in results.summary.metrics() {
+        let regressed: bool = regressions
+            .iter()
+            .any(|regression| regression.metric == metric);
+        let baseline_value: f64 = baseline
+            .summary
+            .metrics()
+            .into_iter()
+            .find(|(name, _, _)| *name == metric)
+            .map_or(0.0, |(_, value, _)| value);
+        println!(
+            "{:<26} baseline {:>12.3}  current {:>12.3}{}",
+            metric,
+            baseline_value,
+            current,
+            if regressed { "  REGRESSED" } else { "" }
+        );
+    }
+    Ok(regressions.is_empty())
+}
+
+/// Runs one case on its own task so an agent giving up only fails that case
+async fn run_case(name: &str, project: ProjectBuilder) -> CaseMetrics {
+    let started_at: Instant = Instant::now();
+    // Collects the calls made outside a pipeline stage, such as turning the request into a goal
+    let usage: Arc<Mutex<LlmUsage>> = Arc::new(Mutex::new(LlmUsage::default()));
+    let run = tokio::spawn(with_usage(Some(usage.clone()), async move {
+        project.run().await.map_err(|e| e.to_string())
+    }))
+    .await;
+    let duration_ms: u64 = started_at.elapsed().as_millis() as u64;
+
+    match run {
+        Ok(Ok(result)) => {
+            let tokens: u64 =
+                usage.lock().unwrap().total_tokens() + result.report.usage().total_tokens();
+            CaseMetrics::from_result(name, &result, tokens, duration_ms)
+        }
+        Ok(Err(e)) => CaseMetrics::failed(name, e, duration_ms),
+        Err(e) => {
+            let error: String = match e.try_into_panic() {
+                Ok(panic) => panic
+                    .downcast_ref::<&str>()
+                    .map(|message| message.to_string())
+                    .or_else(|| panic.downcast_ref::<String>().cloned())
+                    .unwrap_or_else(|| "agent panicked".to_string()),
+                Err(e) => e.to_string(),
+            };
+            CaseMetrics::failed(name, error, duration_ms)
+        }
+    }
+}
+
+fn print_case(metrics: &CaseMetrics) {
+    match &metrics.error {
+        Some(error) => println!("  {} stopped: {}", metrics.name, error),
+        None => println!(
+            "  {} succeeded: {}, compiled first try: {}, fixes: {}, endpoints passed: {:.0}%, lints: {}, tokens: {}, {} ms",
+            metrics.name,
+            metrics.succeeded,
+            metrics.compiled_first_try,
+            metrics.fix_iterations,
+            metrics.endpoint_pass_rate * 100.0,
+            metrics.lint_count,
+            metrics.tokens,
+            metrics.duration_ms
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn summary(
+        compile_first_try_rate: f64,
+        mean_fix_iterations: f64,
+        mean_tokens: f64,
+    ) -> EvalSummary {
+        EvalSummary {
+            cases: 3,
+            success_rate: 1.0,
+            compile_first_try_rate,
+            mean_fix_iterations,
+            mean_endpoint_pass_rate: 1.0,
+            mean_lint_count: 0.0,
+            mean_tokens,
+            mean_duration_ms: 60_000.0,
+        }
+    }
+
+    #[test]
+    fn tests_summarizes_cases() {
+        let cases: Vec<CaseMetrics> = vec![
+            CaseMetrics {
+                name: "todo".to_string(),
+                succeeded: true,
+                compiled_first_try: true,
+                endpoint_pass_rate: 1.0,
+                tokens: 3000,
+                ..CaseMetrics::default()
+            },
+            CaseMetrics {
+                name: "blog".to_string(),
+                fix_iterations: 2,
+                lint_count: 3,
+                tokens: 5000,
+                ..CaseMetrics::default()
+            },
+        ];
+
+        let summary: EvalSummary = EvalSummary::from_cases(&cases);
+        assert_eq!(summary.cases, 2);
+        assert_eq!(summary.success_rate, 0.5);
+        assert_eq!(summary.compile_first_try_rate, 0.5);
+        assert_eq!(summary.mean_fix_iterations, 1.0);
+        assert_eq!(summary.mean_endpoint_pass_rate, 0.5);
+        assert_eq!(summary.mean_lint_count, 1.5);
+        assert_eq!(summary.mean_tokens, 4000.0);
+        assert_eq!(EvalSummary::from_cases(&[]).cases, 0);
+    }
+
+    #[test]
+    fn tests_finds_regressions_beyond_threshold() {
+        let baseline: EvalSummary = summary(0.9, 0.5, 10_000.0);
+
+        assert!(find_regressions(&baseline, &summary(0.85, 0.55, 10_900.0), 0.1).is_empty());
+        // Getting better never regresses
+        assert!(find_regressions(&baseline, &summary(1.0, 0.0, 5_000.0), 0.1).is_empty());
+
+        let regressions: Vec<Regression> =
+            find_regressions(&baseline, &summary(0.7, 0.7, 12_000.0), 0.1);
+        let metrics: Vec<&str> = regressions
+            .iter()
+            .map(|regression| regression.metric)
+            .collect();
+        assert_eq!(
+            metrics,
+            vec![
+                "compile_first_try_rate",
+                "mean_fix_iterations",
+                "mean_tokens"
+            ]
+        );
+        assert_eq!(regressions[0].baseline, 0.9);
+        assert_eq!(regressions[0].current, 0.7);
+    }
+
+    #[test]
+    fn tests_parses_eval_args_and_suite() {
+        let args: Vec<String> = [
+            "app",
+            "eval",
+            "suites/small.toml",
+            "--mock",
+            "--threshold",
+            "0.2",
+        ]
+        .iter()
+        .map(|arg| arg.to_string())
+        .collect();
+        let settings: EvalSettings = EvalSettings::from_args(&args).unwrap();
+        assert_eq!(settings.suite, PathBuf::from("suites/small.toml"));
+        assert!(settings.mock && !settings.save_baseline);
+        assert_eq!(settings.threshold, 0.2);
+        assert!(EvalSettings::from_args(&["eval".to_string(), "--mok".to_string()]).is_err());
+
+        let suite: EvalSuite = EvalSuite::from_file(Path::new(DEFAULT_SUITE_PATH)).unwrap();
+        assert!(!suite.cases.is_empty());
+        assert!(
+            EvalSuite::parse("[[cases]]\nname = \"a\"\nrequest = \"b\"\nframwork = \"axum\"")
+                .is_err()
+        );
+    }
+}
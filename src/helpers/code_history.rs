@@ -0,0 +1,338 @@
+use crate::helpers::command_line::PrintCommand;
+use crate::helpers::project_runner::{BuildOutcome, count_errors};
+use serde::{Deserialize, Serialize};
+use similar::TextDiff;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// One saved version of the generated backend code
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CodeIteration {
+    pub number: usize,
+    /// What produced this version, e.g. "initial", "improved" or "fix-2"
+    pub label: String,
+    /// Message context sent to the LLM that produced this version
+    pub prompt: String,
+    #[serde(skip)]
+    pub code: String,
+    /// Unified diff against the previous version
+    pub diff: String,
+    /// None until the version has been built
+    pub compiled: Option<bool>,
+    pub compiler_errors: Option<String>,
+    pub error_count: usize,
+    /// Whether the user approved this version for execution
+    #[serde(default)]
+    pub approved: bool,
+}
+
+impl CodeIteration {
+    fn file_stem(&self) -> String {
+        format!("{:02}_{}", self.number, self.label)
+    }
+}
+
+/// Every code version the backend developer produced during a run, mirrored to disk
+#[derive(Debug)]
+pub struct CodeHistory {
+    dir: PathBuf,
+    /// Agent the history belongs to, failures to save it are reported under its name
+    agent_position: String,
+    iterations: Vec<CodeIteration>,
+}
+
+impl CodeHistory {
+    pub fn new(dir: PathBuf, agent_position: &str) -> Self {
+        Self {
+            dir,
+            agent_position: agent_position.to_string(),
+            iterations: vec![],
+        }
+    }
+
+    pub fn iterations(&self) -> &[CodeIteration] {
+        &self.iterations
+    }
+
+    pub fn latest(&self) -> Option<&CodeIteration> {
+        self.iterations.last()
+    }
+
+    /// Saves a new version with its prompt and the diff against the previous version
+    pub fn record(&mut self, label: &str, prompt: &str, code: &str) -> &CodeIteration {
+        let previous_code: &str = self
+            .latest()
+            .map(|iteration| iteration.code.as_str())
+            .unwrap_or("");
+        let previous_name: String = self
+            .latest()
+            .map(CodeIteration::file_stem)
+            .unwrap_or_else(|| "empty".to_string());
+        let number: usize = self.iterations.len() + 1;
+
+        let diff: String = unified_diff(
+            previous_code,
+            code,
+            &previous_name,
+            &format!("{:02}_{}", number, label),
+        );
+
+        self.iterations.push(CodeIteration {
+            number,
+            label: label.to_string(),
+            prompt: prompt.to_string(),
+            code: code.to_string(),
+            diff,
+            compiled: None,
+            compiler_errors: None,
+            error_count: 0,
+            approved: false,
+        });
+        self.persist_latest();
+        self.iterations.last().unwrap()
+    }
+
+    /// Attaches the build result to the latest version
+    pub fn record_build(&mut self, build_outcome: &BuildOutcome) {
+        if let Some(latest) = self.iterations.last_mut() {
+            latest.compiled = Some(build_outcome.success);
+            latest.error_count = count_errors(&build_outcome.stderr);
+            latest.compiler_errors = (!build_outcome.success).then(|| build_outcome.stderr.clone());
+        }
+        self.persist_latest();
+    }
+
+    /// Whether the latest version broke a build that compiled or has more compiler errors
+    /// than the last version built before it
+    pub fn latest_regressed(&self) -> bool {
+        let Some((latest, earlier)) = self.iterations.split_last() else {
+            return false;
+        };
+        let Some(previous) = earlier.iter().rev().find(|iteration| iteration.compiled.is_some())
+        else {
+            return false;
+        };
+        match (previous.compiled, latest.compiled) {
+            (Some(true), Some(false)) => true,
+            (Some(false), Some(false)) => latest.error_count > previous.error_count,
+            _ => false,
+        }
+    }
+
+    pub fn mark_latest_approved(&mut self) {
+        if let Some(latest) = self.iterations.last_mut() {
+            latest.approved = true;
+        }
+        self.persist_latest();
+    }
+
+    /// Code the user last approved, empty before the first approval
+    pub fn last_approved_code(&self) -> &str {
+        self.iterations
+            .iter()
+            .rev()
+            .find(|iteration| iteration.approved)
+            .map(|iteration| iteration.code.as_str())
+            .unwrap_or("")
+    }
+
+    pub fn last_compiled(&self) -> Option<&CodeIteration> {
+        self.iterations
+            .iter()
+            .rev()
+            .find(|iteration| iteration.compiled == Some(true))
+    }
+
+    /// Best version to fall back to: the last one that compiled, else the one with fewest errors
+    pub fn rollback_target(&self) -> Option<&CodeIteration> {
+        self.last_compiled().or_else(|| {
+            self.iterations
+                .iter()
+                .filter(|iteration| iteration.compiled == Some(false))
+                .min_by_key(|iteration| iteration.error_count)
+        })
+    }
+
+    /// Records a rollback as a new version so the history stays linear
+    pub fn record_rollback(&mut self, target_number: usize) -> Option<&CodeIteration> {
+        let target: CodeIteration = self
+            .iterations
+            .iter()
+            .find(|iteration| iteration.number == target_number)?
+            .clone();
+        let prompt: String = format!(
+            "Rolled back to version {:02}_{}",
+            target.number, target.label
+        );
+
+        self.record(
+            &format!("rollback-to-{:02}", target.number),
+            &prompt,
+            &target.code,
+        );
+        self.copy_build_of(&target);
+        self.iterations.last()
+    }
+
+    /// Records code reformatted by rustfmt, which builds exactly like the latest version
+    pub fn record_formatting(&mut self, code: &str) {
+        let Some(source) = self.latest().cloned() else {
+            return;
+        };
+        if source.code == code {
+            return;
+        }
+        self.record("formatted", "cargo fmt", code);
+        self.copy_build_of(&source);
+    }
+
+    fn copy_build_of(&mut self, source: &CodeIteration) {
+        if let Some(latest) = self.iterations.last_mut() {
+            latest.compiled = source.compiled;
+            latest.compiler_errors = source.compiler_errors.clone();
+            latest.error_count = source.error_count;
+        }
+        self.persist_latest();
+    }
+
+    fn persist_latest(&self) {
+        let Some(latest) = self.latest() else {
+            return;
+        };
+        if let Err(e) = self.write_iteration(latest) {
+            let err_msg: String =
+                format!("Failed to save code history to {}: {}", self.dir.display(), e);
+            PrintCommand::Issue.print_agent_message(&self.agent_position, err_msg.as_str());
+        }
+    }
+
+    fn write_iteration(&self, iteration: &CodeIteration) -> std::io::Result<()> {
+        fs::create_dir_all(&self.dir)?;
+        let stem: String = iteration.file_stem();
+        let path =
+            |extension: &str| -> PathBuf { self.dir.join(format!("{}.{}", stem, extension)) };
+
+        fs::write(path("rs"), &iteration.code)?;
+        fs::write(path("diff"), &iteration.diff)?;
+        fs::write(path("json"), serde_json::to_string_pretty(iteration)?)
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+}
+
+/// Unified diff between two versions of a file
+pub fn unified_diff(old: &str, new: &str, old_name: &str, new_name: &str) -> String {
+    TextDiff::from_lines(old, new)
+        .unified_diff()
+        .context_radius(3)
+        .header(old_name, new_name)
+        .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn failed_build(errors: usize) -> BuildOutcome {
+        BuildOutcome {
+            success: false,
+            stderr: "error[E0425]: cannot find value\n".repeat(errors),
+            warning_count: 0,
+        }
+    }
+
+    #[test]
+    fn tests_records_iterations_with_diffs() {
+        let dir: PathBuf = std::env::temp_dir().join("code_history_records_test");
+        let _ = fs::remove_dir_all(&dir);
+        let mut history: CodeHistory = CodeHistory::new(dir.clone(), "Backend Developer");
+
+        history.record("initial", "CODE TEMPLATE", "fn main() {}\n");
+        let improved: &CodeIteration = history.record(
+            "improved",
+            "PROJECT_DESCRIPTION",
+            "fn main() {\n    run();\n}\n",
+        );
+
+        assert!(improved.diff.contains("--- 01_initial"));
+        assert!(improved.diff.contains("+    run();"));
+        assert!(dir.join("02_improved.rs").exists());
+        assert!(dir.join("02_improved.diff").exists());
+
+        assert_eq!(history.last_approved_code(), "");
+        history.mark_latest_approved();
+        history.record("manual-edit", "Edited by user during review", "fn main() {}\n");
+        assert_eq!(
+            history.last_approved_code(),
+            "fn main() {\n    run();\n}\n"
+        );
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn tests_detects_regression_and_picks_rollback_target() {
+        let dir: PathBuf = std::env::temp_dir().join("code_history_rollback_test");
+        let _ = fs::remove_dir_all(&dir);
+        let mut history: CodeHistory = CodeHistory::new(dir.clone(), "Backend Developer");
+
+        history.record("initial", "", "v1");
+        history.record_build(&BuildOutcome {
+            success: true,
+            stderr: String::new(),
+            warning_count: 0,
+        });
+        history.record("improved", "", "v2");
+        history.record_build(&failed_build(3));
+        assert!(history.latest_regressed());
+
+        history.record("fix-1", "", "v3");
+        history.record_build(&failed_build(1));
+        assert!(!history.latest_regressed());
+
+        history.record("fix-2", "", "v4");
+        history.record_build(&failed_build(2));
+        assert!(history.latest_regressed());
+
+        let target: usize = history.rollback_target().unwrap().number;
+        assert_eq!(target, 1);
+
+        let rolled_back: &CodeIteration = history.record_rollback(target).unwrap();
+        assert_eq!(rolled_back.code, "v1");
+        assert_eq!(rolled_back.compiled, Some(true));
+
+        history.record_formatting("v1\n");
+        assert_eq!(history.latest().unwrap().label, "formatted");
+        assert_eq!(history.latest().unwrap().compiled, Some(true));
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn tests_detects_compiled_code_breaking() {
+        let dir: PathBuf = std::env::temp_dir().join("code_history_broken_test");
+        let _ = fs::remove_dir_all(&dir);
+        let mut history: CodeHistory = CodeHistory::new(dir.clone(), "Backend Developer");
+
+        // The first version is never built when only one candidate is generated
+        history.record("initial", "", "v1");
+        history.record("improved", "", "v2");
+        history.record_build(&failed_build(2));
+        assert!(!history.latest_regressed());
+
+        history.record("fix-1", "", "v3");
+        history.record_build(&BuildOutcome {
+            success: true,
+            stderr: String::new(),
+            warning_count: 0,
+        });
+        history.record_formatting("v3\n");
+        assert!(!history.latest_regressed());
+
+        history.record("lint-fix-1", "", "v5");
+        history.record_build(&failed_build(1));
+        assert!(history.latest_regressed());
+        assert_eq!(history.rollback_target().unwrap().label, "formatted");
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -1,10 +1,17 @@
+use crate::dashboard::{request_approval, request_review};
+use crate::helpers::code_history::unified_diff;
+use crate::helpers::telemetry::AGENT_MESSAGE_TARGET;
+use std::fs;
 use std::io::{stdin, stdout};
+use std::path::PathBuf;
+use std::process::Command;
 use crossterm::{
     style::{Color, ResetColor, SetForegroundColor},
     ExecutableCommand
 };
+use strum_macros::{Display, EnumString};
 
-#[derive(PartialEq, Debug)]
+#[derive(PartialEq, Debug, Display, EnumString)]
 pub enum PrintCommand {
     AICall,
     UnitTest,
@@ -12,7 +19,33 @@ pub enum PrintCommand {
 }
 
 impl PrintCommand {
+    /// Emits an agent message as a tracing event, printing it directly when no subscriber is set
     pub fn print_agent_message(&self, agent_pos: &str, agent_statement: &str) {
+        if !tracing::dispatcher::has_been_set() {
+            self.write_agent_message(agent_pos, agent_statement);
+            return;
+        }
+
+        match self {
+            Self::Issue => tracing::warn!(
+                target: AGENT_MESSAGE_TARGET,
+                agent = agent_pos,
+                kind = %self,
+                "{}",
+                agent_statement
+            ),
+            _ => tracing::info!(
+                target: AGENT_MESSAGE_TARGET,
+                agent = agent_pos,
+                kind = %self,
+                "{}",
+                agent_statement
+            ),
+        }
+    }
+
+    /// Prints an agent message to the console in color
+    pub fn write_agent_message(&self, agent_pos: &str, agent_statement: &str) {
         let mut stdout: std::io::Stdout = stdout();
 
         let statement_color: Color = match self {
@@ -36,7 +69,7 @@ pub fn get_user_response(question: &str) -> String {
     let mut stdout: std::io::Stdout = stdout();
 
     stdout.execute(SetForegroundColor(Color::Blue)).unwrap();
-    println!("");
+    println!();
     println!("{}", question);
 
     stdout.execute(ResetColor).unwrap();
@@ -46,16 +79,24 @@ pub fn get_user_response(question: &str) -> String {
         .read_line(&mut user_response)
         .expect("Failed to read response");
 
-    return user_response.trim().to_string();
+    user_response.trim().to_string()
 }
 
 /// Get user response that code is safe to execute
 pub fn confirm_safe_code() -> bool {
+    // The dashboard owns the terminal while it runs
+    if let Some(approved) = request_approval(
+        "WARNING: You are about to run code written entirely by AI. \
+         Review your code and confirm you wish to continue.",
+    ) {
+        return approved;
+    }
+
     let mut stdout: std::io::Stdout = stdout();
     loop {
         
         stdout.execute(SetForegroundColor(Color::Blue)).unwrap();
-        println!("");
+        println!();
         print!("WARNING: You are about to run code written entirely by AI. ");
         println!("Review your code and confirm you wish to continue.");
 
@@ -85,10 +126,142 @@ pub fn confirm_safe_code() -> bool {
     }
 }
 
+/// What the user decided when reviewing generated code before it runs
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct ReviewOutcome {
+    pub approved: bool,
+    /// Code after the user's manual edits, None when left unchanged
+    pub edited_code: Option<String>,
+    /// Instruction for the next LLM iteration
+    pub instruction: Option<String>,
+}
+
+/// Shows the changes since the last approved version and lets the user edit, instruct or approve
+pub fn review_code(approved_code: &str, code: &str) -> ReviewOutcome {
+    if let Some(outcome) = request_review(approved_code, code) {
+        return outcome;
+    }
+
+    let mut stdout: std::io::Stdout = stdout();
+    let mut outcome: ReviewOutcome = ReviewOutcome::default();
+    let mut current_code: String = code.to_string();
+    print_diff(&unified_diff(approved_code, &current_code, "last-approved", "current"));
+
+    loop {
+        stdout.execute(SetForegroundColor(Color::Blue)).unwrap();
+        println!();
+        print!("WARNING: You are about to run code written entirely by AI. ");
+        println!("Review your code and confirm you wish to continue.");
+
+        stdout.execute(SetForegroundColor(Color::Green)).unwrap();
+        println!("[1] All good");
+        stdout.execute(SetForegroundColor(Color::DarkRed)).unwrap();
+        println!("[2] Lets stop this project");
+        stdout.execute(SetForegroundColor(Color::Yellow)).unwrap();
+        println!("[3] Edit the code in $EDITOR");
+        println!("[4] Add an instruction for the next iteration");
+
+        stdout.execute(ResetColor).unwrap();
+
+        let mut human_response: String = String::new();
+        stdin()
+            .read_line(&mut human_response)
+            .expect("Failed to read response");
+
+        match human_response.trim().to_lowercase().as_str() {
+            "1" | "ok" | "y" => {
+                outcome.approved = true;
+                return outcome;
+            }
+            "2" | "no" | "n" => return outcome,
+            "3" | "e" => match edit_in_editor(&current_code) {
+                Ok(edited_code) => {
+                    print_diff(&unified_diff(
+                        approved_code,
+                        &edited_code,
+                        "last-approved",
+                        "edited",
+                    ));
+                    if edited_code != code {
+                        outcome.edited_code = Some(edited_code.clone());
+                    }
+                    current_code = edited_code;
+                }
+                Err(e) => println!("Failed to open editor: {}", e),
+            },
+            "4" | "i" => {
+                let instruction: String =
+                    get_user_response("What should the next iteration change?");
+                if !instruction.is_empty() {
+                    outcome.instruction = Some(instruction);
+                }
+            }
+            _ => {
+                println!("Invalid input. Please select '1', '2', '3' or '4'")
+            }
+        }
+    }
+}
+
+/// Opens code in $VISUAL or $EDITOR, falling back to vi, and returns the saved result
+pub fn edit_in_editor(code: &str) -> std::io::Result<String> {
+    let editor: String = std::env::var("VISUAL")
+        .or_else(|_| std::env::var("EDITOR"))
+        .unwrap_or_else(|_| "vi".to_string());
+    edit_with(&editor, code)
+}
+
+fn edit_with(editor: &str, code: &str) -> std::io::Result<String> {
+    let review_dir: PathBuf = std::env::temp_dir().join("rust-backend-gen-agents");
+    fs::create_dir_all(&review_dir)?;
+    let review_path: PathBuf = review_dir.join("review_main.rs");
+    fs::write(&review_path, code)?;
+
+    // Editors such as "code --wait" come with their own arguments
+    let mut editor_parts = editor.split_whitespace();
+    let program: &str = editor_parts.next().unwrap_or("vi");
+    let status: std::process::ExitStatus = Command::new(program)
+        .args(editor_parts)
+        .arg(&review_path)
+        .status()?;
+    if !status.success() {
+        return Err(std::io::Error::other(format!("{} exited with {}", program, status)));
+    }
+    fs::read_to_string(&review_path)
+}
+
+/// Prints a unified diff with added lines in green and removed lines in red
+fn print_diff(diff: &str) {
+    let mut stdout: std::io::Stdout = stdout();
+    if diff.is_empty() {
+        println!("No changes since the last approved version");
+        return;
+    }
+
+    for line in diff.lines() {
+        let color: Color = match line.chars().next() {
+            Some('+') => Color::Green,
+            Some('-') => Color::Red,
+            Some('@') => Color::Cyan,
+            _ => Color::Reset,
+        };
+        stdout.execute(SetForegroundColor(color)).unwrap();
+        println!("{}", line);
+    }
+    stdout.execute(ResetColor).unwrap();
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn tests_edits_code_with_editor_command() {
+        let edited: String = edit_with("sed -i s/old_name/new_name/", "fn old_name() {}\n").unwrap();
+        assert_eq!(edited, "fn new_name() {}\n");
+        assert!(edit_with("false", "fn main() {}").is_err());
+    }
+
     #[test]
     fn test_prints_agent_messages() {
         PrintCommand::AICall
@@ -0,0 +1,255 @@
+use crate::models::general::external_api::ExternalApiSample;
+use reqwest::Url;
+use std::cmp::Reverse;
+use std::collections::BTreeMap;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::task::JoinHandle;
+
+/// Largest request head the stub server reads before answering
+const MAX_REQUEST_HEAD_BYTES: usize = 16 * 1024;
+
+/// Recorded response served for a path on a stubbed host
+#[derive(Debug, Clone, PartialEq)]
+pub struct Fixture {
+    pub path_and_query: String,
+    pub status_code: u16,
+    pub content_type: String,
+    pub body: String,
+}
+
+impl Fixture {
+    fn path(&self) -> &str {
+        self.path_and_query.split('?').next().unwrap_or_default()
+    }
+}
+
+/// Local HTTP server replaying recorded responses for one external host
+#[derive(Debug)]
+pub struct FixtureServer {
+    pub host: String,
+    pub env_var: String,
+    pub base_url: String,
+    handle: JoinHandle<()>,
+}
+
+impl FixtureServer {
+    pub async fn start(host: &str, fixtures: Vec<Fixture>) -> std::io::Result<Self> {
+        let listener: TcpListener = TcpListener::bind("127.0.0.1:0").await?;
+        let base_url: String = format!("http://{}", listener.local_addr()?);
+
+        let handle: JoinHandle<()> = tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                let fixtures: Vec<Fixture> = fixtures.clone();
+                tokio::spawn(async move {
+                    let _ = serve_fixture(stream, &fixtures).await;
+                });
+            }
+        });
+
+        Ok(Self {
+            host: host.to_string(),
+            env_var: fixture_env_var(host),
+            base_url,
+            handle,
+        })
+    }
+
+    pub fn shutdown(self) {
+        self.handle.abort();
+    }
+}
+
+/// Name of the env var the generated backend reads the base url of a host from
+pub fn fixture_env_var(host: &str) -> String {
+    let name: String = host
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() {
+                c.to_ascii_uppercase()
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    format!("{}_BASE_URL", name)
+}
+
+/// Groups recorded samples into fixtures per external host
+pub fn fixtures_by_host(samples: &[ExternalApiSample]) -> BTreeMap<String, Vec<Fixture>> {
+    let mut grouped: BTreeMap<String, Vec<Fixture>> = BTreeMap::new();
+
+    for sample in samples {
+        let (Ok(url), Some(body)) = (Url::parse(&sample.url), sample.body.as_ref()) else {
+            continue;
+        };
+        let Some(host) = url.host_str() else {
+            continue;
+        };
+
+        let path_and_query: String = match url.query() {
+            Some(query) => format!("{}?{}", url.path(), query),
+            None => url.path().to_string(),
+        };
+
+        grouped.entry(host.to_string()).or_default().push(Fixture {
+            path_and_query,
+            status_code: sample.status_code,
+            content_type: sample
+                .content_type
+                .clone()
+                .unwrap_or_else(|| "application/json".to_string()),
+            body: body.clone(),
+        });
+    }
+
+    grouped
+}
+
+/// Starts one fixture server per external host found in the samples
+pub async fn start_fixture_servers(
+    samples: &[ExternalApiSample],
+) -> std::io::Result<Vec<FixtureServer>> {
+    let mut servers: Vec<FixtureServer> = vec![];
+    for (host, fixtures) in fixtures_by_host(samples) {
+        servers.push(FixtureServer::start(&host, fixtures).await?);
+    }
+    Ok(servers)
+}
+
+/// Picks the fixture for a request: exact match, then same path, then longest path prefix
+pub fn match_fixture<'a>(fixtures: &'a [Fixture], path_and_query: &str) -> Option<&'a Fixture> {
+    let path: &str = path_and_query.split('?').next().unwrap_or_default();
+
+    fixtures
+        .iter()
+        .find(|fixture| fixture.path_and_query == path_and_query)
+        .or_else(|| fixtures.iter().find(|fixture| fixture.path() == path))
+        .or_else(|| {
+            fixtures
+                .iter()
+                .filter(|fixture| {
+                    let prefix: &str = fixture.path().trim_end_matches('/');
+                    path.strip_prefix(prefix)
+                        .is_some_and(|rest| rest.is_empty() || rest.starts_with('/'))
+                })
+                .min_by_key(|fixture| Reverse(fixture.path().len()))
+        })
+}
+
+async fn serve_fixture(mut stream: TcpStream, fixtures: &[Fixture]) -> std::io::Result<()> {
+    let mut request_head: Vec<u8> = vec![];
+    let mut buffer: [u8; 1024] = [0; 1024];
+
+    while !request_head.windows(4).any(|window| window == b"\r\n\r\n")
+        && request_head.len() < MAX_REQUEST_HEAD_BYTES
+    {
+        let read: usize = stream.read(&mut buffer).await?;
+        if read == 0 {
+            break;
+        }
+        request_head.extend_from_slice(&buffer[..read]);
+    }
+
+    let request_line: String = String::from_utf8_lossy(&request_head)
+        .lines()
+        .next()
+        .unwrap_or_default()
+        .to_string();
+    let path_and_query: &str = request_line.split_whitespace().nth(1).unwrap_or("/");
+
+    let (status_code, content_type, body): (u16, &str, &str) =
+        match match_fixture(fixtures, path_and_query) {
+            Some(fixture) => (fixture.status_code, &fixture.content_type, &fixture.body),
+            None => (
+                404,
+                "application/json",
+                "{\"error\":\"no recorded fixture\"}",
+            ),
+        };
+
+    let response: String = format!(
+        "HTTP/1.1 {} Fixture\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status_code,
+        content_type,
+        body.len(),
+        body
+    );
+    stream.write_all(response.as_bytes()).await?;
+    stream.shutdown().await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample(url: &str, body: &str) -> ExternalApiSample {
+        ExternalApiSample::new(
+            url,
+            200,
+            Some("application/json".to_string()),
+            body.to_string(),
+        )
+    }
+
+    #[test]
+    fn tests_fixture_env_var() {
+        assert_eq!(
+            fixture_env_var("hp-api.onrender.com"),
+            "HP_API_ONRENDER_COM_BASE_URL"
+        );
+    }
+
+    #[test]
+    fn tests_matches_fixtures() {
+        let fixtures: Vec<Fixture> = fixtures_by_host(&[
+            sample("https://api.example.com/characters", "[]"),
+            sample("https://api.example.com/characters?house=gryffindor", "[1]"),
+        ])
+        .remove("api.example.com")
+        .unwrap();
+
+        assert_eq!(
+            match_fixture(&fixtures, "/characters?house=gryffindor")
+                .unwrap()
+                .body,
+            "[1]"
+        );
+        assert_eq!(
+            match_fixture(&fixtures, "/characters?house=slytherin")
+                .unwrap()
+                .body,
+            "[]"
+        );
+        assert_eq!(
+            match_fixture(&fixtures, "/characters/students")
+                .unwrap()
+                .body,
+            "[]"
+        );
+        assert!(match_fixture(&fixtures, "/spells").is_none());
+    }
+
+    #[tokio::test]
+    async fn tests_serves_recorded_responses() {
+        let servers: Vec<FixtureServer> = start_fixture_servers(&[sample(
+            "https://hp-api.onrender.com/api/characters",
+            "[{\"name\":\"Harry Potter\"}]",
+        )])
+        .await
+        .unwrap();
+
+        let url: String = format!("{}/api/characters", servers[0].base_url);
+        let response: reqwest::Response = reqwest::get(url).await.unwrap();
+
+        assert_eq!(response.status().as_u16(), 200);
+        assert_eq!(
+            response.text().await.unwrap(),
+            "[{\"name\":\"Harry Potter\"}]"
+        );
+
+        for server in servers {
+            server.shutdown();
+        }
+    }
+}
@@ -1,2 +1,10 @@
+pub mod code_history;
 pub mod command_line;
-pub mod general;
\ No newline at end of file
+pub mod fixture_server;
+pub mod general;
+pub mod project_runner;
+pub mod run_report;
+pub mod telemetry;
+pub mod template_registry;
+pub mod url_health;
+pub mod url_policy;
\ No newline at end of file
@@ -0,0 +1,441 @@
+use crate::helpers::command_line::PrintCommand;
+use crate::helpers::fixture_server::{FixtureServer, start_fixture_servers};
+use crate::helpers::general::check_status_code;
+use crate::models::agents::agent_traits::RouteObject;
+use crate::models::general::external_api::ExternalApiSample;
+use crate::models::general::lint::{parse_clippy_output, Lint};
+use crate::models::general::settings::env_number;
+
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+use std::collections::BTreeSet;
+use std::fmt;
+use std::io;
+use std::path::Path;
+use std::process::{Child, Command, Output, Stdio};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::time;
+use tracing::Span;
+
+/// Port generated servers listen on unless WEB_SERVER_PORT says otherwise
+pub const DEFAULT_WEB_SERVER_PORT: u16 = 8080;
+
+tokio::task_local! {
+    /// Processes started for the pipeline stage that is running
+    static STAGE_PROCESSES: ProcessTracker;
+}
+
+/// Pids of the cargo processes a project started, so stopping it can kill them
+#[derive(Debug, Clone, Default)]
+pub struct ProcessTracker {
+    pids: Arc<Mutex<BTreeSet<u32>>>,
+}
+
+impl ProcessTracker {
+    fn register(&self, pid: u32) {
+        self.pids.lock().unwrap().insert(pid);
+    }
+
+    fn unregister(&self, pid: u32) {
+        self.pids.lock().unwrap().remove(&pid);
+    }
+
+    /// Kills every process that is still running, aborting a task does not stop its children
+    pub fn kill_all(&self) {
+        let pids: BTreeSet<u32> = std::mem::take(&mut *self.pids.lock().unwrap());
+        for pid in pids {
+            #[cfg(unix)]
+            // SAFETY: kill only sends a signal, it does not touch memory of this process
+            unsafe {
+                libc::kill(pid as libc::pid_t, libc::SIGKILL);
+            }
+            #[cfg(not(unix))]
+            let _ = Command::new("taskkill")
+                .args(["/F", "/T", "/PID", &pid.to_string()])
+                .stdout(Stdio::null())
+                .stderr(Stdio::null())
+                .status();
+        }
+    }
+}
+
+/// Tracks every process the future starts in `processes`, keeping the current tracker when None
+pub async fn with_processes<F: Future>(processes: Option<ProcessTracker>, future: F) -> F::Output {
+    match processes {
+        Some(processes) => STAGE_PROCESSES.scope(processes, future).await,
+        None => future.await,
+    }
+}
+
+/// Same as `with_processes` for work handed to a blocking thread
+pub fn with_processes_blocking<R>(processes: Option<ProcessTracker>, f: impl FnOnce() -> R) -> R {
+    match processes {
+        Some(processes) => STAGE_PROCESSES.sync_scope(processes, f),
+        None => f(),
+    }
+}
+
+/// Tracker of the running stage, None outside a pipeline stage
+pub fn current_processes() -> Option<ProcessTracker> {
+    STAGE_PROCESSES.try_with(ProcessTracker::clone).ok()
+}
+
+/// Spawns the command, registering it with the running stage's tracker
+pub fn spawn_tracked(command: &mut Command) -> io::Result<Child> {
+    let child: Child = command.spawn()?;
+    if let Some(processes) = current_processes() {
+        processes.register(child.id());
+    }
+    Ok(child)
+}
+
+/// Unregisters a tracked process once it exited or was killed
+fn untrack(pid: u32) {
+    if let Some(processes) = current_processes() {
+        processes.unregister(pid);
+    }
+}
+
+/// `Command::output` for a process the running stage can kill
+fn tracked_output(command: &mut Command) -> io::Result<Output> {
+    let child: Child = spawn_tracked(command)?;
+    let pid: u32 = child.id();
+    let output: io::Result<Output> = child.wait_with_output();
+    untrack(pid);
+    output
+}
+
+/// Result of running `cargo build` on a generated project
+#[derive(Debug, Clone, PartialEq)]
+pub struct BuildOutcome {
+    pub success: bool,
+    pub stderr: String,
+    pub warning_count: usize,
+}
+
+/// Result of calling the testable endpoints of a running generated server
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+pub struct EndpointTestReport {
+    pub passed: Vec<String>,
+    pub failed: Vec<String>,
+}
+
+impl EndpointTestReport {
+    /// Share of endpoints that answered with 200, 1.0 when nothing could be tested
+    pub fn pass_rate(&self) -> f32 {
+        let total: usize = self.passed.len() + self.failed.len();
+        if total == 0 {
+            return 1.0;
+        }
+        self.passed.len() as f32 / total as f32
+    }
+}
+
+/// How well a sampled backend candidate did, used to pick the one to continue with
+#[derive(Debug, Clone, PartialEq)]
+pub struct CandidateScore {
+    pub index: usize,
+    pub compiled: bool,
+    pub warning_count: usize,
+    pub endpoint_pass_rate: f32,
+}
+
+impl CandidateScore {
+    pub fn new(
+        index: usize,
+        build_outcome: &BuildOutcome,
+        endpoint_report: &EndpointTestReport,
+    ) -> Self {
+        Self {
+            index,
+            compiled: build_outcome.success,
+            warning_count: build_outcome.warning_count,
+            endpoint_pass_rate: if build_outcome.success {
+                endpoint_report.pass_rate()
+            } else {
+                0.0
+            },
+        }
+    }
+
+    /// Compiling beats not compiling, then a higher pass rate, then fewer warnings
+    pub fn cmp_rank(&self, other: &CandidateScore) -> Ordering {
+        self.compiled
+            .cmp(&other.compiled)
+            .then(self.endpoint_pass_rate.total_cmp(&other.endpoint_pass_rate))
+            .then(other.warning_count.cmp(&self.warning_count))
+            .then(other.index.cmp(&self.index))
+    }
+}
+
+impl fmt::Display for CandidateScore {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "candidate {} compiled: {}, warnings: {}, endpoint pass rate: {:.0}%",
+            self.index,
+            self.compiled,
+            self.warning_count,
+            self.endpoint_pass_rate * 100.0
+        )
+    }
+}
+
+/// Builds a generated project, optionally with its own target directory
+#[tracing::instrument(
+    skip_all,
+    fields(project_dir = %project_dir.display(), success, warning_count, error_count)
+)]
+pub fn build_project(project_dir: &Path, target_dir: Option<&Path>) -> BuildOutcome {
+    let mut command: Command = Command::new("cargo");
+    command
+        .arg("build")
+        .current_dir(project_dir)
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped());
+
+    if let Some(target_dir) = target_dir {
+        command.env("CARGO_TARGET_DIR", target_dir);
+    }
+
+    let output: Output =
+        tracked_output(&mut command).expect("Failed to build backend application");
+    let stderr: String = String::from_utf8_lossy(&output.stderr).to_string();
+
+    let build_outcome: BuildOutcome = BuildOutcome {
+        success: output.status.success(),
+        warning_count: count_warnings(&stderr),
+        stderr,
+    };
+    Span::current()
+        .record("success", build_outcome.success)
+        .record("warning_count", build_outcome.warning_count)
+        .record("error_count", count_errors(&build_outcome.stderr));
+    build_outcome
+}
+
+/// Runs `cargo fmt` on a generated project, returning whether it succeeded
+#[tracing::instrument(skip_all, fields(project_dir = %project_dir.display()), ret)]
+pub fn format_project(project_dir: &Path) -> bool {
+    tracked_output(
+        Command::new("cargo")
+            .arg("fmt")
+            .current_dir(project_dir)
+            .stdout(Stdio::null())
+            .stderr(Stdio::null()),
+    )
+    .is_ok_and(|output| output.status.success())
+}
+
+/// Runs clippy on a generated project, None when clippy could not run
+#[tracing::instrument(skip_all, fields(project_dir = %project_dir.display(), lint_count))]
+pub fn run_clippy(project_dir: &Path) -> Option<Vec<Lint>> {
+    let output: Output = tracked_output(
+        Command::new("cargo")
+            .args(["clippy", "--message-format=json"])
+            .current_dir(project_dir)
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null()),
+    )
+    .ok()?;
+
+    let stdout: String = String::from_utf8_lossy(&output.stdout).to_string();
+    if !output.status.success() && stdout.trim().is_empty() {
+        return None;
+    }
+    let lints: Vec<Lint> = parse_clippy_output(&stdout);
+    Span::current().record("lint_count", lints.len());
+    Some(lints)
+}
+
+/// Counts compiler warnings, ignoring cargo's "generated N warnings" summary lines
+pub fn count_warnings(stderr: &str) -> usize {
+    stderr
+        .lines()
+        .filter(|line| line.starts_with("warning") && !line.contains(" generated "))
+        .count()
+}
+
+/// Counts compiler errors, ignoring cargo's "could not compile" and "aborting" summary lines
+pub fn count_errors(stderr: &str) -> usize {
+    stderr
+        .lines()
+        .filter(|line| {
+            line.starts_with("error")
+                && !line.starts_with("error: could not compile")
+                && !line.contains("aborting due to")
+        })
+        .count()
+}
+
+/// Port the generated server listens on while its endpoints are tested
+pub fn web_server_port() -> u16 {
+    env_number::<u16>("WEB_SERVER_PORT").unwrap_or(DEFAULT_WEB_SERVER_PORT)
+}
+
+/// Runs the generated server and calls every endpoint that needs no input
+#[tracing::instrument(
+    skip_all,
+    fields(project_dir = %project_dir.display(), endpoints = endpoints.len(), passed, failed)
+)]
+pub async fn run_endpoint_tests(
+    project_dir: &Path,
+    target_dir: Option<&Path>,
+    endpoints: &[RouteObject],
+    external_api_samples: &[ExternalApiSample],
+    agent_position: &str,
+) -> EndpointTestReport {
+    let mut report: EndpointTestReport = EndpointTestReport::default();
+
+    // Replay recorded external API responses so tests run offline
+    let fixture_servers: Vec<FixtureServer> = start_fixture_servers(external_api_samples)
+        .await
+        .expect("Failed to start external API fixture servers");
+
+    for fixture_server in &fixture_servers {
+        let fixture_msg: String = format!(
+            "Backend Code Unit Testing: Serving {} fixtures from {}",
+            fixture_server.host, fixture_server.base_url
+        );
+        PrintCommand::UnitTest.print_agent_message(agent_position, fixture_msg.as_str());
+    }
+
+    // Execute running server
+    let port: u16 = web_server_port();
+    let mut command: Command = Command::new("cargo");
+    command
+        .arg("run")
+        .env("PORT", port.to_string())
+        .envs(fixture_servers.iter().map(|fixture_server| {
+            (
+                fixture_server.env_var.as_str(),
+                fixture_server.base_url.as_str(),
+            )
+        }))
+        .current_dir(project_dir)
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped());
+
+    if let Some(target_dir) = target_dir {
+        command.env("CARGO_TARGET_DIR", target_dir);
+    }
+
+    let mut run_backend_server: Child =
+        spawn_tracked(&mut command).expect("Failed to run backend application");
+
+    PrintCommand::UnitTest.print_agent_message(
+        agent_position,
+        "Backend Code Unit Testing: Launching tests on server in 5 seconds...",
+    );
+
+    let seconds_sleep: Duration = Duration::from_secs(5);
+    time::sleep(seconds_sleep).await;
+
+    let client: Client = Client::builder()
+        .timeout(Duration::from_secs(5))
+        .build()
+        .unwrap();
+
+    // Check status code
+    for endpoint in endpoints {
+        let testing_msg: String = format!("Testing endpoint '{}'...", endpoint.route);
+        PrintCommand::UnitTest.print_agent_message(agent_position, testing_msg.as_str());
+
+        // Test url
+        let url: String = format!("http://localhost:{}{}", port, endpoint.route);
+        match check_status_code(&client, &url).await {
+            Ok(200) => report.passed.push(endpoint.route.clone()),
+            Ok(_) => {
+                let err_msg: String = format!(
+                    "WARNING: Failed to call backend url endpoint {}",
+                    endpoint.route
+                );
+                PrintCommand::Issue.print_agent_message(agent_position, err_msg.as_str());
+                report.failed.push(endpoint.route.clone());
+            }
+            Err(e) => {
+                let err_msg: String = format!("Error checking backend {}", e);
+                PrintCommand::Issue.print_agent_message(agent_position, err_msg.as_str());
+                report.failed.push(endpoint.route.clone());
+            }
+        }
+    }
+
+    // kill $(lsof -t -i:$PORT)
+    run_backend_server
+        .kill()
+        .expect("Failed to kill backend web server on completion");
+    run_backend_server
+        .wait()
+        .expect("Failed to wait for backend web server to exit");
+    untrack(run_backend_server.id());
+
+    for fixture_server in fixture_servers {
+        fixture_server.shutdown();
+    }
+
+    Span::current()
+        .record("passed", report.passed.len())
+        .record("failed", report.failed.len());
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tests_counts_warnings() {
+        let stderr: &str = "warning: unused variable: `x`\n --> src/main.rs:2:9\n\
+            warning: function `f` is never used\n\
+            warning: `web_template` (bin \"web_template\") generated 2 warnings\n\
+            Finished `dev` profile";
+        assert_eq!(count_warnings(stderr), 2);
+    }
+
+    #[test]
+    fn tests_counts_errors() {
+        let stderr: &str = "error[E0425]: cannot find value `x` in this scope\n\
+            error[E0308]: mismatched types\n\
+            error: aborting due to 2 previous errors\n\
+            error: could not compile `web_template` (bin \"web_template\") due to 2 previous errors";
+        assert_eq!(count_errors(stderr), 2);
+    }
+
+    #[test]
+    fn tests_endpoint_pass_rate() {
+        let report: EndpointTestReport = EndpointTestReport {
+            passed: vec!["/task".to_string()],
+            failed: vec!["/login".to_string()],
+        };
+        assert_eq!(report.pass_rate(), 0.5);
+        assert_eq!(EndpointTestReport::default().pass_rate(), 1.0);
+    }
+
+    #[test]
+    fn tests_ranks_candidates() {
+        let outcome = |success: bool, warning_count: usize| BuildOutcome {
+            success,
+            stderr: String::new(),
+            warning_count,
+        };
+        let full_pass: EndpointTestReport = EndpointTestReport::default();
+        let half_pass: EndpointTestReport = EndpointTestReport {
+            passed: vec!["/a".to_string()],
+            failed: vec!["/b".to_string()],
+        };
+
+        let mut scores: Vec<CandidateScore> = vec![
+            CandidateScore::new(0, &outcome(false, 0), &full_pass),
+            CandidateScore::new(1, &outcome(true, 3), &half_pass),
+            CandidateScore::new(2, &outcome(true, 5), &full_pass),
+            CandidateScore::new(3, &outcome(true, 1), &full_pass),
+        ];
+        scores.sort_by(|left, right| right.cmp_rank(left));
+
+        let ranked: Vec<usize> = scores.iter().map(|score| score.index).collect();
+        assert_eq!(ranked, vec![3, 2, 1, 0]);
+    }
+}
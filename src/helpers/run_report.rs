@@ -0,0 +1,567 @@
+use crate::models::agents::agent_traits::{FactSheet, ProjectScope};
+use crate::models::agents_manager::report::{ProjectReport, StageReport};
+use crate::models::general::llm::LlmUsage;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+pub const REPORT_MARKDOWN_FILE: &str = "REPORT.md";
+
+pub const REPORT_HTML_FILE: &str = "REPORT.html";
+
+const REPORT_TITLE: &str = "Project report";
+
+/// Inline styles so the HTML report opens anywhere without other files
+const REPORT_CSS: &str = "body{font-family:system-ui,sans-serif;max-width:960px;margin:2em auto;padding:0 1em;color:#222}\
+table{border-collapse:collapse;margin:0.5em 0 1em}\
+th,td{border:1px solid #ccc;padding:4px 8px;text-align:left;vertical-align:top}\
+th{background:#f3f3f3}\
+h2{border-bottom:1px solid #ddd;padding-bottom:4px}";
+
+/// Part of a report section, rendered alike to Markdown and HTML
+#[derive(Debug, Clone, PartialEq)]
+enum Block {
+    Paragraph(String),
+    Subheading(String),
+    List(Vec<String>),
+    Table {
+        headers: Vec<&'static str>,
+        rows: Vec<Vec<String>>,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Section {
+    title: &'static str,
+    blocks: Vec<Block>,
+}
+
+/// Writes `REPORT.md` and `REPORT.html` for a finished run into its directory
+pub fn write_run_report(
+    run_dir: &Path,
+    factsheet: &FactSheet,
+    report: &ProjectReport,
+) -> std::io::Result<Vec<PathBuf>> {
+    fs::create_dir_all(run_dir)?;
+    let markdown_path: PathBuf = run_dir.join(REPORT_MARKDOWN_FILE);
+    let html_path: PathBuf = run_dir.join(REPORT_HTML_FILE);
+    fs::write(&markdown_path, render_markdown(factsheet, report))?;
+    fs::write(&html_path, render_html(factsheet, report))?;
+    Ok(vec![markdown_path, html_path])
+}
+
+pub fn render_markdown(factsheet: &FactSheet, report: &ProjectReport) -> String {
+    let mut markdown: String = format!("# {}\n", REPORT_TITLE);
+    for section in report_sections(factsheet, report) {
+        markdown.push_str(&format!("\n## {}\n", section.title));
+        for block in section.blocks {
+            markdown.push('\n');
+            match block {
+                Block::Paragraph(text) => markdown.push_str(&format!("{}\n", text)),
+                Block::Subheading(text) => markdown.push_str(&format!("### {}\n", text)),
+                Block::List(items) => {
+                    for item in items {
+                        markdown.push_str(&format!("- {}\n", item));
+                    }
+                }
+                Block::Table { headers, rows } => {
+                    markdown.push_str(&format!("| {} |\n", headers.join(" | ")));
+                    markdown.push_str(&format!("|{}\n", " --- |".repeat(headers.len())));
+                    for row in rows {
+                        let cells: Vec<String> =
+                            row.iter().map(|cell| markdown_cell(cell)).collect();
+                        markdown.push_str(&format!("| {} |\n", cells.join(" | ")));
+                    }
+                }
+            }
+        }
+    }
+    markdown
+}
+
+/// Self-contained HTML page with the same content as the Markdown report
+pub fn render_html(factsheet: &FactSheet, report: &ProjectReport) -> String {
+    let mut html: String = format!(
+        "<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">\n<title>{}</title>\n<style>{}</style>\n</head>\n<body>\n<h1>{}</h1>\n",
+        REPORT_TITLE, REPORT_CSS, REPORT_TITLE
+    );
+    for section in report_sections(factsheet, report) {
+        html.push_str(&format!("<h2>{}</h2>\n", escape_html(section.title)));
+        for block in section.blocks {
+            match block {
+                Block::Paragraph(text) => {
+                    html.push_str(&format!("<p>{}</p>\n", escape_html(&text)))
+                }
+                Block::Subheading(text) => {
+                    html.push_str(&format!("<h3>{}</h3>\n", escape_html(&text)))
+                }
+                Block::List(items) => {
+                    html.push_str("<ul>\n");
+                    for item in items {
+                        html.push_str(&format!("<li>{}</li>\n", escape_html(&item)));
+                    }
+                    html.push_str("</ul>\n");
+                }
+                Block::Table { headers, rows } => {
+                    html.push_str("<table>\n<tr>");
+                    for header in headers {
+                        html.push_str(&format!("<th>{}</th>", escape_html(header)));
+                    }
+                    html.push_str("</tr>\n");
+                    for row in rows {
+                        html.push_str("<tr>");
+                        for cell in row {
+                            html.push_str(&format!("<td>{}</td>", escape_html(&cell)));
+                        }
+                        html.push_str("</tr>\n");
+                    }
+                    html.push_str("</table>\n");
+                }
+            }
+        }
+    }
+    html.push_str("</body>\n</html>\n");
+    html
+}
+
+fn report_sections(factsheet: &FactSheet, report: &ProjectReport) -> Vec<Section> {
+    vec![
+        summary_section(report),
+        goal_section(factsheet),
+        scope_section(factsheet.project_scope.as_ref()),
+        external_urls_section(factsheet),
+        input_checks_section(factsheet),
+        agents_section(&report.stages),
+        endpoints_section(factsheet),
+    ]
+}
+
+fn summary_section(report: &ProjectReport) -> Section {
+    let usage: LlmUsage = report.usage();
+    let outcome: &str = if report.succeeded() {
+        "succeeded"
+    } else {
+        "failed"
+    };
+    Section {
+        title: "Summary",
+        blocks: vec![Block::List(vec![
+            format!("Outcome: {}", outcome),
+            format!("Duration: {}", format_duration(report.duration_ms)),
+            format!("LLM calls: {}", usage.calls),
+            format!(
+                "Tokens: {} ({} prompt, {} completion)",
+                usage.total_tokens(),
+                usage.prompt_tokens,
+                usage.completion_tokens
+            ),
+            format!("Cost: {}", format_cost(&usage)),
+        ])],
+    }
+}
+
+fn goal_section(factsheet: &FactSheet) -> Section {
+    let mut blocks: Vec<Block> = vec![Block::Paragraph(factsheet.project_description.clone())];
+    if !factsheet.clarifications.is_empty() {
+        blocks.push(Block::Subheading("Clarifications".to_string()));
+        blocks.push(Block::List(
+            factsheet
+                .clarifications
+                .iter()
+                .map(|clarification| {
+                    format!("{} → {}", clarification.question, clarification.answer)
+                })
+                .collect(),
+        ));
+    }
+    if !factsheet.requirements.is_empty() {
+        blocks.push(Block::Subheading("Requirements".to_string()));
+        blocks.push(Block::List(factsheet.requirements.clone()));
+    }
+    if !factsheet.non_functional_requirements.is_empty() {
+        blocks.push(Block::Subheading("Non-functional requirements".to_string()));
+        blocks.push(Block::List(factsheet.non_functional_requirements.clone()));
+    }
+    Section {
+        title: "Goal",
+        blocks,
+    }
+}
+
+fn scope_section(scope: Option<&ProjectScope>) -> Section {
+    let Some(scope) = scope else {
+        return Section {
+            title: "Scope",
+            blocks: vec![Block::Paragraph("No scope was determined.".to_string())],
+        };
+    };
+
+    let mut blocks: Vec<Block> = vec![Block::Table {
+        headers: vec!["Flag", "Value"],
+        rows: vec![
+            vec!["CRUD required".to_string(), yes_no(scope.is_crud_required)],
+            vec![
+                "User login and logout".to_string(),
+                yes_no(scope.is_user_login_and_logout),
+            ],
+            vec![
+                "External URLs required".to_string(),
+                yes_no(scope.is_external_urls_required),
+            ],
+            vec![
+                "Features".to_string(),
+                match scope.features.enabled().as_slice() {
+                    [] => "none".to_string(),
+                    features => features.join(", "),
+                },
+            ],
+        ],
+    }];
+    if !scope.entities.is_empty() {
+        blocks.push(Block::Subheading("Entities".to_string()));
+        blocks.push(Block::List(
+            scope
+                .entities
+                .iter()
+                .map(|entity| entity.to_string())
+                .collect(),
+        ));
+    }
+    Section {
+        title: "Scope",
+        blocks,
+    }
+}
+
+fn external_urls_section(factsheet: &FactSheet) -> Section {
+    let passed = factsheet
+        .external_urls
+        .iter()
+        .flatten()
+        .map(|url| {
+            let latency: Option<u64> = factsheet
+                .external_api_samples
+                .iter()
+                .find(|sample| &sample.url == url)
+                .map(|sample| sample.latency_ms);
+            let result: String = match latency {
+                Some(latency_ms) => format!("passed in {} ms", latency_ms),
+                None => "passed".to_string(),
+            };
+            vec![url.clone(), result]
+        });
+    let excluded = factsheet.excluded_urls.iter().map(|excluded| {
+        vec![
+            excluded.url.clone(),
+            format!("excluded: {}", excluded.reason),
+        ]
+    });
+    let rows: Vec<Vec<String>> = passed.chain(excluded).collect();
+
+    let block: Block = if rows.is_empty() {
+        Block::Paragraph("No external URLs were used.".to_string())
+    } else {
+        Block::Table {
+            headers: vec!["URL", "Result"],
+            rows,
+        }
+    };
+    Section {
+        title: "External URLs",
+        blocks: vec![block],
+    }
+}
+
+fn input_checks_section(factsheet: &FactSheet) -> Section {
+    let block: Block = if factsheet.injection_findings.is_empty() {
+        Block::Paragraph("No prompt injection patterns were found in the input.".to_string())
+    } else {
+        Block::Table {
+            headers: vec!["Source", "Pattern", "Excerpt"],
+            rows: factsheet
+                .injection_findings
+                .iter()
+                .map(|finding| {
+                    vec![
+                        finding.source.clone(),
+                        finding.kind.to_string(),
+                        finding.excerpt.clone(),
+                    ]
+                })
+                .collect(),
+        }
+    };
+    Section {
+        title: "Input checks",
+        blocks: vec![block],
+    }
+}
+
+fn agents_section(stages: &[StageReport]) -> Section {
+    let mut blocks: Vec<Block> = vec![Block::Table {
+        headers: vec![
+            "Stage",
+            "Agent",
+            "Finished",
+            "Iterations",
+            "Duration",
+            "Tokens",
+            "Cost",
+        ],
+        rows: stages
+            .iter()
+            .map(|stage| {
+                vec![
+                    stage.stage.clone(),
+                    stage.agent.clone(),
+                    yes_no(stage.finished),
+                    stage.iterations.len().to_string(),
+                    format_duration(stage.duration_ms),
+                    stage.usage.total_tokens().to_string(),
+                    format_cost(&stage.usage),
+                ]
+            })
+            .collect(),
+    }];
+
+    for stage in stages {
+        if stage.issues.is_empty() && stage.iterations.is_empty() {
+            continue;
+        }
+        blocks.push(Block::Subheading(format!(
+            "{} ({})",
+            stage.stage, stage.agent
+        )));
+        if !stage.issues.is_empty() {
+            blocks.push(Block::List(stage.issues.clone()));
+        }
+        if !stage.iterations.is_empty() {
+            blocks.push(Block::Table {
+                headers: vec!["#", "Version", "Compiled", "Errors"],
+                rows: stage
+                    .iterations
+                    .iter()
+                    .map(|iteration| {
+                        vec![
+                            iteration.number.to_string(),
+                            iteration.label.clone(),
+                            iteration
+                                .compiled
+                                .map(yes_no)
+                                .unwrap_or_else(|| "not built".to_string()),
+                            iteration.error_count.to_string(),
+                        ]
+                    })
+                    .collect(),
+            });
+        }
+    }
+    Section {
+        title: "Agents",
+        blocks,
+    }
+}
+
+fn endpoints_section(factsheet: &FactSheet) -> Section {
+    let endpoints = factsheet.api_endpoint_schema.iter().flatten();
+    let rows: Vec<Vec<String>> = endpoints
+        .map(|endpoint| {
+            let result: &str = match &factsheet.endpoint_test_report {
+                Some(tests) if tests.passed.contains(&endpoint.route) => "passed",
+                Some(tests) if tests.failed.contains(&endpoint.route) => "failed",
+                _ => "not tested",
+            };
+            vec![
+                endpoint
+                    .method
+                    .map_or("?".to_string(), |method| method.to_string().to_uppercase()),
+                endpoint.route.clone(),
+                result.to_string(),
+            ]
+        })
+        .collect();
+
+    let block: Block = if rows.is_empty() {
+        Block::Paragraph("No endpoints were extracted.".to_string())
+    } else {
+        Block::Table {
+            headers: vec!["Method", "Route", "Test"],
+            rows,
+        }
+    };
+    Section {
+        title: "Endpoints",
+        blocks: vec![block],
+    }
+}
+
+fn yes_no(value: bool) -> String {
+    if value { "yes" } else { "no" }.to_string()
+}
+
+fn format_duration(duration_ms: u64) -> String {
+    match duration_ms {
+        0..1_000 => format!("{} ms", duration_ms),
+        1_000..60_000 => format!("{:.1} s", duration_ms as f64 / 1_000.0),
+        _ => format!(
+            "{}m {:02}s",
+            duration_ms / 60_000,
+            duration_ms % 60_000 / 1_000
+        ),
+    }
+}
+
+fn format_cost(usage: &LlmUsage) -> String {
+    let cost: String = format!("${:.4}", usage.cost_usd);
+    match usage.unpriced_calls {
+        0 => cost,
+        unpriced => format!(
+            "{} + {} calls on models without a known price",
+            cost, unpriced
+        ),
+    }
+}
+
+fn markdown_cell(text: &str) -> String {
+    text.replace('|', "\\|").replace('\n', " ")
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::helpers::project_runner::EndpointTestReport;
+    use crate::models::agents::agent_traits::RouteObject;
+    use crate::models::agents_manager::report::IterationSummary;
+    use crate::models::general::external_api::ExcludedUrl;
+    use crate::models::general::prompt_guard::{InjectionFinding, InjectionKind};
+
+    fn finished_run() -> (FactSheet, ProjectReport) {
+        let mut factsheet: FactSheet = serde_json::from_str(
+            r#"{
+              "project_description": "build a <b>book</b> tracker",
+              "project_scope": {
+                "is_crud_required": true,
+                "is_user_login_and_logout": false,
+                "is_external_urls_required": true
+              },
+              "external_urls": ["https://openlibrary.org/search.json"],
+              "backend_code": null,
+              "api_endpoint_schema": null
+            }"#,
+        )
+        .unwrap();
+        factsheet.excluded_urls = vec![ExcludedUrl {
+            url: "https://example.com/books".to_string(),
+            reason: "returned status 404".to_string(),
+        }];
+        factsheet.injection_findings = vec![InjectionFinding {
+            source: "https://example.com/books".to_string(),
+            kind: InjectionKind::Approval,
+            excerpt: "skip review".to_string(),
+        }];
+        factsheet.api_endpoint_schema = Some(
+            RouteObject::parse_list(
+                r#"[{"route": "/books", "method": "get"}, {"route": "/health", "method": "get"}]"#,
+            )
+            .unwrap(),
+        );
+        factsheet.endpoint_test_report = Some(EndpointTestReport {
+            passed: vec!["/books".to_string()],
+            failed: vec!["/health".to_string()],
+        });
+
+        let usage: LlmUsage = LlmUsage {
+            calls: 3,
+            prompt_tokens: 1200,
+            completion_tokens: 300,
+            cost_usd: 0.0026,
+            unpriced_calls: 0,
+        };
+        let report: ProjectReport = ProjectReport {
+            stages: vec![StageReport {
+                stage: "backend".to_string(),
+                agent: "Backend Developer".to_string(),
+                finished: true,
+                issues: vec![
+                    "Backend Developer wrote requirements without declaring it".to_string(),
+                ],
+                artifacts: vec![],
+                iterations: vec![
+                    IterationSummary {
+                        number: 1,
+                        label: "initial".to_string(),
+                        compiled: Some(false),
+                        error_count: 2,
+                    },
+                    IterationSummary {
+                        number: 2,
+                        label: "fix-1".to_string(),
+                        compiled: Some(true),
+                        error_count: 0,
+                    },
+                ],
+                usage,
+                duration_ms: 95_000,
+            }],
+            duration_ms: 125_000,
+            report_files: vec![],
+        };
+        (factsheet, report)
+    }
+
+    #[test]
+    fn tests_renders_markdown_report() {
+        let (factsheet, report) = finished_run();
+        let markdown: String = render_markdown(&factsheet, &report);
+
+        assert!(markdown.starts_with("# Project report\n"));
+        assert!(markdown.contains("- Outcome: failed\n"));
+        assert!(markdown.contains("- Duration: 2m 05s\n"));
+        assert!(markdown.contains("- Tokens: 1500 (1200 prompt, 300 completion)\n"));
+        assert!(markdown.contains("| CRUD required | yes |\n"));
+        assert!(markdown.contains("| https://openlibrary.org/search.json | passed |\n"));
+        assert!(
+            markdown.contains("| https://example.com/books | excluded: returned status 404 |\n")
+        );
+        assert!(
+            markdown
+                .contains("| backend | Backend Developer | yes | 2 | 1m 35s | 1500 | $0.0026 |\n")
+        );
+        assert!(markdown.contains("| https://example.com/books | approval | skip review |\n"));
+        assert!(markdown.contains("| 1 | initial | no | 2 |\n"));
+        assert!(markdown.contains("| GET | /books | passed |\n"));
+        assert!(markdown.contains("| GET | /health | failed |\n"));
+    }
+
+    #[test]
+    fn tests_writes_self_contained_html_report() {
+        let (factsheet, report) = finished_run();
+        let run_dir: PathBuf = std::env::temp_dir().join("rust-backend-gen-agents-report-test");
+        let _ = fs::remove_dir_all(&run_dir);
+
+        let files: Vec<PathBuf> = write_run_report(&run_dir, &factsheet, &report).unwrap();
+        assert_eq!(
+            files,
+            vec![
+                run_dir.join(REPORT_MARKDOWN_FILE),
+                run_dir.join(REPORT_HTML_FILE)
+            ]
+        );
+
+        let html: String = fs::read_to_string(run_dir.join(REPORT_HTML_FILE)).unwrap();
+        assert!(html.contains("<style>"));
+        assert!(!html.contains("<link") && !html.contains("<script"));
+        assert!(html.contains("<p>build a &lt;b&gt;book&lt;/b&gt; tracker</p>"));
+        assert!(
+            html.contains(
+                "<td>https://example.com/books</td><td>excluded: returned status 404</td>"
+            )
+        );
+    }
+}
@@ -0,0 +1,264 @@
+use crate::dashboard::layer::DashboardLayer;
+use crate::dashboard::state::DashboardState;
+use crate::helpers::command_line::PrintCommand;
+use std::fmt;
+use std::fs::{self, File, OpenOptions};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+use strum_macros::{Display, EnumString};
+use tracing::field::{Field, Visit};
+use tracing::{Event, Subscriber};
+use tracing_subscriber::fmt::format::FmtSpan;
+use tracing_subscriber::fmt::MakeWriter;
+use tracing_subscriber::layer::{Context, Layer, SubscriberExt};
+use tracing_subscriber::registry::LookupSpan;
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::EnvFilter;
+
+/// Target of the events `PrintCommand::print_agent_message` emits
+pub const AGENT_MESSAGE_TARGET: &str = "agent_message";
+
+/// JSON-lines log written when no `--log-file` is given
+pub const DEFAULT_LOG_FILE: &str = "./runs/agent_log.jsonl";
+
+/// Where tracing output goes, selected with `--log-format`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Display, EnumString)]
+#[strum(serialize_all = "lowercase", ascii_case_insensitive)]
+pub enum LogFormat {
+    /// Colored agent messages on the console, as before
+    #[default]
+    Pretty,
+    /// Every span and event as JSON lines in the log file
+    Json,
+    /// Console messages and the JSON-lines file
+    Both,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct LogSettings {
+    pub format: LogFormat,
+    pub file: PathBuf,
+    /// Show the full-screen dashboard instead of console messages
+    pub tui: bool,
+}
+
+impl Default for LogSettings {
+    fn default() -> Self {
+        Self {
+            format: LogFormat::default(),
+            file: PathBuf::from(DEFAULT_LOG_FILE),
+            tui: false,
+        }
+    }
+}
+
+impl LogSettings {
+    /// Takes `--log-format <pretty|json|both>`, `--log-file <path>` and `--tui` out of the CLI args
+    pub fn from_args(args: &mut Vec<String>) -> Result<Self, String> {
+        let mut settings: LogSettings = LogSettings::default();
+        let mut remaining: Vec<String> = vec![];
+        let mut arg_iter = args.drain(..);
+
+        while let Some(arg) = arg_iter.next() {
+            match arg.as_str() {
+                "--log-format" => {
+                    let format: String = arg_iter
+                        .next()
+                        .ok_or("--log-format needs one of pretty, json or both")?;
+                    settings.format = LogFormat::from_str(&format)
+                        .map_err(|_| format!("Unknown log format '{}'", format))?;
+                }
+                "--log-file" => {
+                    settings.file = arg_iter.next().ok_or("--log-file needs a path")?.into();
+                }
+                "--tui" => settings.tui = true,
+                _ => remaining.push(arg),
+            }
+        }
+
+        drop(arg_iter);
+        *args = remaining;
+        Ok(settings)
+    }
+
+    fn writes_console(&self) -> bool {
+        self.format != LogFormat::Json && !self.tui
+    }
+
+    fn writes_json(&self) -> bool {
+        self.format != LogFormat::Pretty
+    }
+}
+
+/// Installs the global tracing subscriber, filtered by RUST_LOG and defaulting to info
+pub fn init_logging(
+    settings: &LogSettings,
+    dashboard_state: Option<Arc<Mutex<DashboardState>>>,
+) -> std::io::Result<()> {
+    let console_layer: Option<AgentConsoleLayer> =
+        settings.writes_console().then_some(AgentConsoleLayer);
+
+    let json_layer = if settings.writes_json() {
+        let log_file: File = open_log_file(&settings.file)?;
+        Some(json_layer(Mutex::new(log_file)))
+    } else {
+        None
+    };
+
+    let filter: EnvFilter =
+        EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("info"));
+
+    tracing_subscriber::registry()
+        .with(filter)
+        .with(console_layer)
+        .with(json_layer)
+        .with(dashboard_state.map(DashboardLayer::new))
+        .init();
+    Ok(())
+}
+
+/// JSON-lines layer recording every event with its spans, and each span with its duration on close
+fn json_layer<S, W>(make_writer: W) -> impl Layer<S>
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+    W: for<'w> MakeWriter<'w> + Send + Sync + 'static,
+{
+    tracing_subscriber::fmt::layer()
+        .json()
+        .with_current_span(true)
+        .with_span_list(true)
+        .with_span_events(FmtSpan::CLOSE)
+        .with_writer(make_writer)
+}
+
+fn open_log_file(path: &Path) -> std::io::Result<File> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    OpenOptions::new().create(true).append(true).open(path)
+}
+
+/// Renders agent message events with the colored console format
+struct AgentConsoleLayer;
+
+impl<S: Subscriber> Layer<S> for AgentConsoleLayer {
+    fn on_event(&self, event: &Event<'_>, _ctx: Context<'_, S>) {
+        if event.metadata().target() != AGENT_MESSAGE_TARGET {
+            return;
+        }
+
+        let mut fields: AgentMessageFields = AgentMessageFields::default();
+        event.record(&mut fields);
+
+        let print_command: PrintCommand =
+            PrintCommand::from_str(&fields.kind).unwrap_or(PrintCommand::AICall);
+        print_command.write_agent_message(&fields.agent, &fields.message);
+    }
+}
+
+#[derive(Default)]
+struct AgentMessageFields {
+    agent: String,
+    kind: String,
+    message: String,
+}
+
+impl Visit for AgentMessageFields {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        match field.name() {
+            "agent" => self.agent = value.to_string(),
+            "kind" => self.kind = value.to_string(),
+            "message" => self.message = value.to_string(),
+            _ => {}
+        }
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        self.record_str(field, &format!("{:?}", value));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(list: &[&str]) -> Vec<String> {
+        list.iter().map(|arg| arg.to_string()).collect()
+    }
+
+    #[test]
+    fn tests_takes_log_flags_from_args() {
+        let mut cli_args: Vec<String> = args(&[
+            "agents",
+            "--log-format",
+            "JSON",
+            "migrate-schema",
+            "--log-file",
+            "/tmp/agents.jsonl",
+            "--tui",
+        ]);
+        let settings: LogSettings = LogSettings::from_args(&mut cli_args).unwrap();
+
+        assert_eq!(settings.format, LogFormat::Json);
+        assert_eq!(settings.file, PathBuf::from("/tmp/agents.jsonl"));
+        assert!(settings.tui && !settings.writes_console());
+        assert_eq!(cli_args, args(&["agents", "migrate-schema"]));
+
+        let mut cli_args: Vec<String> = args(&["agents"]);
+        assert_eq!(
+            LogSettings::from_args(&mut cli_args).unwrap(),
+            LogSettings::default()
+        );
+    }
+
+    #[derive(Clone, Default)]
+    struct SharedBuffer(std::sync::Arc<Mutex<Vec<u8>>>);
+
+    impl std::io::Write for SharedBuffer {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn tests_writes_agent_messages_as_json_lines() {
+        let buffer: SharedBuffer = SharedBuffer::default();
+        let writer: SharedBuffer = buffer.clone();
+        let subscriber = tracing_subscriber::registry()
+            .with(AgentConsoleLayer)
+            .with(json_layer(move || writer.clone()));
+
+        tracing::subscriber::with_default(subscriber, || {
+            let _span = tracing::info_span!("agent_execution", agent = "Backend Developer").entered();
+            PrintCommand::Issue.print_agent_message("Backend Developer", "Too many bugs");
+        });
+
+        let output: String = String::from_utf8(buffer.0.lock().unwrap().clone()).unwrap();
+        let lines: Vec<serde_json::Value> = output
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+
+        let message: &serde_json::Value = &lines[0];
+        assert_eq!(message["level"], "WARN");
+        assert_eq!(message["target"], AGENT_MESSAGE_TARGET);
+        assert_eq!(message["fields"]["message"], "Too many bugs");
+        assert_eq!(message["fields"]["kind"], "Issue");
+        assert_eq!(message["span"]["name"], "agent_execution");
+        assert_eq!(lines[1]["fields"]["message"], "close");
+    }
+
+    #[test]
+    fn tests_rejects_unknown_log_format() {
+        let mut cli_args: Vec<String> = args(&["agents", "--log-format", "xml"]);
+        assert!(LogSettings::from_args(&mut cli_args).is_err());
+
+        let mut cli_args: Vec<String> = args(&["agents", "--log-file"]);
+        assert!(LogSettings::from_args(&mut cli_args).is_err());
+    }
+}
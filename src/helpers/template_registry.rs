@@ -0,0 +1,345 @@
+use crate::models::agents::agent_traits::ProjectScope;
+use crate::models::general::framework::TargetFramework;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use strum_macros::{Display, EnumString};
+
+/// Directory holding the built-in code templates
+pub const BUILTIN_TEMPLATE_DIR: &str = "./template/";
+
+/// Header line listing the tags a template covers, e.g. `// template-tags: crud, auth`
+const TAGS_HEADER: &str = "// template-tags:";
+
+/// Header line marking a template as a fragment to compose into a full template
+const KIND_HEADER: &str = "// template-kind:";
+
+/// Header line naming the web framework of a template, actix-web when missing
+const FRAMEWORK_HEADER: &str = "// template-framework:";
+
+/// Capability a code template demonstrates
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Display, EnumString)]
+#[strum(serialize_all = "kebab-case", ascii_case_insensitive)]
+pub enum TemplateTag {
+    Crud,
+    Auth,
+    Proxy,
+    Websocket,
+    FileUpload,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TemplateKind {
+    /// A complete webserver the backend developer can start from
+    Full,
+    /// A snippet showing one capability, composed into a full template
+    Fragment,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct CodeTemplate {
+    pub name: String,
+    pub tags: Vec<TemplateTag>,
+    pub kind: TemplateKind,
+    pub framework: TargetFramework,
+    pub contents: String,
+}
+
+impl CodeTemplate {
+    /// Reads a template file, taking its metadata from the header comments
+    pub fn from_file(path: &Path) -> std::io::Result<Self> {
+        let raw: String = fs::read_to_string(path)?;
+        let name: String = path
+            .file_stem()
+            .and_then(|stem| stem.to_str())
+            .unwrap_or("template")
+            .to_string();
+        Ok(Self::parse(name, &raw))
+    }
+
+    /// Splits the metadata header from the template code
+    pub fn parse(name: String, raw: &str) -> Self {
+        let mut tags: Vec<TemplateTag> = vec![];
+        let mut kind: TemplateKind = TemplateKind::Full;
+        let mut framework: TargetFramework = TargetFramework::default();
+        let mut body_lines: Vec<&str> = vec![];
+
+        for line in raw.lines() {
+            let trimmed: &str = line.trim();
+            if let Some(tag_list) = trimmed.strip_prefix(TAGS_HEADER) {
+                tags.extend(
+                    tag_list
+                        .split(',')
+                        .filter_map(|tag| TemplateTag::from_str(tag.trim()).ok()),
+                );
+            } else if let Some(kind_name) = trimmed.strip_prefix(KIND_HEADER) {
+                if kind_name.trim().eq_ignore_ascii_case("fragment") {
+                    kind = TemplateKind::Fragment;
+                }
+            } else if let Some(framework_name) = trimmed.strip_prefix(FRAMEWORK_HEADER) {
+                framework = TargetFramework::from_str(framework_name.trim()).unwrap_or_default();
+            } else {
+                body_lines.push(line);
+            }
+        }
+
+        Self {
+            name,
+            tags,
+            kind,
+            framework,
+            contents: body_lines.join("\n").trim_start().to_string(),
+        }
+    }
+
+    fn covered(&self, required: &[TemplateTag]) -> usize {
+        required
+            .iter()
+            .filter(|tag| self.tags.contains(tag))
+            .count()
+    }
+
+    fn extra(&self, required: &[TemplateTag]) -> usize {
+        self.tags
+            .iter()
+            .filter(|tag| !required.contains(tag))
+            .count()
+    }
+}
+
+/// Templates chosen for a project: one full template plus fragments for uncovered tags
+#[derive(Debug, Clone, PartialEq)]
+pub struct TemplateSelection {
+    pub primary: CodeTemplate,
+    pub fragments: Vec<CodeTemplate>,
+    /// Required tags no template of the framework covers
+    pub uncovered: Vec<TemplateTag>,
+}
+
+impl TemplateSelection {
+    /// Joins the primary template and its fragments into one prompt section
+    pub fn compose(&self) -> String {
+        let mut composed: String = self.primary.contents.clone();
+        for fragment in &self.fragments {
+            let tags: Vec<String> = fragment.tags.iter().map(TemplateTag::to_string).collect();
+            composed.push_str(&format!(
+                "\n\n// FRAGMENT '{}' ({}): merge this into the code above where needed\n{}",
+                fragment.name,
+                tags.join(", "),
+                fragment.contents
+            ));
+        }
+        composed
+    }
+
+    pub fn names(&self) -> Vec<&str> {
+        std::iter::once(&self.primary)
+            .chain(&self.fragments)
+            .map(|template| template.name.as_str())
+            .collect()
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct TemplateRegistry {
+    templates: Vec<CodeTemplate>,
+}
+
+impl TemplateRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registry with the built-in templates and the templates in the given directories
+    pub fn with_directories(template_dirs: &[PathBuf]) -> std::io::Result<Self> {
+        let mut registry: TemplateRegistry = TemplateRegistry::new();
+        registry.load_directory(Path::new(BUILTIN_TEMPLATE_DIR))?;
+
+        for template_dir in template_dirs {
+            registry.load_directory(template_dir)?;
+        }
+        Ok(registry)
+    }
+
+    pub fn register(&mut self, template: CodeTemplate) {
+        // A template registered later under the same name replaces the earlier one
+        self.templates
+            .retain(|existing| existing.name != template.name);
+        self.templates.push(template);
+    }
+
+    /// Registers every `.rs` file in a directory and its subdirectories
+    pub fn load_directory(&mut self, dir: &Path) -> std::io::Result<usize> {
+        let mut paths: Vec<PathBuf> = fs::read_dir(dir)?
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .collect();
+        paths.sort();
+
+        let mut loaded: usize = 0;
+        for path in paths {
+            if path.is_dir() {
+                loaded += self.load_directory(&path)?;
+            } else if path.extension().is_some_and(|extension| extension == "rs") {
+                self.register(CodeTemplate::from_file(&path)?);
+                loaded += 1;
+            }
+        }
+        Ok(loaded)
+    }
+
+    /// Tags the generated backend needs according to the project scope
+    pub fn required_tags(scope: &ProjectScope) -> Vec<TemplateTag> {
+        [
+            (scope.is_crud_required, TemplateTag::Crud),
+            (scope.is_user_login_and_logout, TemplateTag::Auth),
+            (scope.is_external_urls_required, TemplateTag::Proxy),
+            (scope.features.realtime, TemplateTag::Websocket),
+            (scope.features.file_upload, TemplateTag::FileUpload),
+        ]
+        .into_iter()
+        .filter_map(|(is_required, tag)| is_required.then_some(tag))
+        .collect()
+    }
+
+    /// Picks the full template covering most required tags, then fragments for the rest
+    pub fn select(
+        &self,
+        framework: TargetFramework,
+        required: &[TemplateTag],
+    ) -> Option<TemplateSelection> {
+        let candidates: Vec<&CodeTemplate> = self
+            .templates
+            .iter()
+            .filter(|template| template.framework == framework)
+            .collect();
+
+        let primary: &CodeTemplate = candidates
+            .iter()
+            .copied()
+            .filter(|template| template.kind == TemplateKind::Full)
+            .min_by_key(|template| {
+                (
+                    std::cmp::Reverse(template.covered(required)),
+                    template.extra(required),
+                )
+            })?;
+
+        let mut uncovered: Vec<TemplateTag> = required
+            .iter()
+            .filter(|tag| !primary.tags.contains(tag))
+            .copied()
+            .collect();
+
+        let mut fragments: Vec<CodeTemplate> = vec![];
+        while !uncovered.is_empty() {
+            let Some(best) = candidates
+                .iter()
+                .copied()
+                .filter(|template| template.name != primary.name && !fragments.contains(template))
+                .filter(|template| template.covered(&uncovered) > 0)
+                .max_by_key(|template| {
+                    (
+                        template.covered(&uncovered),
+                        template.kind == TemplateKind::Fragment,
+                    )
+                })
+            else {
+                break;
+            };
+
+            uncovered.retain(|tag| !best.tags.contains(tag));
+            fragments.push(best.clone());
+        }
+
+        Some(TemplateSelection {
+            primary: primary.clone(),
+            fragments,
+            uncovered,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn registry() -> TemplateRegistry {
+        let mut registry: TemplateRegistry = TemplateRegistry::new();
+        registry.register(CodeTemplate::parse(
+            "crud_auth".to_string(),
+            "// template-tags: crud, auth\nfn crud() {}",
+        ));
+        registry.register(CodeTemplate::parse(
+            "proxy".to_string(),
+            "// template-tags: proxy\nfn proxy() {}",
+        ));
+        registry.register(CodeTemplate::parse(
+            "upload".to_string(),
+            "// template-tags: file-upload\n// template-kind: fragment\nfn upload() {}",
+        ));
+        registry
+    }
+
+    #[test]
+    fn tests_parses_template_headers() {
+        let template: CodeTemplate = CodeTemplate::parse(
+            "upload".to_string(),
+            "// template-tags: file-upload, Auth\n// template-kind: fragment\nfn upload() {}",
+        );
+        assert_eq!(
+            template.tags,
+            vec![TemplateTag::FileUpload, TemplateTag::Auth]
+        );
+        assert_eq!(template.kind, TemplateKind::Fragment);
+        assert_eq!(template.contents, "fn upload() {}");
+    }
+
+    #[test]
+    fn tests_selects_and_composes_templates() {
+        let selection: TemplateSelection = registry()
+            .select(TargetFramework::ActixWeb, &[TemplateTag::Proxy, TemplateTag::FileUpload])
+            .unwrap();
+        assert_eq!(selection.names(), vec!["proxy", "upload"]);
+        assert!(selection.uncovered.is_empty());
+        assert!(
+            selection
+                .compose()
+                .contains("// FRAGMENT 'upload' (file-upload)")
+        );
+
+        let selection: TemplateSelection = registry()
+            .select(TargetFramework::ActixWeb, &[TemplateTag::Crud, TemplateTag::Auth, TemplateTag::Proxy])
+            .unwrap();
+        assert_eq!(selection.names(), vec!["crud_auth", "proxy"]);
+
+        let selection: TemplateSelection = registry()
+            .select(TargetFramework::ActixWeb, &[TemplateTag::Crud, TemplateTag::Websocket])
+            .unwrap();
+        assert_eq!(selection.names(), vec!["crud_auth"]);
+        assert_eq!(selection.uncovered, vec![TemplateTag::Websocket]);
+    }
+
+    #[test]
+    fn tests_loads_builtin_templates() {
+        let mut registry: TemplateRegistry = TemplateRegistry::new();
+        let loaded: usize = registry
+            .load_directory(Path::new(BUILTIN_TEMPLATE_DIR))
+            .unwrap();
+        assert!(loaded >= 2);
+
+        let selection: TemplateSelection = registry
+            .select(TargetFramework::ActixWeb, &[TemplateTag::Proxy])
+            .unwrap();
+        assert_eq!(selection.primary.name, "main");
+
+        for framework in [TargetFramework::ActixWeb, TargetFramework::Axum, TargetFramework::Rocket] {
+            let selection: TemplateSelection = registry
+                .select(framework, &[TemplateTag::Crud, TemplateTag::Auth, TemplateTag::FileUpload])
+                .unwrap();
+            assert_eq!(selection.primary.framework, framework);
+            assert_eq!(selection.fragments.len(), 1);
+            assert!(selection.fragments[0].tags.contains(&TemplateTag::FileUpload));
+            assert!(selection.uncovered.is_empty());
+        }
+    }
+}
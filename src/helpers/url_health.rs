@@ -0,0 +1,196 @@
+use crate::helpers::general::fetch_external_sample;
+use crate::helpers::url_policy::UrlPolicy;
+use crate::models::general::external_api::{ExcludedUrl, ExternalApiSample};
+use futures_util::stream::{self, StreamExt};
+use std::time::Instant;
+
+/// External URLs checked at the same time unless URL_CHECK_CONCURRENCY says otherwise
+pub const DEFAULT_URL_CHECK_CONCURRENCY: usize = 4;
+
+/// Result of checking one external URL
+#[derive(Debug, Clone, PartialEq)]
+pub enum UrlHealth {
+    /// Answered with JSON the generated code can use
+    Healthy(ExternalApiSample),
+    Excluded(ExcludedUrl),
+}
+
+/// Checks every URL, at most `concurrency` at a time, keeping the order of `urls`
+pub async fn check_external_urls(
+    policy: &UrlPolicy,
+    urls: &[String],
+    concurrency: usize,
+) -> Vec<UrlHealth> {
+    // Owned URLs keep the stream Send inside async trait methods
+    stream::iter(urls.to_vec())
+        .map(|url: String| async move { check_external_url(policy, &url).await })
+        .buffered(concurrency.max(1))
+        .collect()
+        .await
+}
+
+/// Fetches a URL through the policy, following redirects, and judges the response
+pub async fn check_external_url(policy: &UrlPolicy, url: &str) -> UrlHealth {
+    let started_at: Instant = Instant::now();
+    let result: Result<ExternalApiSample, String> = fetch_external_sample(policy, url)
+        .await
+        .and_then(|mut sample| {
+            sample.latency_ms = started_at.elapsed().as_millis() as u64;
+            unusable_reason(&sample).map_or(Ok(sample), Err)
+        });
+
+    match result {
+        Ok(sample) => UrlHealth::Healthy(sample),
+        Err(reason) => UrlHealth::Excluded(ExcludedUrl {
+            url: url.to_string(),
+            reason,
+        }),
+    }
+}
+
+/// Why the generated code could not rely on this response, None when it can
+pub fn unusable_reason(sample: &ExternalApiSample) -> Option<String> {
+    let content_type: &str = sample.content_type.as_deref().unwrap_or("no content type");
+    match sample.status_code {
+        401 | 403 => Some(format!(
+            "requires authentication (status {})",
+            sample.status_code
+        )),
+        200..=299 if content_type.starts_with("text/html") => {
+            Some("returned an HTML page instead of JSON".to_string())
+        }
+        200..=299 if sample.schema.is_none() => {
+            Some(format!("response is not valid JSON ({})", content_type))
+        }
+        200..=299 => None,
+        status_code => Some(format!("returned status {}", status_code)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::Router;
+    use axum::http::{StatusCode, header};
+    use axum::response::Redirect;
+    use axum::routing::get;
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::time::Duration;
+    use tokio::net::TcpListener;
+
+    const JSON: [(header::HeaderName, &str); 1] = [(header::CONTENT_TYPE, "application/json")];
+
+    async fn start_api(in_flight: Arc<AtomicUsize>, max_in_flight: Arc<AtomicUsize>) -> String {
+        let router: Router = Router::new()
+            .route("/books", get(|| async { (JSON, r#"[{"title": "Dune"}]"#) }))
+            .route(
+                "/created",
+                get(|| async { (StatusCode::CREATED, JSON, r#"{"id": 1}"#) }),
+            )
+            .route("/moved", get(|| async { Redirect::permanent("/books") }))
+            .route(
+                "/login",
+                get(|| async {
+                    (
+                        [(header::CONTENT_TYPE, "text/html")],
+                        "<html>Sign in</html>",
+                    )
+                }),
+            )
+            .route("/private", get(|| async { StatusCode::UNAUTHORIZED }))
+            .route("/forbidden", get(|| async { StatusCode::FORBIDDEN }))
+            .route("/broken", get(|| async { (JSON, "{\"title\": ") }))
+            .route("/gone", get(|| async { StatusCode::NOT_FOUND }))
+            .route(
+                "/slow",
+                get(move || async move {
+                    let running: usize = in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+                    max_in_flight.fetch_max(running, Ordering::SeqCst);
+                    tokio::time::sleep(Duration::from_millis(50)).await;
+                    in_flight.fetch_sub(1, Ordering::SeqCst);
+                    (JSON, "[]")
+                }),
+            );
+
+        let listener: TcpListener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base: String = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, router).await.unwrap() });
+        base
+    }
+
+    #[tokio::test]
+    async fn tests_checks_urls_concurrently() {
+        let max_in_flight: Arc<AtomicUsize> = Arc::new(AtomicUsize::new(0));
+        let base: String = start_api(Arc::new(AtomicUsize::new(0)), max_in_flight.clone()).await;
+        let policy: UrlPolicy = UrlPolicy {
+            allow_hosts: vec!["127.0.0.1".to_string()],
+            ..UrlPolicy::default()
+        };
+
+        let paths: [&str; 8] = [
+            "/books",
+            "/created",
+            "/moved",
+            "/login",
+            "/private",
+            "/forbidden",
+            "/broken",
+            "/gone",
+        ];
+        let urls: Vec<String> = paths
+            .iter()
+            .map(|path| format!("{}{}", base, path))
+            .collect();
+        let checks: Vec<UrlHealth> = check_external_urls(&policy, &urls, 3).await;
+
+        let outcomes: Vec<String> = checks
+            .iter()
+            .map(|check| match check {
+                UrlHealth::Healthy(sample) => format!("healthy {}", sample.status_code),
+                UrlHealth::Excluded(excluded) => excluded.reason.clone(),
+            })
+            .collect();
+        assert_eq!(
+            outcomes,
+            vec![
+                "healthy 200",
+                "healthy 201",
+                "healthy 200",
+                "returned an HTML page instead of JSON",
+                "requires authentication (status 401)",
+                "requires authentication (status 403)",
+                "response is not valid JSON (application/json)",
+                "returned status 404",
+            ]
+        );
+        let UrlHealth::Healthy(moved) = &checks[2] else {
+            panic!("redirect was not followed");
+        };
+        assert_eq!(moved.url, urls[2]);
+        assert_eq!(moved.sample, r#"[{"title": "Dune"}]"#);
+
+        let slow_urls: Vec<String> = vec![format!("{}/slow", base); 6];
+        let slow_checks: Vec<UrlHealth> = check_external_urls(&policy, &slow_urls, 2).await;
+        assert!(
+            slow_checks.iter().all(
+                |check| matches!(check, UrlHealth::Healthy(sample) if sample.latency_ms >= 50)
+            )
+        );
+        assert!(max_in_flight.load(Ordering::SeqCst) <= 2);
+    }
+
+    #[tokio::test]
+    async fn tests_excludes_blocked_urls() {
+        let health: UrlHealth =
+            check_external_url(&UrlPolicy::default(), "http://169.254.169.254/latest").await;
+        assert_eq!(
+            health,
+            UrlHealth::Excluded(ExcludedUrl {
+                url: "http://169.254.169.254/latest".to_string(),
+                reason: "host '169.254.169.254' resolves to non-public address 169.254.169.254"
+                    .to_string(),
+            })
+        );
+    }
+}
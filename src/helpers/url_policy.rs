@@ -0,0 +1,398 @@
+use crate::models::general::settings::env_number;
+use reqwest::header::{CONTENT_TYPE, LOCATION};
+use reqwest::{Client, Response, Url, redirect};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::time::Duration;
+
+/// Largest response body read from an external URL
+pub const DEFAULT_MAX_RESPONSE_BYTES: usize = 2_000_000;
+
+/// Redirects followed before giving up on a URL
+pub const DEFAULT_MAX_REDIRECTS: usize = 5;
+
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Which external URLs the agents may call and how much they read back
+///
+/// URLs come from the model, so every host is resolved first and addresses outside the
+/// public internet are refused. Each redirect is checked again before it is followed.
+#[derive(Debug, Clone, PartialEq)]
+pub struct UrlPolicy {
+    /// Hosts that may resolve to private, loopback or link-local addresses, as exact names, IPs or `*.domain`
+    pub allow_hosts: Vec<String>,
+    /// Hosts that are never called, checked before the allow list
+    pub deny_hosts: Vec<String>,
+    pub max_response_bytes: usize,
+    pub max_redirects: usize,
+    pub timeout: Duration,
+}
+
+impl Default for UrlPolicy {
+    fn default() -> Self {
+        Self {
+            allow_hosts: vec![],
+            deny_hosts: vec![],
+            max_response_bytes: DEFAULT_MAX_RESPONSE_BYTES,
+            max_redirects: DEFAULT_MAX_REDIRECTS,
+            timeout: DEFAULT_TIMEOUT,
+        }
+    }
+}
+
+/// Response of a URL that passed the policy
+#[derive(Debug, Clone, PartialEq)]
+pub struct GuardedResponse {
+    /// URL after following redirects
+    pub url: String,
+    pub status_code: u16,
+    pub content_type: Option<String>,
+    pub body: String,
+}
+
+impl UrlPolicy {
+    /// Reads URL_ALLOWLIST and URL_DENYLIST as comma separated hosts, keeping defaults for anything missing
+    pub fn from_env() -> Self {
+        let mut policy: UrlPolicy = UrlPolicy {
+            allow_hosts: env_host_list("URL_ALLOWLIST"),
+            deny_hosts: env_host_list("URL_DENYLIST"),
+            ..UrlPolicy::default()
+        };
+        if let Some(max_response_bytes) = env_number::<usize>("URL_MAX_RESPONSE_BYTES") {
+            policy.max_response_bytes = max_response_bytes;
+        }
+        if let Some(max_redirects) = env_number::<usize>("URL_MAX_REDIRECTS") {
+            policy.max_redirects = max_redirects;
+        }
+        policy
+    }
+
+    /// Checks the scheme, the host lists and every resolved address, returning the addresses to connect to
+    pub async fn check(&self, url: &Url) -> Result<Vec<SocketAddr>, String> {
+        if !matches!(url.scheme(), "http" | "https") {
+            return Err(format!("scheme '{}' is not allowed", url.scheme()));
+        }
+        let host: &str = url.host_str().ok_or("URL has no host")?;
+        let host: &str = host.trim_start_matches('[').trim_end_matches(']');
+        if self
+            .deny_hosts
+            .iter()
+            .any(|pattern| host_matches(pattern, host))
+        {
+            return Err(format!("host '{}' is on the deny list", host));
+        }
+
+        let port: u16 = url.port_or_known_default().unwrap_or(80);
+        let addrs: Vec<SocketAddr> = tokio::net::lookup_host((host, port))
+            .await
+            .map_err(|e| format!("could not resolve '{}': {}", host, e))?
+            .collect();
+        if addrs.is_empty() {
+            return Err(format!("could not resolve '{}'", host));
+        }
+
+        let allowed: bool = self
+            .allow_hosts
+            .iter()
+            .any(|pattern| host_matches(pattern, host));
+        if let Some(blocked) = addrs
+            .iter()
+            .find(|addr| !allowed && !is_public_ip(addr.ip()))
+        {
+            return Err(format!(
+                "host '{}' resolves to non-public address {}",
+                host,
+                blocked.ip()
+            ));
+        }
+        Ok(addrs)
+    }
+
+    /// GETs a URL, following redirects that pass the policy and reading at most `max_response_bytes`
+    pub async fn get(&self, url: &str) -> Result<GuardedResponse, String> {
+        let mut url: Url = Url::parse(url).map_err(|e| format!("invalid URL: {}", e))?;
+        let mut redirects: usize = 0;
+
+        loop {
+            let addrs: Vec<SocketAddr> = self.check(&url).await?;
+            let host: String = url.host_str().unwrap_or_default().to_string();
+
+            // Connect to the addresses that were checked, a second lookup could answer differently,
+            // and never through a proxy from the environment, which would connect on our behalf
+            let client: Client = Client::builder()
+                .no_proxy()
+                .redirect(redirect::Policy::none())
+                .timeout(self.timeout)
+                .resolve_to_addrs(&host, &addrs)
+                .build()
+                .map_err(|e| e.to_string())?;
+            let response: Response = client
+                .get(url.clone())
+                .send()
+                .await
+                .map_err(|e| e.to_string())?;
+
+            if response.status().is_redirection()
+                && let Some(location) = response.headers().get(LOCATION)
+            {
+                if redirects == self.max_redirects {
+                    return Err(format!("more than {} redirects", self.max_redirects));
+                }
+                let location: &str = location.to_str().map_err(|e| e.to_string())?;
+                url = url
+                    .join(location)
+                    .map_err(|e| format!("invalid redirect: {}", e))?;
+                redirects += 1;
+                continue;
+            }
+
+            return self.read_response(url, response).await;
+        }
+    }
+
+    async fn read_response(
+        &self,
+        url: Url,
+        mut response: Response,
+    ) -> Result<GuardedResponse, String> {
+        let too_large: String =
+            format!("response is larger than {} bytes", self.max_response_bytes);
+        if response
+            .content_length()
+            .is_some_and(|length| length > self.max_response_bytes as u64)
+        {
+            return Err(too_large);
+        }
+
+        let status_code: u16 = response.status().as_u16();
+        let content_type: Option<String> = response
+            .headers()
+            .get(CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .map(str::to_string);
+
+        // Content-Length can be missing or wrong, so the cap also holds while reading
+        let mut body: Vec<u8> = vec![];
+        while let Some(chunk) = response.chunk().await.map_err(|e| e.to_string())? {
+            if body.len() + chunk.len() > self.max_response_bytes {
+                return Err(too_large);
+            }
+            body.extend_from_slice(&chunk);
+        }
+
+        Ok(GuardedResponse {
+            url: url.to_string(),
+            status_code,
+            content_type,
+            body: String::from_utf8_lossy(&body).into_owned(),
+        })
+    }
+}
+
+/// Address on the public internet, not private, loopback, link-local or otherwise reserved
+pub fn is_public_ip(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => is_public_ipv4(ip),
+        IpAddr::V6(ip) => match ip.to_ipv4_mapped() {
+            Some(mapped) => is_public_ipv4(mapped),
+            None => is_public_ipv6(ip),
+        },
+    }
+}
+
+fn is_public_ipv4(ip: Ipv4Addr) -> bool {
+    let [first, second, third, _] = ip.octets();
+    !(ip.is_private()
+        || ip.is_loopback()
+        || ip.is_link_local()
+        || ip.is_unspecified()
+        || ip.is_broadcast()
+        || ip.is_documentation()
+        || ip.is_multicast()
+        // "This network", carrier-grade NAT, IETF protocol assignments, 6to4 relay anycast,
+        // benchmarking and reserved ranges
+        || first == 0
+        || (first == 100 && (64..128).contains(&second))
+        || (first == 192 && second == 0 && third == 0)
+        || (first == 192 && second == 88 && third == 99)
+        || (first == 198 && (18..20).contains(&second))
+        || first >= 240)
+}
+
+fn is_public_ipv6(ip: Ipv6Addr) -> bool {
+    let segments: [u16; 8] = ip.segments();
+    let documentation: bool = segments[..2] == [0x2001, 0xdb8];
+    // NAT64, 6to4 and Teredo addresses reach an embedded IPv4 address, which could be private
+    let nat64: bool = segments[..6] == [0x64, 0xff9b, 0, 0, 0, 0] || segments[..3] == [0x64, 0xff9b, 1];
+    let six_to_four: bool = segments[0] == 0x2002;
+    let teredo: bool = segments[..2] == [0x2001, 0];
+    !(ip.is_loopback()
+        || ip.is_unspecified()
+        || ip.is_multicast()
+        || ip.is_unique_local()
+        || ip.is_unicast_link_local()
+        || documentation
+        || nat64
+        || six_to_four
+        || teredo)
+}
+
+/// Exact host or IP, `*.domain` also matching the domain itself
+fn host_matches(pattern: &str, host: &str) -> bool {
+    let pattern: &str = pattern.trim().trim_start_matches('[').trim_end_matches(']');
+    match pattern.strip_prefix("*.") {
+        Some(domain) => {
+            host.eq_ignore_ascii_case(domain)
+                || host
+                    .to_ascii_lowercase()
+                    .ends_with(&format!(".{}", domain.to_ascii_lowercase()))
+        }
+        None => host.eq_ignore_ascii_case(pattern),
+    }
+}
+
+fn env_host_list(name: &str) -> Vec<String> {
+    std::env::var(name)
+        .unwrap_or_default()
+        .split(',')
+        .map(str::trim)
+        .filter(|host| !host.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::Router;
+    use axum::http::header;
+    use axum::response::{IntoResponse, Redirect};
+    use axum::routing::get;
+    use tokio::net::TcpListener;
+
+    async fn check_url(policy: &UrlPolicy, url: &str) -> Result<Vec<SocketAddr>, String> {
+        policy.check(&Url::parse(url).unwrap()).await
+    }
+
+    #[test]
+    fn tests_classifies_addresses() {
+        for ip in [
+            "127.0.0.1",
+            "10.1.2.3",
+            "172.16.0.1",
+            "192.168.1.1",
+            "169.254.169.254",
+            "0.0.0.0",
+            "100.64.0.1",
+            "192.0.0.8",
+            "192.88.99.1",
+            "::1",
+            "fd00::1",
+            "fe80::1",
+            "::ffff:127.0.0.1",
+            "64:ff9b::7f00:1",
+            "64:ff9b:1::a01:203",
+            "2002:7f00:1::1",
+            "2001:0:4136:e378::1",
+        ] {
+            assert!(!is_public_ip(ip.parse().unwrap()), "{}", ip);
+        }
+        for ip in ["93.184.216.34", "8.8.8.8", "192.0.1.1", "192.88.98.1", "2606:4700::1111"] {
+            assert!(is_public_ip(ip.parse().unwrap()), "{}", ip);
+        }
+    }
+
+    #[tokio::test]
+    async fn tests_blocks_internal_urls() {
+        let policy: UrlPolicy = UrlPolicy {
+            deny_hosts: vec!["*.example.com".to_string()],
+            ..UrlPolicy::default()
+        };
+
+        for (url, reason) in [
+            ("file:///etc/passwd", "scheme 'file' is not allowed"),
+            ("ftp://93.184.216.34/", "scheme 'ftp' is not allowed"),
+            (
+                "http://api.example.com/data",
+                "host 'api.example.com' is on the deny list",
+            ),
+            (
+                "http://example.com/data",
+                "host 'example.com' is on the deny list",
+            ),
+            (
+                "http://localhost:8080/",
+                "host 'localhost' resolves to non-public address",
+            ),
+            (
+                "http://169.254.169.254/latest/meta-data/",
+                "host '169.254.169.254' resolves to non-public address",
+            ),
+            ("http://[::1]/", "host '::1' resolves to non-public address"),
+            (
+                "http://10.0.0.1/",
+                "host '10.0.0.1' resolves to non-public address",
+            ),
+        ] {
+            let error: String = check_url(&policy, url).await.unwrap_err();
+            assert!(error.starts_with(reason), "{}: {}", url, error);
+        }
+        assert!(check_url(&policy, "http://93.184.216.34/").await.is_ok());
+
+        let policy: UrlPolicy = UrlPolicy {
+            allow_hosts: vec!["127.0.0.1".to_string()],
+            ..UrlPolicy::default()
+        };
+        assert!(check_url(&policy, "http://127.0.0.1:8080/").await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn tests_follows_redirects_within_limits() {
+        let listener: TcpListener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port: u16 = listener.local_addr().unwrap().port();
+        let router: Router = Router::new()
+            .route(
+                "/data",
+                get(|| async { ([(header::CONTENT_TYPE, "application/json")], "[1, 2, 3]") }),
+            )
+            .route("/moved", get(|| async { Redirect::temporary("/data") }))
+            .route("/loop", get(|| async { Redirect::temporary("/loop") }))
+            .route(
+                "/internal",
+                get(move || async move {
+                    Redirect::temporary(&format!("http://localhost:{}/data", port))
+                }),
+            )
+            .route("/large", get(|| async { "x".repeat(2048).into_response() }));
+        tokio::spawn(async move { axum::serve(listener, router).await.unwrap() });
+
+        let policy: UrlPolicy = UrlPolicy {
+            allow_hosts: vec!["127.0.0.1".to_string()],
+            max_response_bytes: 1024,
+            max_redirects: 2,
+            ..UrlPolicy::default()
+        };
+        let base: String = format!("http://127.0.0.1:{}", port);
+
+        let response: GuardedResponse = policy.get(&format!("{}/moved", base)).await.unwrap();
+        assert_eq!(response.url, format!("{}/data", base));
+        assert_eq!(response.status_code, 200);
+        assert_eq!(response.content_type.as_deref(), Some("application/json"));
+        assert_eq!(response.body, "[1, 2, 3]");
+
+        assert_eq!(
+            policy.get(&format!("{}/loop", base)).await.unwrap_err(),
+            "more than 2 redirects"
+        );
+        assert!(
+            policy
+                .get(&format!("{}/internal", base))
+                .await
+                .unwrap_err()
+                .starts_with("host 'localhost' resolves to non-public address")
+        );
+        assert_eq!(
+            policy.get(&format!("{}/large", base)).await.unwrap_err(),
+            "response is larger than 1024 bytes"
+        );
+    }
+}
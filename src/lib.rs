@@ -0,0 +1,42 @@
+//! Agents that turn a website request into a working Rust backend.
+//!
+//! The items re-exported here are the stable API: configure a run with
+//! [`ProjectBuilder`], add your own [`SpecialFunctions`] agents and read the
+//! [`ProjectResult`]. The modules are public for the CLI and may change between releases.
+
+// Lets `#[ai_function]` name this crate the same way inside and outside of it
+extern crate self as rust_backend_gen_agents;
+
+#[macro_export]
+macro_rules! get_function_string {
+    ($func: ident) => {{
+        stringify!($func)
+    }};
+}
+
+#[macro_use]
+pub mod ai_functions;
+pub mod apis;
+pub mod dashboard;
+pub mod eval;
+pub mod helpers;
+pub mod models;
+mod project;
+pub mod server;
+
+pub use ai_functions::PromptMetadata;
+pub use apis::call_request::{ProviderConfig, DEFAULT_MODEL};
+pub use helpers::general::{ai_task_request, ai_task_request_decoded, AiFunctionCall};
+pub use models::agent_basic::basic_agent::{AgentState, BasicAgent};
+pub use models::agent_basic::basic_traits::BasicTraits;
+pub use models::agents::agent_traits::{FactSheet, FactSheetField, SpecialFunctions};
+pub use models::agents_manager::managing_agent::ManagingAgent;
+pub use models::agents_manager::pipeline::{AgentRegistry, PipelineDefinition, StageDefinition};
+pub use models::agents_manager::report::{IterationSummary, ProjectReport, StageReport};
+pub use models::general::clarification::Clarification;
+pub use models::general::dependencies::DependencyAllowlist;
+pub use models::general::framework::TargetFramework;
+pub use models::general::llm::LlmUsage;
+pub use models::general::settings::{ApprovalPolicy, BackendSettings};
+pub use project::{ProjectBuilder, ProjectResult};
+pub use proc_macro::{ai_function, function_to_string};
@@ -1,26 +1,94 @@
-#[macro_export]
-macro_rules! get_function_string {
-    ($func: ident) => {{
-        stringify!($func)
-    }};
-}
-
-#[macro_use]
-mod ai_functions;
-mod apis;
-mod helpers;
-mod models;
-
-use helpers::command_line::get_user_response;
-use models::agents_manager::managing_agent::ManagingAgent;
+use rust_backend_gen_agents::dashboard::state::DashboardState;
+use rust_backend_gen_agents::dashboard::{with_dashboard, Dashboard};
+use rust_backend_gen_agents::eval::{run_eval, EvalSettings};
+use rust_backend_gen_agents::helpers::command_line::get_user_response;
+use rust_backend_gen_agents::helpers::general::{migrate_api_schema, save_factsheet, API_SCHEMA_PATH};
+use rust_backend_gen_agents::helpers::telemetry::{init_logging, LogSettings};
+use rust_backend_gen_agents::models::general::clarification::ProjectSpecFile;
+use rust_backend_gen_agents::server::{serve, ServeSettings};
+use rust_backend_gen_agents::{Clarification, ManagingAgent, ProjectBuilder, ProjectReport};
+use std::sync::{Arc, Mutex};
 
 #[tokio::main]
 async fn main() {
-    let usr_req: String = get_user_response("What website do you want to build?");
+    let mut args: Vec<String> = std::env::args().collect();
+
+    // Pick the console and/or JSON-lines log sinks
+    let log_settings: LogSettings = LogSettings::from_args(&mut args).unwrap_or_else(|e| {
+        eprintln!("{}", e);
+        std::process::exit(2);
+    });
+    let dashboard_state: Option<Arc<Mutex<DashboardState>>> = log_settings
+        .tui
+        .then(|| Arc::new(Mutex::new(DashboardState::default())));
+    init_logging(&log_settings, dashboard_state.clone()).expect("Failed to open log file");
+
+    // Non-interactive runs take the request and clarifying answers from a spec file
+    let spec: Option<ProjectSpecFile> = ProjectSpecFile::from_args(&mut args).unwrap_or_else(|e| {
+        eprintln!("{}", e);
+        std::process::exit(2);
+    });
+
+    // Rewrite an api_schema.json from the old string-typed format
+    if args.get(1).map(String::as_str) == Some("migrate-schema") {
+        let path: &str = args.get(2).map(String::as_str).unwrap_or(API_SCHEMA_PATH);
+        let migrated: usize = migrate_api_schema(path).expect("Failed to migrate API schema");
+        println!("Migrated {} endpoints in {}", migrated, path);
+        return;
+    }
+
+    // Run a suite of project descriptions and compare the metrics against the baseline
+    if args.get(1).map(String::as_str) == Some("eval") {
+        let eval_settings: EvalSettings = EvalSettings::from_args(&args).unwrap_or_else(|e| {
+            eprintln!("{}", e);
+            std::process::exit(2);
+        });
+        let passed: bool = run_eval(&eval_settings).await.expect("Failed to run eval");
+        if !passed {
+            std::process::exit(1);
+        }
+        return;
+    }
+
+    // Run projects submitted over HTTP, each in its own workspace
+    if args.get(1).map(String::as_str) == Some("serve") {
+        let serve_settings: ServeSettings = ServeSettings::from_args(&mut args).unwrap_or_else(|e| {
+            eprintln!("{}", e);
+            std::process::exit(2);
+        });
+        serve(serve_settings).await.expect("Failed to run server");
+        return;
+    }
+
+    let (usr_req, answers): (String, Option<Vec<Clarification>>) = match spec {
+        Some(spec) => (spec.request, Some(spec.answers)),
+        None => (get_user_response("What website do you want to build?"), None),
+    };
+
+    let mut project: ProjectBuilder = ProjectBuilder::new(&usr_req);
+    if let Some(answers) = answers {
+        project = project.answers(answers);
+    }
+
+    // Clarifying questions are asked on the console before the dashboard takes over
+    let mut manage_agent: ManagingAgent = project.build().await.expect("Error creating agent");
+
+    // Quitting the dashboard stops the project, which still writes its report
+    let dashboard: Option<Dashboard> = dashboard_state
+        .clone()
+        .map(|state| Dashboard::start(state, manage_agent.stop_signal()));
+
+    let report: ProjectReport =
+        with_dashboard(dashboard_state, manage_agent.execute_project()).await;
 
-    let mut manage_agent: ManagingAgent = ManagingAgent::new(usr_req)
-        .await
-        .expect("Error creating agent");
+    if let Some(dashboard) = dashboard {
+        dashboard.stop();
+    }
 
-    manage_agent.execute_project().await;
+    if let Err(e) = save_factsheet(manage_agent.factsheet()) {
+        eprintln!("Failed to save FactSheet: {}", e);
+    }
+    if !report.succeeded() {
+        std::process::exit(1);
+    }
 }
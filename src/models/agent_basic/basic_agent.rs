@@ -14,6 +14,7 @@ pub struct BasicAgent {
     pub objective: String,
     pub position: String,
     pub state: AgentState,
+    #[allow(dead_code)]
     pub memory: Vec<Message>
 }
 
@@ -28,6 +29,12 @@ impl BasicTraits for BasicAgent {
     }
 
     fn update_state(&mut self, new_state: AgentState) {
+        tracing::info!(
+            agent = %self.position,
+            from = ?self.state,
+            to = ?new_state,
+            "State transition"
+        );
         self.state = new_state;
     }
     
@@ -7,5 +7,6 @@ pub trait BasicTraits {
     fn get_objective(&self) -> &String;
     fn get_position(&self) -> &String;
     fn get_state(&self) -> &AgentState;
+    #[allow(dead_code)]
     fn get_memory(&self) -> &Vec<Message>;
 }
\ No newline at end of file
@@ -1,13 +1,19 @@
-use crate::ai_functions::aifunc_architect::{print_project_scope, print_site_urls};
+use crate::ai_functions::aifunc_architect::{
+    print_non_functional_requirements, print_project_scope, print_site_urls,
+};
 use crate::helpers::command_line::PrintCommand;
-use crate::helpers::general::{ai_task_request_decoded, check_status_code};
+use crate::helpers::url_health::{check_external_urls, UrlHealth, DEFAULT_URL_CHECK_CONCURRENCY};
+use crate::helpers::url_policy::UrlPolicy;
 use crate::models::agent_basic::basic_agent::{AgentState, BasicAgent};
 use crate::models::agent_basic::basic_traits::BasicTraits;
-use crate::models::agents::agent_traits::{FactSheet, ProjectScope, SpecialFunctions};
+use crate::models::agents::agent_traits::{
+    FactSheet, FactSheetField, ProjectScope, SpecialFunctions,
+};
+use crate::models::general::external_api::{ExcludedUrl, ExternalApiSample};
+use crate::models::general::prompt_guard::scan_for_injection;
+use crate::models::general::settings::env_number;
 
 use async_trait::async_trait;
-use reqwest::Client;
-use std::time::Duration;
 
 // Solutions Architect
 #[derive(Debug)]
@@ -15,48 +21,48 @@ pub struct AgentSolutionArchitect {
     attributes: BasicAgent,
 }
 
+impl Default for AgentSolutionArchitect {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl AgentSolutionArchitect {
     pub fn new() -> Self {
-        let attributes: BasicAgent = BasicAgent {
-            objective: "Gathers information and design solutions for website development"
-                .to_string(),
-            position: "Solutions Architect".to_string(),
-            state: AgentState::Discovery,
-            memory: vec![],
-        };
+        let attributes: BasicAgent = BasicAgent::new(
+            "Gathers information and design solutions for website development".to_string(),
+            "Solutions Architect".to_string(),
+        );
 
         Self { attributes }
     }
 
     /// Retrieve project scope
     async fn call_project_scope(&mut self, factsheet: &mut FactSheet) -> ProjectScope {
-        let msg_context: String = format!("{}", factsheet.project_description);
-        let ai_response: ProjectScope = ai_task_request_decoded::<ProjectScope>(
-            msg_context,
-            &self.attributes.position,
-            get_function_string!(print_project_scope),
-            print_project_scope,
-        ).await;
+        let ai_response: ProjectScope =
+            print_project_scope(&self.attributes.position, &factsheet.project_description).await;
         factsheet.project_scope = Some(ai_response.clone());
         self.attributes.update_state(AgentState::Finished);
         ai_response
     }
 
-    /// Retrieve Project Scope
-    async fn call_determine_external_urls(
-        &mut self,
-        factsheet: &mut FactSheet,
-        msg_context: String,
-    ) {
-        let ai_response: Vec<String> = ai_task_request_decoded::<Vec<String>>(
-            msg_context,
+    /// Retrieve non-functional requirements
+    async fn call_non_functional_requirements(&mut self, factsheet: &mut FactSheet) {
+        let ai_response: Vec<String> = print_non_functional_requirements(
             &self.attributes.position,
-            get_function_string!(print_site_urls),
-            print_site_urls,
-        ).await;
+            &factsheet.project_description,
+        )
+        .await;
+        factsheet.non_functional_requirements = ai_response;
+    }
+
+    /// Retrieve Project Scope
+    async fn call_determine_external_urls(&mut self, factsheet: &mut FactSheet) {
+        let ai_response: Vec<String> =
+            print_site_urls(&self.attributes.position, &factsheet.project_description).await;
 
         factsheet.external_urls = Some(ai_response);
-        self.attributes.state = AgentState::UnitTesting;
+        self.attributes.update_state(AgentState::UnitTesting);
     }
 }
 
@@ -66,6 +72,21 @@ impl SpecialFunctions for AgentSolutionArchitect {
         &self.attributes
     }
 
+    fn reads(&self) -> Vec<FactSheetField> {
+        vec![FactSheetField::ProjectDescription]
+    }
+
+    fn writes(&self) -> Vec<FactSheetField> {
+        vec![
+            FactSheetField::ProjectScope,
+            FactSheetField::ExternalUrls,
+            FactSheetField::NonFunctionalRequirements,
+            FactSheetField::ExternalApiSamples,
+            FactSheetField::ExcludedUrls,
+            FactSheetField::InjectionFindings,
+        ]
+    }
+
     async fn execute(
         &mut self,
         factsheet: &mut FactSheet,
@@ -74,25 +95,22 @@ impl SpecialFunctions for AgentSolutionArchitect {
             match self.attributes.state {
                 AgentState::Discovery => {
                     let project_scope: ProjectScope = self.call_project_scope(factsheet).await;
+                    self.call_non_functional_requirements(factsheet).await;
 
                     // Confirm if external urls
                     if project_scope.is_external_urls_required {
-                        self.call_determine_external_urls(
-                            factsheet,
-                            factsheet.project_description.clone(),
-                        )
-                        .await;
-                        self.attributes.state = AgentState::UnitTesting;
+                        self.call_determine_external_urls(factsheet).await;
+                        self.attributes.update_state(AgentState::UnitTesting);
                     }
                 }
 
                 AgentState::UnitTesting => {
-                    let mut exclude_urls: Vec<String> = vec![];
+                    let mut exclude_urls: Vec<ExcludedUrl> = vec![];
+                    let mut samples: Vec<ExternalApiSample> = vec![];
 
-                    let client: Client = Client::builder()
-                        .timeout(Duration::from_secs(5))
-                        .build()
-                        .unwrap();
+                    let policy: UrlPolicy = UrlPolicy::from_env();
+                    let concurrency: usize = env_number::<usize>("URL_CHECK_CONCURRENCY")
+                        .unwrap_or(DEFAULT_URL_CHECK_CONCURRENCY);
 
                     // Defining urls to check
                     let urls: &Vec<String> = factsheet
@@ -100,45 +118,70 @@ impl SpecialFunctions for AgentSolutionArchitect {
                         .as_ref()
                         .expect("No URL object on factsheet");
 
-                    // Find faulty urls
-                    for url in urls {
-                        let endpoint_str: String = format!("Testing URL Endpoint: {}", url);
-                        PrintCommand::UnitTest.print_agent_message(
-                            self.attributes.position.as_str(),
-                            endpoint_str.as_str(),
-                        );
-
-                        // Perform URL Test
-                        match check_status_code(&client, url).await {
-                            Ok(status_code) => {
-                                if status_code != 200 {
-                                    exclude_urls.push(url.clone())
+                    let testing_str: String = format!("Testing {} URL Endpoints...", urls.len());
+                    PrintCommand::UnitTest.print_agent_message(
+                        self.attributes.position.as_str(),
+                        testing_str.as_str(),
+                    );
+
+                    // Find faulty urls, keeping a sample of every healthy response
+                    for health in check_external_urls(&policy, urls, concurrency).await {
+                        match health {
+                            UrlHealth::Healthy(sample) => {
+                                let healthy_str: String = format!(
+                                    "URL {} returned {} in {} ms",
+                                    sample.url, sample.status_code, sample.latency_ms
+                                );
+                                PrintCommand::UnitTest.print_agent_message(
+                                    self.attributes.position.as_str(),
+                                    healthy_str.as_str(),
+                                );
+
+                                // The sample ends up in later prompts, so it is untrusted input too
+                                for finding in scan_for_injection(&sample.url, &sample.sample) {
+                                    PrintCommand::Issue.print_agent_message(
+                                        self.attributes.position.as_str(),
+                                        &finding.to_string(),
+                                    );
+                                    factsheet.injection_findings.push(finding);
                                 }
+                                samples.push(sample);
+                            }
+                            UrlHealth::Excluded(excluded) => {
+                                let excluded_str: String =
+                                    format!("Excluding URL {}: {}", excluded.url, excluded.reason);
+                                PrintCommand::Issue.print_agent_message(
+                                    self.attributes.position.as_str(),
+                                    excluded_str.as_str(),
+                                );
+                                exclude_urls.push(excluded);
                             }
-                            Err(e) => println!("Error checking {}: {}", url, e),
                         }
                     }
 
+                    factsheet.external_api_samples = samples;
+
                     // Exclude any faulty urls
-                    if exclude_urls.len() > 0 {
+                    if !exclude_urls.is_empty() {
                         let new_urls: Vec<String> = factsheet
                             .external_urls
                             .as_ref()
                             .unwrap()
                             .iter()
-                            .filter(|url| !exclude_urls.contains(&url))
+                            .filter(|url| !exclude_urls.iter().any(|excluded| &excluded.url == *url))
                             .cloned()
                             .collect();
                         factsheet.external_urls = Some(new_urls);
                     }
+                    factsheet.excluded_urls = exclude_urls;
 
                     // Confirm done
-                    self.attributes.state = AgentState::Finished;
+                    self.attributes.update_state(AgentState::Finished);
                 }
 
                 // Default to Finished state
                 _ => {
-                    self.attributes.state = AgentState::Finished;
+                    self.attributes.update_state(AgentState::Finished);
                 }
             }
         }
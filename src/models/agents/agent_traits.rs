@@ -1,22 +1,130 @@
+use crate::helpers::fixture_server::fixture_env_var;
+use crate::helpers::project_runner::EndpointTestReport;
 use crate::models::agent_basic::basic_agent::BasicAgent;
+use crate::models::agents_manager::report::IterationSummary;
+use crate::models::general::api_schema::{
+    deserialize_lenient_bool, deserialize_lenient_status_codes, deserialize_lenient_string_list,
+    extract_path_params, strip_code_fences, HttpMethod, SchemaNode,
+};
+use crate::models::general::clarification::Clarification;
+use crate::models::general::dependencies::CrateDependency;
+use crate::models::general::external_api::{
+    schema_field_names, schema_to_string, ExcludedUrl, ExternalApiSample,
+};
+use crate::models::general::framework::TargetFramework;
+use crate::models::general::lint::LintCounts;
+use crate::models::general::project_spec::{Entity, ProjectFeatures};
+use crate::models::general::prompt_guard::InjectionFinding;
 use async_trait::async_trait;
 use serde::{Deserialize, Serialize};
+use serde_json::Value;
 use std::fmt::Debug;
+use std::path::PathBuf;
+use strum::IntoEnumIterator;
+use strum_macros::{Display, EnumIter};
+
+/// Keys the model uses for the route of an endpoint
+const ROUTE_KEYS: [&str; 3] = ["route", "path", "url"];
 
 #[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
 pub struct RouteObject {
+    #[serde(alias = "path", alias = "url")]
     pub route: String,
-    pub is_route_dynamic: String,
-    pub method: String,
-    pub request_body: serde_json::Value,
-    pub response: serde_json::Value
+    #[serde(default, deserialize_with = "deserialize_lenient_bool")]
+    pub is_route_dynamic: bool,
+    /// None when the model left the method out, such endpoints are not called in testing
+    #[serde(default, alias = "http_method", skip_serializing_if = "Option::is_none")]
+    pub method: Option<HttpMethod>,
+    #[serde(default, deserialize_with = "deserialize_lenient_string_list")]
+    pub path_params: Vec<String>,
+    #[serde(default, deserialize_with = "deserialize_lenient_string_list")]
+    pub query_params: Vec<String>,
+    #[serde(default, deserialize_with = "deserialize_lenient_status_codes")]
+    pub status_codes: Vec<u16>,
+    #[serde(default, alias = "body")]
+    pub request_body: SchemaNode,
+    #[serde(default)]
+    pub response: SchemaNode,
+}
+
+impl RouteObject {
+    /// Decodes the endpoint list printed by the LLM or stored in an older api_schema.json
+    pub fn parse_list(text: &str) -> Result<Vec<RouteObject>, serde_json::Error> {
+        let value: Value = serde_json::from_str(strip_code_fences(text))?;
+
+        // Accept a bare array, a single route or an object wrapping the array
+        let routes: Vec<RouteObject> = match value {
+            Value::Array(_) => serde_json::from_value(value)?,
+            Value::Object(ref fields)
+                if ROUTE_KEYS.iter().any(|key| fields.contains_key(*key)) =>
+            {
+                vec![serde_json::from_value(value)?]
+            }
+            Value::Object(fields) => match fields.into_iter().find(|(_, value)| value.is_array()) {
+                Some((_, routes)) => serde_json::from_value(routes)?,
+                None => vec![],
+            },
+            other => serde_json::from_value(other)?,
+        };
+
+        Ok(routes.into_iter().map(RouteObject::normalized).collect())
+    }
+
+    /// Moves query strings out of the route and derives path params and dynamic flag
+    pub fn normalized(mut self) -> Self {
+        if let Some((path, query)) = self.route.clone().split_once('?') {
+            self.route = path.to_string();
+            for pair in query.split('&') {
+                let name: &str = pair.split('=').next().unwrap_or_default();
+                if !name.is_empty() && !self.query_params.iter().any(|param| param == name) {
+                    self.query_params.push(name.to_string());
+                }
+            }
+        }
+
+        if self.path_params.is_empty() {
+            self.path_params = extract_path_params(&self.route);
+        }
+        self.is_route_dynamic = self.is_route_dynamic || !self.path_params.is_empty();
+        self
+    }
+
+    /// Whether the endpoint can be called in unit testing without any input
+    pub fn is_testable_without_input(&self) -> bool {
+        self.method == Some(HttpMethod::Get) && !self.is_route_dynamic
+    }
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
 pub struct ProjectScope {
     pub is_crud_required: bool,
     pub is_user_login_and_logout: bool,
-    pub is_external_urls_required: bool
+    pub is_external_urls_required: bool,
+    #[serde(default)]
+    pub entities: Vec<Entity>,
+    #[serde(default)]
+    pub features: ProjectFeatures,
+}
+
+/// FactSheet fields agents declare they read or write, named like their serialized keys
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Display, EnumIter)]
+#[strum(serialize_all = "snake_case")]
+pub enum FactSheetField {
+    ProjectDescription,
+    ProjectScope,
+    ExternalUrls,
+    BackendCode,
+    ApiEndpointSchema,
+    NonFunctionalRequirements,
+    ExternalApiSamples,
+    TargetFramework,
+    LintCounts,
+    ExtraDependencies,
+    Clarifications,
+    Requirements,
+    ExcludedUrls,
+    EndpointTestReport,
+    InjectionFindings,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
@@ -26,15 +134,262 @@ pub struct FactSheet {
     pub external_urls: Option<Vec<String>>,
     pub backend_code: Option<String>,
     pub api_endpoint_schema: Option<Vec<RouteObject>>,
+    #[serde(default)]
+    pub non_functional_requirements: Vec<String>,
+    #[serde(default)]
+    pub external_api_samples: Vec<ExternalApiSample>,
+    #[serde(default)]
+    pub target_framework: TargetFramework,
+    #[serde(default)]
+    pub lint_counts: Option<LintCounts>,
+    #[serde(default)]
+    pub extra_dependencies: Vec<CrateDependency>,
+    /// Answers to the clarifying questions asked before the project started
+    #[serde(default)]
+    pub clarifications: Vec<Clarification>,
+    /// Requirements taken from the clarifying answers
+    #[serde(default)]
+    pub requirements: Vec<String>,
+    /// External URLs dropped from `external_urls` by the URL checks
+    #[serde(default)]
+    pub excluded_urls: Vec<ExcludedUrl>,
+    /// Results of calling the final endpoints on the running server
+    #[serde(default)]
+    pub endpoint_test_report: Option<EndpointTestReport>,
+    /// Prompt injection patterns found in the user request or external data
+    #[serde(default)]
+    pub injection_findings: Vec<InjectionFinding>,
+}
+
+impl FactSheet {
+    /// Framework conventions and every library the generated project can use
+    pub fn framework_context(&self) -> String {
+        self.target_framework
+            .prompt_context(&self.extra_dependencies)
+    }
+
+    /// Renders the structured project spec that the backend developer builds from
+    ///
+    /// Holds the scope flags, features and non-functional requirements, the framework is
+    /// rendered by `framework_context`. The user's goal and answers, the entities and URLs
+    /// taken from them and third-party data are rendered by `untrusted_spec`.
+    pub fn project_spec(&self) -> String {
+        let mut spec: String = String::new();
+
+        if let Some(scope) = &self.project_scope {
+            spec.push_str(&format!(
+                "CRUD REQUIRED: {}\nUSER LOGIN AND LOGOUT: {}\nEXTERNAL DATA REQUIRED: {}\n",
+                scope.is_crud_required, scope.is_user_login_and_logout, scope.is_external_urls_required
+            ));
+
+            let features: Vec<&str> = scope.features.enabled();
+            if !features.is_empty() {
+                spec.push_str(&format!("REQUIRED FEATURES: {}\n", features.join(", ")));
+            }
+        }
+
+        if !self.non_functional_requirements.is_empty() {
+            spec.push_str("NON-FUNCTIONAL REQUIREMENTS:\n");
+            for requirement in &self.non_functional_requirements {
+                spec.push_str(&format!("  - {}\n", requirement));
+            }
+        }
+
+        spec
+    }
+
+    /// Renders the parts of the spec taken from the user or external APIs, sent only as data
+    pub fn untrusted_spec(&self) -> String {
+        let mut spec: String = format!("GOAL: {}\n", self.project_description);
+
+        if let Some(scope) = self.project_scope.as_ref().filter(|scope| !scope.entities.is_empty()) {
+            spec.push_str("ENTITIES:\n");
+            for entity in &scope.entities {
+                spec.push_str(&format!("  - {}\n", entity));
+            }
+        }
+
+        if let Some(external_urls) = &self.external_urls {
+            spec.push_str(&format!("EXTERNAL URLS: {}\n", external_urls.join(", ")));
+        }
+
+        if !self.external_api_samples.is_empty() {
+            spec.push_str("EXTERNAL API SAMPLES:\n");
+            for sample in &self.external_api_samples {
+                spec.push_str(&format!("  - URL: {}\n", sample.url));
+                if let Some(host) = reqwest::Url::parse(&sample.url)
+                    .ok()
+                    .and_then(|url| url.host_str().map(str::to_string))
+                {
+                    spec.push_str(&format!("    BASE URL ENV VAR: {}\n", fixture_env_var(&host)));
+                }
+                if let Some(schema) = &sample.schema {
+                    spec.push_str(&format!(
+                        "    FIELDS: {}\n    SCHEMA: {}\n",
+                        schema_field_names(schema).join(", "),
+                        schema_to_string(schema)
+                    ));
+                }
+                spec.push_str(&format!("    SAMPLE: {}\n", sample.sample));
+            }
+        }
+
+        if !self.requirements.is_empty() {
+            spec.push_str("REQUIREMENTS:\n");
+            for requirement in &self.requirements {
+                spec.push_str(&format!("  - {}\n", requirement));
+            }
+        }
+
+        spec
+    }
 }
 
 #[async_trait]
-pub trait SpecialFunctions: Debug {
+pub trait SpecialFunctions: Debug + Send {
     fn get_attributes_from_agent(&self) -> &BasicAgent;
 
+    /// FactSheet fields the agent reads, all of them unless the agent says otherwise
+    fn reads(&self) -> Vec<FactSheetField> {
+        FactSheetField::iter().collect()
+    }
+
+    /// FactSheet fields the agent writes, all of them unless the agent says otherwise
+    fn writes(&self) -> Vec<FactSheetField> {
+        FactSheetField::iter().collect()
+    }
+
+    /// Files the agent produced, reported with the project result
+    fn artifacts(&self) -> Vec<PathBuf> {
+        vec![]
+    }
+
+    /// Versions of its output the agent went through, empty for agents that do not iterate
+    fn iterations(&self) -> Vec<IterationSummary> {
+        vec![]
+    }
+
     /// This function will allow agents to execute their logic
     async fn execute(
         &mut self,
         factsheet: &mut FactSheet,
     ) -> Result<(), Box<dyn std::error::Error>>;
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tests_parses_legacy_route_objects() {
+        let legacy_schema: &str = r#"```json
+        [
+          {
+            "route": "/item/{id}",
+            "is_route_dynamic": "true",
+            "method": "GET",
+            "request_body": "None",
+            "response": { "id": "number", "name": "string", "completed": "bool" }
+          },
+          {
+            "route": "/crypto?symbol=BTC",
+            "is_route_dynamic": "false",
+            "method": "get",
+            "request_body": "None",
+            "response": "not_provided"
+          }
+        ]
+        ```"#;
+
+        let routes: Vec<RouteObject> = RouteObject::parse_list(legacy_schema).unwrap();
+
+        assert_eq!(routes[0].path_params, vec!["id"]);
+        assert!(routes[0].is_route_dynamic);
+        assert!(routes[0].request_body.is_none());
+        assert_eq!(routes[1].route, "/crypto");
+        assert_eq!(routes[1].query_params, vec!["symbol"]);
+        assert_eq!(routes[1].response, SchemaNode::NotProvided);
+        assert!(routes[1].is_testable_without_input());
+    }
+
+    #[test]
+    fn tests_parses_wrapped_route_objects() {
+        let wrapped: &str = r#"{ "endpoints": [
+          { "path": "/users/:id", "method": "Delete", "status_codes": ["204 No Content", 404] }
+        ] }"#;
+
+        let routes: Vec<RouteObject> = RouteObject::parse_list(wrapped).unwrap();
+
+        assert_eq!(routes[0].method, Some(HttpMethod::Delete));
+        assert_eq!(routes[0].status_codes, vec![204, 404]);
+        assert!(!routes[0].is_testable_without_input());
+    }
+
+    #[test]
+    fn tests_parses_single_route_under_any_alias() {
+        for key in ["route", "path", "url"] {
+            let single: String = format!(r#"{{ "{}": "/health", "method": "get" }}"#, key);
+
+            let routes: Vec<RouteObject> = RouteObject::parse_list(&single).unwrap();
+
+            assert_eq!(routes.len(), 1);
+            assert_eq!(routes[0].route, "/health");
+        }
+    }
+
+    #[test]
+    fn tests_skips_endpoints_without_a_method() {
+        let routes: Vec<RouteObject> = RouteObject::parse_list(r#"[{ "route": "/users" }]"#).unwrap();
+
+        assert_eq!(routes[0].method, None);
+        assert!(!routes[0].is_testable_without_input());
+        assert!(!serde_json::to_string(&routes[0]).unwrap().contains("method"));
+    }
+
+    #[test]
+    fn tests_renders_project_spec() {
+        let factsheet: FactSheet = serde_json::from_str(
+            r#"{
+              "project_description": "build a website that tracks fitness progress",
+              "project_scope": {
+                "is_crud_required": true,
+                "is_user_login_and_logout": true,
+                "is_external_urls_required": false,
+                "entities": [{ "name": "Workout", "fields": [{ "name": "minutes", "type": "int" }] }],
+                "features": { "pagination": true }
+              },
+              "external_urls": ["https://hp-api.onrender.com/api/characters"],
+              "backend_code": null,
+              "api_endpoint_schema": null,
+              "non_functional_requirements": ["respond within 200ms"],
+              "requirements": ["Workouts are private to each user"],
+              "external_api_samples": [{
+                "url": "https://hp-api.onrender.com/api/characters",
+                "status_code": 200,
+                "content_type": "application/json",
+                "sample": "[{\"name\":\"Harry Potter\"}]",
+                "schema": [{ "name": "string" }]
+              }]
+            }"#,
+        )
+        .unwrap();
+
+        let spec: String = factsheet.project_spec();
+        let untrusted_spec: String = factsheet.untrusted_spec();
+
+        assert!(factsheet.framework_context().contains("TARGET_FRAMEWORK: actix-web\n"));
+        assert!(!spec.contains("TARGET_FRAMEWORK"));
+        assert!(!spec.contains("Workout"));
+        assert!(spec.contains("REQUIRED FEATURES: pagination"));
+        assert!(spec.contains("  - respond within 200ms"));
+        assert!(!spec.contains("fitness progress"));
+        assert!(!spec.contains("Harry Potter"));
+        assert!(!spec.contains("hp-api.onrender.com"));
+        assert!(untrusted_spec.contains("ENTITIES:\n  - Workout { minutes: integer }\n"));
+        assert!(untrusted_spec.contains("EXTERNAL URLS: https://hp-api.onrender.com/api/characters\n"));
+        assert!(untrusted_spec.starts_with("GOAL: build a website that tracks fitness progress\n"));
+        assert!(untrusted_spec.contains("REQUIREMENTS:\n  - Workouts are private to each user\n"));
+        assert!(untrusted_spec.contains("    FIELDS: name\n"));
+        assert!(untrusted_spec.contains("    BASE URL ENV VAR: HP_API_ONRENDER_COM_BASE_URL\n"));
+    }
+}
@@ -0,0 +1,284 @@
+use crate::models::agents::agent_traits::{FactSheet, FactSheetField};
+use crate::models::agents_manager::pipeline::PipelineStage;
+use std::collections::BTreeSet;
+use strum::IntoEnumIterator;
+
+/// Earlier stages each stage waits for, as indexes into the stages in execution order
+///
+/// A stage waits for the stages it depends on by name and for every earlier stage
+/// writing a field it reads or writes itself. Everything else may run at the same time.
+pub fn dependency_graph(stages: &[PipelineStage]) -> Vec<BTreeSet<usize>> {
+    stages
+        .iter()
+        .enumerate()
+        .map(|(index, stage)| {
+            let reads: Vec<FactSheetField> = stage.agent.reads();
+            let writes: Vec<FactSheetField> = stage.agent.writes();
+            stages[..index]
+                .iter()
+                .enumerate()
+                .filter(|(_, earlier)| {
+                    stage.depends_on.contains(&earlier.name)
+                        || earlier
+                            .agent
+                            .writes()
+                            .iter()
+                            .any(|field| reads.contains(field) || writes.contains(field))
+                })
+                .map(|(earlier_index, _)| earlier_index)
+                .collect()
+        })
+        .collect()
+}
+
+/// Fields taken over from an agent's FactSheet copy and the problems found on the way
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct MergeReport {
+    pub merged: Vec<FactSheetField>,
+    /// Changed by the agent without being listed in its `writes`, the change is dropped
+    pub undeclared: Vec<FactSheetField>,
+    /// Changed by another agent since the copy was taken, the other agent's value is kept
+    pub conflicts: Vec<FactSheetField>,
+}
+
+/// Copies the fields an agent changed on its copy of the FactSheet back into the shared one
+///
+/// Only fields listed in `writes` are copied, so agents the graph runs at the same time
+/// cannot overwrite each other.
+pub fn merge_factsheet(
+    target: &mut FactSheet,
+    snapshot: &FactSheet,
+    result: &FactSheet,
+    writes: &[FactSheetField],
+) -> MergeReport {
+    let mut report: MergeReport = MergeReport::default();
+
+    for field in FactSheetField::iter() {
+        macro_rules! merge {
+            ($name:ident) => {
+                merge_value(&mut target.$name, &snapshot.$name, &result.$name, writes.contains(&field))
+            };
+        }
+        let outcome: FieldMerge = match field {
+            FactSheetField::ProjectDescription => merge!(project_description),
+            FactSheetField::ProjectScope => merge!(project_scope),
+            FactSheetField::ExternalUrls => merge!(external_urls),
+            FactSheetField::BackendCode => merge!(backend_code),
+            FactSheetField::ApiEndpointSchema => merge!(api_endpoint_schema),
+            FactSheetField::NonFunctionalRequirements => merge!(non_functional_requirements),
+            FactSheetField::ExternalApiSamples => merge!(external_api_samples),
+            FactSheetField::TargetFramework => merge!(target_framework),
+            FactSheetField::LintCounts => merge!(lint_counts),
+            FactSheetField::ExtraDependencies => merge!(extra_dependencies),
+            FactSheetField::Clarifications => merge!(clarifications),
+            FactSheetField::Requirements => merge!(requirements),
+            FactSheetField::ExcludedUrls => merge!(excluded_urls),
+            FactSheetField::EndpointTestReport => merge!(endpoint_test_report),
+            FactSheetField::InjectionFindings => merge!(injection_findings),
+        };
+        match outcome {
+            FieldMerge::Merged => report.merged.push(field),
+            FieldMerge::Undeclared => report.undeclared.push(field),
+            FieldMerge::Conflict => report.conflicts.push(field),
+            FieldMerge::Unchanged => {}
+        }
+    }
+    report
+}
+
+/// What merging one field did to the shared FactSheet
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum FieldMerge {
+    Unchanged,
+    Merged,
+    /// Changed by an agent that does not declare writing it, left out
+    Undeclared,
+    Conflict,
+}
+
+/// Takes over the agent's value unless the agent does not declare the field or another agent
+/// changed it since the copy was taken
+fn merge_value<T: Clone + PartialEq>(
+    target: &mut T,
+    before: &T,
+    after: &T,
+    declared: bool,
+) -> FieldMerge {
+    if before == after {
+        FieldMerge::Unchanged
+    } else if !declared {
+        FieldMerge::Undeclared
+    } else if target != before {
+        FieldMerge::Conflict
+    } else {
+        *target = after.clone();
+        FieldMerge::Merged
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::agent_basic::basic_agent::BasicAgent;
+    use crate::models::agent_basic::basic_traits::BasicTraits;
+    use crate::models::agents::agent_traits::SpecialFunctions;
+    use async_trait::async_trait;
+
+    #[derive(Debug)]
+    struct FieldAgent {
+        attributes: BasicAgent,
+        reads: Vec<FactSheetField>,
+        writes: Vec<FactSheetField>,
+    }
+
+    #[async_trait]
+    impl SpecialFunctions for FieldAgent {
+        fn get_attributes_from_agent(&self) -> &BasicAgent {
+            &self.attributes
+        }
+
+        fn reads(&self) -> Vec<FactSheetField> {
+            self.reads.clone()
+        }
+
+        fn writes(&self) -> Vec<FactSheetField> {
+            self.writes.clone()
+        }
+
+        async fn execute(
+            &mut self,
+            _factsheet: &mut FactSheet,
+        ) -> Result<(), Box<dyn std::error::Error>> {
+            Ok(())
+        }
+    }
+
+    fn stage(
+        name: &str,
+        depends_on: &[&str],
+        reads: Vec<FactSheetField>,
+        writes: Vec<FactSheetField>,
+    ) -> PipelineStage {
+        PipelineStage {
+            name: name.to_string(),
+            depends_on: depends_on.iter().map(|stage| stage.to_string()).collect(),
+            model: None,
+            agent: Box::new(FieldAgent {
+                attributes: BasicAgent::new(name.to_string(), name.to_string()),
+                reads,
+                writes,
+            }),
+        }
+    }
+
+    fn factsheet() -> FactSheet {
+        serde_json::from_str(
+            r#"{
+              "project_description": "build a website that tracks books",
+              "project_scope": null,
+              "external_urls": null,
+              "backend_code": null,
+              "api_endpoint_schema": null
+            }"#,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn tests_builds_dependency_graph_from_fields() {
+        use FactSheetField::*;
+        let stages: Vec<PipelineStage> = vec![
+            stage("architect", &[], vec![ProjectDescription], vec![ProjectScope]),
+            stage("backend", &[], vec![ProjectScope], vec![BackendCode]),
+            stage("frontend", &[], vec![BackendCode], vec![Requirements]),
+            stage("docs", &[], vec![BackendCode], vec![NonFunctionalRequirements]),
+            stage("review", &["docs"], vec![ProjectDescription], vec![Clarifications]),
+            stage("notes", &[], vec![ProjectDescription], vec![Requirements]),
+        ];
+
+        let graph: Vec<BTreeSet<usize>> = dependency_graph(&stages);
+
+        assert_eq!(graph[0], BTreeSet::new());
+        assert_eq!(graph[1], BTreeSet::from([0]));
+        // Frontend and docs both only need the backend, so they run side by side
+        assert_eq!(graph[2], BTreeSet::from([1]));
+        assert_eq!(graph[3], BTreeSet::from([1]));
+        assert_eq!(graph[4], BTreeSet::from([3]));
+        // Writing the same field as the frontend keeps the two in order
+        assert_eq!(graph[5], BTreeSet::from([2]));
+    }
+
+    #[test]
+    fn tests_merges_changes_and_detects_conflicts() {
+        let snapshot: FactSheet = factsheet();
+        let mut shared: FactSheet = snapshot.clone();
+
+        let mut docs: FactSheet = snapshot.clone();
+        docs.requirements = vec!["Document every route".to_string()];
+        let report: MergeReport = merge_factsheet(
+            &mut shared,
+            &snapshot,
+            &docs,
+            &[FactSheetField::Requirements],
+        );
+        assert_eq!(report.merged, vec![FactSheetField::Requirements]);
+        assert_eq!(shared.requirements, docs.requirements);
+
+        let mut frontend: FactSheet = snapshot.clone();
+        frontend.requirements = vec!["Serve a single page app".to_string()];
+        frontend.backend_code = Some("fn main() {}".to_string());
+        let report: MergeReport = merge_factsheet(
+            &mut shared,
+            &snapshot,
+            &frontend,
+            &[FactSheetField::Requirements],
+        );
+        assert!(report.merged.is_empty());
+        assert_eq!(report.undeclared, vec![FactSheetField::BackendCode]);
+        assert_eq!(report.conflicts, vec![FactSheetField::Requirements]);
+        assert_eq!(shared.requirements, vec!["Document every route"]);
+        // A field the agent did not declare is not merged
+        assert_eq!(shared.backend_code, snapshot.backend_code);
+    }
+
+    #[test]
+    fn tests_merges_typed_fields_unchanged() {
+        let snapshot: FactSheet = factsheet();
+        let mut shared: FactSheet = snapshot.clone();
+        let mut architect: FactSheet = snapshot.clone();
+        architect.project_scope = Some(
+            serde_json::from_str(
+                r#"{
+                  "is_crud_required": true,
+                  "is_user_login_and_logout": false,
+                  "is_external_urls_required": false,
+                  "entities": [{ "name": "Book", "fields": [
+                    { "name": "tags", "type": "list<string>" },
+                    { "name": "published", "type": "datetime" },
+                    { "name": "location", "type": "GeoPoint" }
+                  ] }]
+                }"#,
+            )
+            .unwrap(),
+        );
+
+        let report: MergeReport = merge_factsheet(
+            &mut shared,
+            &snapshot,
+            &architect,
+            &[FactSheetField::ProjectScope],
+        );
+        assert_eq!(report.merged, vec![FactSheetField::ProjectScope]);
+        assert_eq!(shared.project_scope, architect.project_scope);
+    }
+
+    #[test]
+    fn tests_fields_cover_factsheet() {
+        let keys: BTreeSet<String> = match serde_json::to_value(factsheet()).unwrap() {
+            serde_json::Value::Object(fields) => fields.keys().cloned().collect(),
+            _ => BTreeSet::new(),
+        };
+        let fields: BTreeSet<String> = FactSheetField::iter().map(|field| field.to_string()).collect();
+        assert_eq!(keys, fields);
+    }
+}
\ No newline at end of file
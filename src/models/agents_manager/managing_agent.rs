@@ -1,39 +1,103 @@
+use crate::helpers::command_line::{get_user_response, PrintCommand};
 use crate::models::agent_basic::basic_agent::{AgentState, BasicAgent};
-use crate::models::agents::agent_traits::{FactSheet, SpecialFunctions};
+use crate::models::agent_basic::basic_traits::BasicTraits;
+use crate::models::agents::agent_traits::FactSheet;
 
-use crate::ai_functions::aifunc_managing::convert_user_input_to_goal;
-use crate::helpers::general::ai_task_request;
-use crate::models::agents::agent_architect::AgentSolutionArchitect;
-use crate::models::agents::agent_backend::AgentBackendDeveloper;
+use crate::ai_functions::aifunc_managing::{
+    convert_user_input_to_goal, print_clarified_goal, print_clarifying_questions,
+};
+use crate::helpers::project_runner::{with_processes, ProcessTracker};
+use crate::helpers::run_report::write_run_report;
+use crate::apis::call_request::{with_model, with_provider, with_usage, ProviderConfig};
+use crate::dashboard::{current_dashboard, with_dashboard};
+use crate::models::agents_manager::agent_graph::{dependency_graph, merge_factsheet, MergeReport};
+use crate::models::agents_manager::pipeline::PipelineStage;
+use crate::models::agents_manager::report::{ProjectReport, StageReport};
+use crate::models::general::clarification::{
+    clarified_request, Clarification, ClarifiedGoal, DEFAULT_MAX_CLARIFYING_QUESTIONS,
+};
+use crate::models::general::framework::TargetFramework;
+use crate::models::general::llm::LlmUsage;
+use crate::models::general::prompt_guard::{scan_for_injection, InjectionFinding};
+use crate::models::general::settings::env_number;
+use std::any::Any;
+use std::collections::{BTreeSet, HashMap};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+use tokio::sync::Notify;
+use tokio::task::{self, AbortHandle, JoinSet};
+use tracing::{Instrument, Span};
 
 #[derive(Debug)]
 pub struct ManagingAgent {
     _attributes: BasicAgent,
     factsheet: FactSheet,
-    agents: Vec<Box<dyn SpecialFunctions>>,
+    agents: Vec<PipelineStage>,
+    /// Where the report of the run is written
+    run_dir: PathBuf,
+    /// Provider every stage calls, None uses the environment
+    provider: Option<ProviderConfig>,
+    /// Notified to stop the running stages, such as by quitting the dashboard
+    stop: Arc<Notify>,
+    /// Cargo processes the stages started, killed when the project stops
+    processes: ProcessTracker,
 }
 
 impl ManagingAgent {
-    pub async fn new(usr_req: String) -> Result<Self, Box<dyn std::error::Error>> {
+    /// Turns the user request into a goal, asking clarifying questions unless answers are given
+    ///
+    /// The report is written to `run_dir`, the directory the agents save their history in.
+    /// Every LLM call of the project goes to `provider`, or to the environment's when None.
+    pub async fn new(
+        usr_req: String,
+        answers: Option<Vec<Clarification>>,
+        agents: Vec<PipelineStage>,
+        target_framework: TargetFramework,
+        run_dir: PathBuf,
+        provider: Option<ProviderConfig>,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
         let position: String = "Project Manager".to_string();
 
-        let attributes: BasicAgent = BasicAgent {
-            objective: "Manage agents who are building an excellent website for the user"
-                .to_string(),
-            position: position.clone(),
-            state: AgentState::Discovery,
-            memory: vec![],
+        let attributes: BasicAgent = BasicAgent::new(
+            "Manage agents who are building an excellent website for the user".to_string(),
+            position.clone(),
+        );
+
+        let clarifications: Vec<Clarification> = match answers {
+            Some(answers) => answers,
+            None => {
+                with_provider(
+                    provider.clone(),
+                    Self::ask_clarifying_questions(&usr_req, &position),
+                )
+                .await
+            }
         };
 
-        let project_description: String = ai_task_request(
-            usr_req,
-            &position,
-            get_function_string!(convert_user_input_to_goal),
-            convert_user_input_to_goal,
-        )
-        .await;
+        let mut injection_findings: Vec<InjectionFinding> =
+            scan_for_injection("user request", &usr_req);
+        for clarification in &clarifications {
+            injection_findings.extend(scan_for_injection("clarification answer", &clarification.answer));
+        }
+        for finding in &injection_findings {
+            PrintCommand::Issue.print_agent_message(&position, &finding.to_string());
+        }
 
-        let agents: Vec<Box<dyn SpecialFunctions>> = vec![];
+        let (project_description, requirements): (String, Vec<String>) =
+            with_provider(provider.clone(), async {
+                if clarifications.is_empty() {
+                    let project_description: String =
+                        convert_user_input_to_goal(&position, &usr_req).await;
+                    (project_description, vec![])
+                } else {
+                    let clarified_goal: ClarifiedGoal =
+                        print_clarified_goal(&position, &clarified_request(&usr_req, &clarifications))
+                            .await;
+                    (clarified_goal.project_description, clarified_goal.requirements)
+                }
+            })
+            .await;
 
         let factsheet: FactSheet = FactSheet {
             project_description,
@@ -41,44 +105,664 @@ impl ManagingAgent {
             external_urls: None,
             backend_code: None,
             api_endpoint_schema: None,
+            non_functional_requirements: vec![],
+            external_api_samples: vec![],
+            target_framework,
+            lint_counts: None,
+            extra_dependencies: vec![],
+            clarifications,
+            requirements,
+            excluded_urls: vec![],
+            endpoint_test_report: None,
+            injection_findings,
         };
 
         Ok(Self {
             _attributes: attributes,
             factsheet,
             agents,
+            run_dir,
+            provider,
+            stop: Arc::new(Notify::new()),
+            processes: ProcessTracker::default(),
         })
     }
 
-    fn add_agent(&mut self, agent: Box<dyn SpecialFunctions>) {
-        self.agents.push(agent);
+    /// Asks up to MAX_CLARIFYING_QUESTIONS questions when the request is vague, skipping blank answers
+    async fn ask_clarifying_questions(usr_req: &str, position: &str) -> Vec<Clarification> {
+        let max_questions: usize = env_number::<usize>("MAX_CLARIFYING_QUESTIONS")
+            .unwrap_or(DEFAULT_MAX_CLARIFYING_QUESTIONS);
+        if max_questions == 0 {
+            return vec![];
+        }
+
+        let mut questions: Vec<String> =
+            print_clarifying_questions(position, max_questions, usr_req).await;
+        questions.truncate(max_questions);
+
+        questions
+            .into_iter()
+            .filter_map(|question| {
+                let answer: String =
+                    get_user_response(&format!("{} (press Enter to skip)", question));
+                (!answer.is_empty()).then_some(Clarification { question, answer })
+            })
+            .collect()
+    }
+
+    pub fn factsheet(&self) -> &FactSheet {
+        &self.factsheet
+    }
+
+    pub fn into_factsheet(self) -> FactSheet {
+        self.factsheet
+    }
+
+    pub fn run_dir(&self) -> &Path {
+        &self.run_dir
+    }
+
+    /// Signal that stops `execute_project`, which still writes the report of the run so far
+    pub fn stop_signal(&self) -> Arc<Notify> {
+        Arc::clone(&self.stop)
+    }
+
+    /// Runs each stage once the stages it waits for are done, independent stages in parallel
+    ///
+    /// A stage that panics or a notified `stop_signal` still stops the project, after the report
+    /// of the run so far is written.
+    pub async fn execute_project(&mut self) -> ProjectReport {
+        let started_at: Instant = Instant::now();
+        let graph: Vec<BTreeSet<usize>> = dependency_graph(&self.agents);
+        let stage_agents: Vec<(String, String)> = self
+            .agents
+            .iter()
+            .map(|stage| {
+                let position: &str = stage.agent.get_attributes_from_agent().get_position();
+                (stage.name.clone(), position.to_string())
+            })
+            .collect();
+        let mut waiting: Vec<Option<PipelineStage>> = self.agents.drain(..).map(Some).collect();
+        let mut finished: Vec<Option<PipelineStage>> = waiting.iter().map(|_| None).collect();
+        let mut running: JoinSet<StageRun> = JoinSet::new();
+        let mut running_stages: HashMap<task::Id, usize> = HashMap::new();
+        let mut stage_reports: Vec<(usize, StageReport)> = vec![];
+
+        loop {
+            for (index, slot) in waiting.iter_mut().enumerate() {
+                let ready: bool = graph[index]
+                    .iter()
+                    .all(|dependency| finished[*dependency].is_some());
+                if let Some(stage) = slot.take_if(|_| ready) {
+                    self.announce_handover(&stage);
+                    // Task locals do not reach spawned tasks, hand the provider and dashboard over explicitly
+                    let handle: AbortHandle = running.spawn(with_provider(
+                        self.provider.clone(),
+                        with_dashboard(
+                            current_dashboard(),
+                            with_processes(
+                                Some(self.processes.clone()),
+                                run_stage(index, stage, self.factsheet.clone()),
+                            ),
+                        ),
+                    ));
+                    running_stages.insert(handle.id(), index);
+                }
+            }
+
+            let stop: Arc<Notify> = Arc::clone(&self.stop);
+            let joined = tokio::select! {
+                joined = running.join_next_with_id() => joined,
+                _ = stop.notified() => {
+                    // Aborting drops the tasks, the cargo builds and servers they started keep running
+                    running.abort_all();
+                    self.processes.kill_all();
+                    for index in running_stages.values() {
+                        if finished[*index].is_none() {
+                            let (stage, position) = &stage_agents[*index];
+                            stage_reports.push((
+                                *index,
+                                StageReport::stopped(
+                                    stage,
+                                    position,
+                                    format!("{} was stopped by the user", position),
+                                ),
+                            ));
+                        }
+                    }
+                    return self.finish_report(stage_reports, started_at);
+                }
+            };
+            let Some(joined) = joined else {
+                break;
+            };
+            let stage_run: StageRun = match joined {
+                Ok((_, stage_run)) => stage_run,
+                // Agents stop the project by panicking, keep doing that from their task
+                Err(e) => {
+                    let index: usize = running_stages[&e.id()];
+                    let panic: Box<dyn Any + Send> = e.into_panic();
+                    let (stage, position) = &stage_agents[index];
+                    let issue: String = format!(
+                        "{} stopped the project: {}",
+                        position,
+                        panic_message(panic.as_ref())
+                    );
+                    stage_reports.push((index, StageReport::stopped(stage, position, issue)));
+                    running.abort_all();
+                    self.processes.kill_all();
+                    self.finish_report(stage_reports, started_at);
+                    std::panic::resume_unwind(panic)
+                }
+            };
+
+            let merge_report: MergeReport = merge_factsheet(
+                &mut self.factsheet,
+                &stage_run.snapshot,
+                &stage_run.factsheet,
+                &stage_run.stage.agent.writes(),
+            );
+            stage_reports.push((stage_run.index, self.report_stage(&stage_run, &merge_report)));
+            finished[stage_run.index] = Some(stage_run.stage);
+        }
+
+        self.agents = finished.into_iter().flatten().collect();
+        self.finish_report(stage_reports, started_at)
+    }
+
+    /// Puts the stage reports in execution order and writes the report of the run
+    fn finish_report(
+        &self,
+        mut stage_reports: Vec<(usize, StageReport)>,
+        started_at: Instant,
+    ) -> ProjectReport {
+        stage_reports.sort_by_key(|(index, _)| *index);
+        let mut report: ProjectReport = ProjectReport {
+            stages: stage_reports.into_iter().map(|(_, report)| report).collect(),
+            duration_ms: started_at.elapsed().as_millis() as u64,
+            report_files: vec![],
+        };
+        self.write_report(&mut report);
+        report
+    }
+
+    /// Saves the report next to the code history, a failure to write it does not fail the project
+    fn write_report(&self, report: &mut ProjectReport) {
+        match write_run_report(&self.run_dir, &self.factsheet, report) {
+            Ok(report_files) => {
+                let report_msg: String =
+                    format!("Project report saved to {}", self.run_dir.display());
+                PrintCommand::AICall
+                    .print_agent_message(self._attributes.get_position(), report_msg.as_str());
+                report.report_files = report_files;
+            }
+            Err(e) => {
+                let issue_msg: String = format!("Failed to write project report: {}", e);
+                PrintCommand::Issue
+                    .print_agent_message(self._attributes.get_position(), issue_msg.as_str());
+            }
+        }
     }
 
-    fn create_agents(&mut self) {
-        self.add_agent(Box::new(AgentSolutionArchitect::new()));
-        self.add_agent(Box::new(AgentBackendDeveloper::new()));
+    fn announce_handover(&self, stage: &PipelineStage) {
+        let agent_attributes: &BasicAgent = stage.agent.get_attributes_from_agent();
+        let handover_msg: String = format!(
+            "Handing over to {}: {}",
+            agent_attributes.get_position(),
+            agent_attributes.get_objective()
+        );
+        PrintCommand::AICall
+            .print_agent_message(self._attributes.get_position(), handover_msg.as_str());
     }
 
-    pub async fn execute_project(&mut self) {
-        self.create_agents();
+    /// Reports failed agents and FactSheet writes that could not be merged cleanly
+    fn report_stage(&self, stage_run: &StageRun, merge_report: &MergeReport) -> StageReport {
+        let agent_attributes: &BasicAgent = stage_run.stage.agent.get_attributes_from_agent();
+        let position: &str = agent_attributes.get_position();
+        let mut issues: Vec<String> = vec![];
 
-        for agent in &mut self.agents {
-            let _agent_res: Result<(), Box<dyn std::error::Error>> = agent.execute(&mut self.factsheet).await;
+        if let Err(e) = &stage_run.outcome {
+            issues.push(format!("{} failed: {}", position, e));
         }
+        if agent_attributes.get_state() != &AgentState::Finished {
+            issues.push(format!("{} did not finish its work", position));
+        }
+        for field in &merge_report.undeclared {
+            issues.push(format!(
+                "{} wrote {} without declaring it, the change was not merged",
+                position, field
+            ));
+        }
+        for field in &merge_report.conflicts {
+            issues.push(format!(
+                "{} wrote {} after another agent changed it, keeping the other agent's value",
+                position, field
+            ));
+        }
+
+        for issue_msg in &issues {
+            PrintCommand::Issue
+                .print_agent_message(self._attributes.get_position(), issue_msg.as_str());
+        }
+
+        StageReport {
+            stage: stage_run.stage.name.clone(),
+            agent: position.to_string(),
+            finished: agent_attributes.get_state() == &AgentState::Finished,
+            issues,
+            artifacts: stage_run.stage.agent.artifacts(),
+            iterations: stage_run.stage.agent.iterations(),
+            usage: stage_run.usage,
+            duration_ms: stage_run.duration_ms,
+        }
+    }
+}
+
+/// Text a stage panicked with, as given to `panic!`
+fn panic_message(panic: &(dyn Any + Send)) -> &str {
+    panic
+        .downcast_ref::<&str>()
+        .copied()
+        .or_else(|| panic.downcast_ref::<String>().map(String::as_str))
+        .unwrap_or("the agent panicked")
+}
+
+/// An agent's run on its own copy of the FactSheet
+struct StageRun {
+    index: usize,
+    stage: PipelineStage,
+    /// FactSheet as it was when the agent started
+    snapshot: FactSheet,
+    factsheet: FactSheet,
+    outcome: Result<(), String>,
+    usage: LlmUsage,
+    duration_ms: u64,
+}
+
+async fn run_stage(index: usize, mut stage: PipelineStage, snapshot: FactSheet) -> StageRun {
+    let execution_span: Span = tracing::info_span!(
+        "agent_execution",
+        agent = %stage.agent.get_attributes_from_agent().get_position(),
+        stage = %stage.name
+    );
+
+    let mut factsheet: FactSheet = snapshot.clone();
+    let usage: Arc<Mutex<LlmUsage>> = Arc::new(Mutex::new(LlmUsage::default()));
+    let started_at: Instant = Instant::now();
+    let outcome: Result<(), String> = with_usage(
+        Some(usage.clone()),
+        with_model(stage.model.clone(), stage.agent.execute(&mut factsheet)),
+    )
+    .instrument(execution_span)
+    .await
+    .map_err(|e| e.to_string());
+
+    let usage: LlmUsage = *usage.lock().unwrap();
+    StageRun {
+        index,
+        stage,
+        snapshot,
+        factsheet,
+        outcome,
+        usage,
+        duration_ms: started_at.elapsed().as_millis() as u64,
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::helpers::general::run_dir;
+    #[cfg(unix)]
+    use crate::helpers::project_runner::spawn_tracked;
+    use crate::models::agent_basic::basic_traits::BasicTraits;
+    use crate::models::agents::agent_traits::{FactSheetField, SpecialFunctions};
+    use crate::models::agents_manager::pipeline::{AgentRegistry, PipelineDefinition};
+    use crate::models::general::dependencies::DependencyAllowlist;
+    use crate::models::general::settings::BackendSettings;
+    use async_trait::async_trait;
+    use futures_util::FutureExt;
+    use std::panic::AssertUnwindSafe;
+    use std::sync::Arc;
+    use std::time::Duration;
+    use tokio::sync::Barrier;
+
+    /// Waits for its sibling before writing one requirement, so it only finishes when run in parallel
+    #[derive(Debug)]
+    struct ParallelAgent {
+        attributes: BasicAgent,
+        barrier: Arc<Barrier>,
+        field: FactSheetField,
+    }
+
+    #[async_trait]
+    impl SpecialFunctions for ParallelAgent {
+        fn get_attributes_from_agent(&self) -> &BasicAgent {
+            &self.attributes
+        }
+
+        fn reads(&self) -> Vec<FactSheetField> {
+            vec![FactSheetField::ProjectDescription]
+        }
+
+        fn writes(&self) -> Vec<FactSheetField> {
+            vec![self.field]
+        }
+
+        async fn execute(
+            &mut self,
+            factsheet: &mut FactSheet,
+        ) -> Result<(), Box<dyn std::error::Error>> {
+            self.barrier.wait().await;
+            let requirement: String = format!("written by {}", self.attributes.position);
+            match self.field {
+                FactSheetField::Requirements => factsheet.requirements.push(requirement),
+                _ => factsheet.non_functional_requirements.push(requirement),
+            }
+            self.attributes.update_state(AgentState::Finished);
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn tests_runs_independent_agents_in_parallel() {
+        let barrier: Arc<Barrier> = Arc::new(Barrier::new(2));
+        let agents: Vec<PipelineStage> = [
+            ("docs", FactSheetField::Requirements),
+            ("frontend", FactSheetField::NonFunctionalRequirements),
+        ]
+        .into_iter()
+        .map(|(name, field)| PipelineStage {
+            name: name.to_string(),
+            depends_on: vec![],
+            model: None,
+            agent: Box::new(ParallelAgent {
+                attributes: BasicAgent::new(name.to_string(), name.to_string()),
+                barrier: Arc::clone(&barrier),
+                field,
+            }),
+        })
+        .collect();
+        let mut managing_agent: ManagingAgent = ManagingAgent {
+            _attributes: BasicAgent::new("Manage agents".to_string(), "Project Manager".to_string()),
+            factsheet: serde_json::from_str(
+                r#"{
+                  "project_description": "build a website that tracks books",
+                  "project_scope": null,
+                  "external_urls": null,
+                  "backend_code": null,
+                  "api_endpoint_schema": null
+                }"#,
+            )
+            .unwrap(),
+            agents,
+            run_dir: std::env::temp_dir().join("rust-backend-gen-agents-parallel-run"),
+            provider: None,
+            stop: Arc::new(Notify::new()),
+            processes: ProcessTracker::default(),
+        };
+
+        let report: ProjectReport =
+            tokio::time::timeout(Duration::from_secs(5), managing_agent.execute_project())
+                .await
+                .expect("Independent agents did not run in parallel");
+
+        assert!(report.succeeded());
+        assert_eq!(report.stages[0].stage, "docs");
+        assert_eq!(report.report_files.len(), 2);
+        let markdown: String = std::fs::read_to_string(&report.report_files[0]).unwrap();
+        assert!(markdown.contains("| docs | docs | yes | 0 |"));
+
+        assert_eq!(managing_agent.factsheet.requirements, vec!["written by docs"]);
+        assert_eq!(
+            managing_agent.factsheet.non_functional_requirements,
+            vec!["written by frontend"]
+        );
+        assert_eq!(managing_agent.agents.len(), 2);
+    }
+
+    /// Stops the project the way agents do when they give up
+    #[derive(Debug)]
+    struct PanickingAgent {
+        attributes: BasicAgent,
+    }
+
+    #[async_trait]
+    impl SpecialFunctions for PanickingAgent {
+        fn get_attributes_from_agent(&self) -> &BasicAgent {
+            &self.attributes
+        }
+
+        async fn execute(
+            &mut self,
+            _factsheet: &mut FactSheet,
+        ) -> Result<(), Box<dyn std::error::Error>> {
+            panic!("Error: Too many bugs")
+        }
+    }
+
+    #[tokio::test]
+    async fn tests_writes_report_when_a_stage_panics() {
+        let run_dir: PathBuf = std::env::temp_dir().join("rust-backend-gen-agents-panicked-run");
+        let _ = std::fs::remove_dir_all(&run_dir);
+        let mut managing_agent: ManagingAgent = ManagingAgent {
+            _attributes: BasicAgent::new("Manage agents".to_string(), "Project Manager".to_string()),
+            factsheet: serde_json::from_str(
+                r#"{
+                  "project_description": "build a website that tracks books",
+                  "project_scope": null,
+                  "external_urls": null,
+                  "backend_code": null,
+                  "api_endpoint_schema": null
+                }"#,
+            )
+            .unwrap(),
+            agents: vec![PipelineStage {
+                name: "backend".to_string(),
+                depends_on: vec![],
+                model: None,
+                agent: Box::new(PanickingAgent {
+                    attributes: BasicAgent::new(
+                        "Write code".to_string(),
+                        "Backend Developer".to_string(),
+                    ),
+                }),
+            }],
+            run_dir: run_dir.clone(),
+            provider: None,
+            stop: Arc::new(Notify::new()),
+            processes: ProcessTracker::default(),
+        };
+
+        let outcome = AssertUnwindSafe(managing_agent.execute_project())
+            .catch_unwind()
+            .await;
+
+        assert!(outcome.is_err());
+        let markdown: String = std::fs::read_to_string(run_dir.join("REPORT.md")).unwrap();
+        assert!(markdown.contains("Backend Developer stopped the project: Error: Too many bugs"));
+        std::fs::remove_dir_all(run_dir).unwrap();
+    }
+
+    /// Never finishes on its own, like an agent waiting on the user
+    #[derive(Debug)]
+    struct WaitingAgent {
+        attributes: BasicAgent,
+    }
+
+    #[async_trait]
+    impl SpecialFunctions for WaitingAgent {
+        fn get_attributes_from_agent(&self) -> &BasicAgent {
+            &self.attributes
+        }
+
+        async fn execute(
+            &mut self,
+            _factsheet: &mut FactSheet,
+        ) -> Result<(), Box<dyn std::error::Error>> {
+            std::future::pending().await
+        }
+    }
+
+    #[tokio::test]
+    async fn tests_writes_report_when_stopped() {
+        let run_dir: PathBuf = std::env::temp_dir().join("rust-backend-gen-agents-stopped-run");
+        let _ = std::fs::remove_dir_all(&run_dir);
+        let mut managing_agent: ManagingAgent = ManagingAgent {
+            _attributes: BasicAgent::new("Manage agents".to_string(), "Project Manager".to_string()),
+            factsheet: serde_json::from_str(
+                r#"{
+                  "project_description": "build a website that tracks books",
+                  "project_scope": null,
+                  "external_urls": null,
+                  "backend_code": null,
+                  "api_endpoint_schema": null
+                }"#,
+            )
+            .unwrap(),
+            agents: vec![PipelineStage {
+                name: "backend".to_string(),
+                depends_on: vec![],
+                model: None,
+                agent: Box::new(WaitingAgent {
+                    attributes: BasicAgent::new(
+                        "Write code".to_string(),
+                        "Backend Developer".to_string(),
+                    ),
+                }),
+            }],
+            run_dir: run_dir.clone(),
+            provider: None,
+            stop: Arc::new(Notify::new()),
+            processes: ProcessTracker::default(),
+        };
+        managing_agent.stop_signal().notify_one();
+
+        let report: ProjectReport =
+            tokio::time::timeout(Duration::from_secs(5), managing_agent.execute_project())
+                .await
+                .expect("Stopping did not end the project");
+
+        assert!(!report.succeeded());
+        assert_eq!(report.stages[0].issues, vec!["Backend Developer was stopped by the user"]);
+        let markdown: String = std::fs::read_to_string(run_dir.join("REPORT.md")).unwrap();
+        assert!(markdown.contains("Backend Developer was stopped by the user"));
+        std::fs::remove_dir_all(run_dir).unwrap();
+    }
+
+    /// Leaves a process behind, like a stage stopped while its server runs
+    #[cfg(unix)]
+    #[derive(Debug)]
+    struct ServingAgent {
+        attributes: BasicAgent,
+        server_pid: Arc<Mutex<Option<u32>>>,
+        started: Arc<Notify>,
+    }
+
+    #[cfg(unix)]
+    #[async_trait]
+    impl SpecialFunctions for ServingAgent {
+        fn get_attributes_from_agent(&self) -> &BasicAgent {
+            &self.attributes
+        }
+
+        async fn execute(
+            &mut self,
+            _factsheet: &mut FactSheet,
+        ) -> Result<(), Box<dyn std::error::Error>> {
+            let server: std::process::Child =
+                spawn_tracked(std::process::Command::new("sleep").arg("30"))?;
+            *self.server_pid.lock().unwrap() = Some(server.id());
+            self.started.notify_one();
+            std::future::pending().await
+        }
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn tests_kills_stage_processes_when_stopped() {
+        let run_dir: PathBuf = std::env::temp_dir().join("rust-backend-gen-agents-killed-run");
+        let _ = std::fs::remove_dir_all(&run_dir);
+        let server_pid: Arc<Mutex<Option<u32>>> = Arc::new(Mutex::new(None));
+        let started: Arc<Notify> = Arc::new(Notify::new());
+        let mut managing_agent: ManagingAgent = ManagingAgent {
+            _attributes: BasicAgent::new("Manage agents".to_string(), "Project Manager".to_string()),
+            factsheet: serde_json::from_str(
+                r#"{
+                  "project_description": "build a website that tracks books",
+                  "project_scope": null,
+                  "external_urls": null,
+                  "backend_code": null,
+                  "api_endpoint_schema": null
+                }"#,
+            )
+            .unwrap(),
+            agents: vec![PipelineStage {
+                name: "backend".to_string(),
+                depends_on: vec![],
+                model: None,
+                agent: Box::new(ServingAgent {
+                    attributes: BasicAgent::new(
+                        "Write code".to_string(),
+                        "Backend Developer".to_string(),
+                    ),
+                    server_pid: Arc::clone(&server_pid),
+                    started: Arc::clone(&started),
+                }),
+            }],
+            run_dir: run_dir.clone(),
+            provider: None,
+            stop: Arc::new(Notify::new()),
+            processes: ProcessTracker::default(),
+        };
+        let stop: Arc<Notify> = managing_agent.stop_signal();
+        tokio::spawn(async move {
+            started.notified().await;
+            stop.notify_one();
+        });
+
+        tokio::time::timeout(Duration::from_secs(5), managing_agent.execute_project())
+            .await
+            .expect("Stopping did not end the project");
+
+        let server_pid: u32 = server_pid.lock().unwrap().expect("The server was not started");
+        // The aborted stage never waits on its server, a killed one is left as a zombie
+        let is_running = || {
+            std::fs::read_to_string(format!("/proc/{}/stat", server_pid))
+                .is_ok_and(|stat| !stat.contains(") Z "))
+        };
+        for _ in 0..100 {
+            if !is_running() {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        assert!(!is_running());
+        std::fs::remove_dir_all(run_dir).unwrap();
+    }
 
     #[tokio::test]
     async fn tests_managing_agent() {
         let usr_request: &str = "need a full stack app that fetches and tracks my fitness progress. Needs to include timezone info from the web.";
 
-        let mut managing_agent: ManagingAgent = ManagingAgent::new(usr_request.to_string())
-            .await
-            .expect("Error creating Managing Agent");
+        let run_dir: PathBuf = run_dir();
+        let agents: Vec<PipelineStage> = AgentRegistry::with_builtin_agents(
+            BackendSettings::from_env(),
+            DependencyAllowlist::from_env().unwrap(),
+            run_dir.clone(),
+        )
+        .create_stages(&PipelineDefinition::default())
+        .unwrap();
+        let mut managing_agent: ManagingAgent = ManagingAgent::new(
+            usr_request.to_string(),
+            Some(vec![]),
+            agents,
+            TargetFramework::from_env(),
+            run_dir,
+            None,
+        )
+        .await
+        .expect("Error creating Managing Agent");
 
         managing_agent.execute_project().await;
 
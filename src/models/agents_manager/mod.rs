@@ -1 +1,4 @@
-pub mod managing_agent;
\ No newline at end of file
+pub mod agent_graph;
+pub mod managing_agent;
+pub mod pipeline;
+pub mod report;
\ No newline at end of file
@@ -0,0 +1,382 @@
+use crate::models::agents::agent_architect::AgentSolutionArchitect;
+use crate::models::agents::agent_backend::AgentBackendDeveloper;
+use crate::models::agents::agent_traits::SpecialFunctions;
+use crate::models::general::dependencies::DependencyAllowlist;
+use crate::models::general::settings::{ApprovalPolicy, BackendSettings};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Pipeline shipped with the repo, replaced by the file in the PIPELINE_FILE env var
+pub const DEFAULT_PIPELINE_PATH: &str = "./pipeline.toml";
+
+/// One agent run in the pipeline and the settings it runs with
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct StageDefinition {
+    pub name: String,
+    /// Name the agent is registered under in the `AgentRegistry`
+    pub agent: String,
+    /// Stages that must finish before this one starts
+    #[serde(default)]
+    pub depends_on: Vec<String>,
+    /// LLM model for this agent, the default model when missing
+    #[serde(default)]
+    pub model: Option<String>,
+    /// Improvement iterations the agent may spend fixing its own output
+    #[serde(default)]
+    pub max_iterations: Option<usize>,
+    #[serde(default)]
+    pub approval: Option<ApprovalPolicy>,
+}
+
+impl StageDefinition {
+    fn new(name: &str, agent: &str, depends_on: &[&str]) -> Self {
+        Self {
+            name: name.to_string(),
+            agent: agent.to_string(),
+            depends_on: depends_on.iter().map(|stage| stage.to_string()).collect(),
+            model: None,
+            max_iterations: None,
+            approval: None,
+        }
+    }
+}
+
+/// Stages the managing agent runs, in file order unless dependencies say otherwise
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct PipelineDefinition {
+    pub stages: Vec<StageDefinition>,
+}
+
+impl Default for PipelineDefinition {
+    fn default() -> Self {
+        Self {
+            stages: vec![
+                StageDefinition::new("architect", "solution_architect", &[]),
+                StageDefinition::new("backend", "backend_developer", &["architect"]),
+            ],
+        }
+    }
+}
+
+impl PipelineDefinition {
+    pub fn parse(text: &str) -> Result<Self, toml::de::Error> {
+        toml::from_str(text)
+    }
+
+    /// Loads the pipeline from PIPELINE_FILE or the default file, the built-in pipeline when
+    /// PIPELINE_FILE is unset and the default file is missing
+    pub fn from_env() -> Result<Self, String> {
+        match std::env::var("PIPELINE_FILE") {
+            Ok(path) => Self::load(Path::new(&path)),
+            Err(_) if Path::new(DEFAULT_PIPELINE_PATH).exists() => {
+                Self::load(Path::new(DEFAULT_PIPELINE_PATH))
+            }
+            Err(_) => Ok(Self::default()),
+        }
+    }
+
+    /// Reads and parses a pipeline file, failing when it is missing
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let text: String = fs::read_to_string(path)
+            .map_err(|e| format!("Failed to read pipeline file {}: {}", path.display(), e))?;
+        Self::parse(&text)
+            .map_err(|e| format!("Failed to parse pipeline file {}: {}", path.display(), e))
+    }
+
+    /// Sets the approval policy of every backend developer stage, the only built-in agent that asks
+    pub fn set_approval(&mut self, approval: ApprovalPolicy) {
+        for stage in &mut self.stages {
+            if stage.agent == "backend_developer" {
+                stage.approval = Some(approval);
+            }
+        }
+    }
+
+    /// Stages ordered so each one runs after its dependencies, keeping file order where free
+    pub fn execution_order(&self) -> Result<Vec<&StageDefinition>, String> {
+        if self.stages.is_empty() {
+            return Err("Pipeline has no stages".to_string());
+        }
+
+        let mut names: HashSet<&str> = HashSet::new();
+        for stage in &self.stages {
+            if !names.insert(stage.name.as_str()) {
+                return Err(format!("Pipeline stage '{}' is defined twice", stage.name));
+            }
+        }
+        for stage in &self.stages {
+            if let Some(missing) = stage
+                .depends_on
+                .iter()
+                .find(|dependency| !names.contains(dependency.as_str()))
+            {
+                return Err(format!(
+                    "Pipeline stage '{}' depends on unknown stage '{}'",
+                    stage.name, missing
+                ));
+            }
+        }
+
+        let mut done: HashSet<&str> = HashSet::new();
+        let mut order: Vec<&StageDefinition> = vec![];
+        while order.len() < self.stages.len() {
+            let next: &StageDefinition = self
+                .stages
+                .iter()
+                .find(|stage| {
+                    !done.contains(stage.name.as_str())
+                        && stage
+                            .depends_on
+                            .iter()
+                            .all(|dependency| done.contains(dependency.as_str()))
+                })
+                .ok_or("Pipeline stages depend on each other in a cycle")?;
+            done.insert(next.name.as_str());
+            order.push(next);
+        }
+        Ok(order)
+    }
+}
+
+/// An agent created for a pipeline stage
+#[derive(Debug)]
+pub struct PipelineStage {
+    pub name: String,
+    pub depends_on: Vec<String>,
+    pub model: Option<String>,
+    pub agent: Box<dyn SpecialFunctions>,
+}
+
+/// Builds an agent from the settings of its stage
+pub type AgentConstructor = Box<dyn Fn(&StageDefinition) -> Box<dyn SpecialFunctions> + Send + Sync>;
+
+/// Agents the pipeline file can refer to by name
+#[derive(Default)]
+pub struct AgentRegistry {
+    constructors: BTreeMap<String, AgentConstructor>,
+    /// Agents that ignore `max_iterations` and `approval`, so stages setting them are rejected
+    without_settings: BTreeSet<String>,
+}
+
+impl std::fmt::Debug for AgentRegistry {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("AgentRegistry")
+            .field("agents", &self.names())
+            .finish()
+    }
+}
+
+impl AgentRegistry {
+    /// Registry with the solution architect and a backend developer starting from these settings
+    /// and picking crates from `allowlist`, saving its code history in `run_dir`
+    pub fn with_builtin_agents(
+        backend_settings: BackendSettings,
+        allowlist: DependencyAllowlist,
+        run_dir: PathBuf,
+    ) -> Self {
+        let mut registry: AgentRegistry = AgentRegistry::default();
+        registry.register("solution_architect", |_stage: &StageDefinition| {
+            Box::new(AgentSolutionArchitect::new()) as Box<dyn SpecialFunctions>
+        });
+        registry
+            .without_settings
+            .insert("solution_architect".to_string());
+        registry.register("backend_developer", move |stage: &StageDefinition| {
+            backend_developer(stage, backend_settings.clone(), allowlist.clone(), &run_dir)
+        });
+        registry
+    }
+
+    /// Registers an agent, replacing any agent with the same name
+    pub fn register<F>(&mut self, name: &str, constructor: F)
+    where
+        F: Fn(&StageDefinition) -> Box<dyn SpecialFunctions> + Send + Sync + 'static,
+    {
+        self.constructors
+            .insert(name.to_string(), Box::new(constructor));
+        self.without_settings.remove(name);
+    }
+
+    /// Takes over every agent of another registry, replacing agents with the same name
+    pub fn extend(&mut self, other: AgentRegistry) {
+        for name in other.constructors.keys() {
+            self.without_settings.remove(name);
+        }
+        self.constructors.extend(other.constructors);
+        self.without_settings.extend(other.without_settings);
+    }
+
+    /// Creates the agents of every stage in execution order
+    pub fn create_stages(&self, pipeline: &PipelineDefinition) -> Result<Vec<PipelineStage>, String> {
+        pipeline
+            .execution_order()?
+            .into_iter()
+            .map(|stage| {
+                let constructor: &AgentConstructor =
+                    self.constructors.get(&stage.agent).ok_or_else(|| {
+                        format!(
+                            "Pipeline stage '{}' uses unknown agent '{}', registered agents: {}",
+                            stage.name,
+                            stage.agent,
+                            self.names().join(", ")
+                        )
+                    })?;
+                if self.without_settings.contains(&stage.agent) {
+                    let ignored: Vec<&str> = [
+                        ("max_iterations", stage.max_iterations.is_some()),
+                        ("approval", stage.approval.is_some()),
+                    ]
+                    .into_iter()
+                    .filter_map(|(setting, set)| set.then_some(setting))
+                    .collect();
+                    if !ignored.is_empty() {
+                        return Err(format!(
+                            "Pipeline stage '{}' sets {}, which agent '{}' does not use",
+                            stage.name,
+                            ignored.join(" and "),
+                            stage.agent
+                        ));
+                    }
+                }
+                Ok(PipelineStage {
+                    name: stage.name.clone(),
+                    depends_on: stage.depends_on.clone(),
+                    model: stage.model.clone(),
+                    agent: constructor(stage),
+                })
+            })
+            .collect()
+    }
+
+    fn names(&self) -> Vec<&str> {
+        self.constructors.keys().map(String::as_str).collect()
+    }
+}
+
+fn backend_developer(
+    stage: &StageDefinition,
+    mut settings: BackendSettings,
+    allowlist: DependencyAllowlist,
+    run_dir: &Path,
+) -> Box<dyn SpecialFunctions> {
+    if let Some(max_iterations) = stage.max_iterations {
+        settings.max_bug_fixes = max_iterations;
+    }
+    if let Some(approval) = stage.approval {
+        settings.approval = approval;
+    }
+    Box::new(AgentBackendDeveloper::new(settings, allowlist, run_dir))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stage_names(pipeline: &PipelineDefinition) -> Result<Vec<&str>, String> {
+        pipeline
+            .execution_order()
+            .map(|order| order.iter().map(|stage| stage.name.as_str()).collect())
+    }
+
+    #[test]
+    fn tests_parses_pipeline_file() {
+        let pipeline: PipelineDefinition = PipelineDefinition::parse(
+            r#"
+            [[stages]]
+            name = "backend"
+            agent = "backend_developer"
+            depends_on = ["architect"]
+            model = "gpt-4o"
+            max_iterations = 4
+            approval = "auto"
+
+            [[stages]]
+            name = "architect"
+            agent = "solution_architect"
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(pipeline.stages[0].model.as_deref(), Some("gpt-4o"));
+        assert_eq!(pipeline.stages[0].max_iterations, Some(4));
+        assert_eq!(pipeline.stages[0].approval, Some(ApprovalPolicy::Auto));
+        assert_eq!(stage_names(&pipeline), Ok(vec!["architect", "backend"]));
+        assert!(PipelineDefinition::parse("[[stages]]\nname = \"a\"\nagent = \"b\"\nmodle = \"x\"").is_err());
+
+        let shipped: PipelineDefinition =
+            PipelineDefinition::parse(&fs::read_to_string(DEFAULT_PIPELINE_PATH).unwrap()).unwrap();
+        assert_eq!(stage_names(&shipped), Ok(vec!["architect", "backend"]));
+    }
+
+    #[test]
+    fn tests_rejects_invalid_pipelines() {
+        let mut pipeline: PipelineDefinition = PipelineDefinition::default();
+        pipeline.stages[0].depends_on = vec!["backend".to_string()];
+        assert!(stage_names(&pipeline).unwrap_err().contains("cycle"));
+
+        pipeline.stages[0].depends_on = vec!["frontend".to_string()];
+        assert!(stage_names(&pipeline).unwrap_err().contains("unknown stage 'frontend'"));
+
+        pipeline.stages[0] = pipeline.stages[1].clone();
+        assert!(stage_names(&pipeline).unwrap_err().contains("defined twice"));
+
+        assert!(PipelineDefinition { stages: vec![] }.execution_order().is_err());
+    }
+
+    #[test]
+    fn tests_creates_registered_agents() {
+        let registry: AgentRegistry = AgentRegistry::with_builtin_agents(
+            BackendSettings::default(),
+            DependencyAllowlist::default(),
+            std::env::temp_dir(),
+        );
+        let stages: Vec<PipelineStage> = registry
+            .create_stages(&PipelineDefinition::default())
+            .unwrap();
+        let positions: Vec<&str> = stages
+            .iter()
+            .map(|stage| stage.agent.get_attributes_from_agent().position.as_str())
+            .collect();
+        assert_eq!(positions, vec!["Solutions Architect", "Backend Developer"]);
+
+        let mut pipeline: PipelineDefinition = PipelineDefinition::default();
+        pipeline.stages[1].agent = "frontend_developer".to_string();
+        let error: String = registry.create_stages(&pipeline).unwrap_err();
+        assert!(error.contains("unknown agent 'frontend_developer'"));
+        assert!(error.contains("backend_developer, solution_architect"));
+    }
+
+    #[test]
+    fn tests_rejects_settings_the_agent_ignores() {
+        let mut registry: AgentRegistry = AgentRegistry::with_builtin_agents(
+            BackendSettings::default(),
+            DependencyAllowlist::default(),
+            std::env::temp_dir(),
+        );
+        let mut pipeline: PipelineDefinition = PipelineDefinition::default();
+        pipeline.stages[0].max_iterations = Some(4);
+        pipeline.stages[0].approval = Some(ApprovalPolicy::Auto);
+
+        let error: String = registry.create_stages(&pipeline).unwrap_err();
+        assert!(error.contains(
+            "stage 'architect' sets max_iterations and approval, which agent 'solution_architect' does not use"
+        ));
+
+        // An agent registered in its place decides for itself
+        registry.register("solution_architect", |_stage: &StageDefinition| {
+            Box::new(AgentSolutionArchitect::new()) as Box<dyn SpecialFunctions>
+        });
+        assert!(registry.create_stages(&pipeline).is_ok());
+    }
+
+    #[test]
+    fn tests_fails_on_missing_pipeline_file() {
+        let error: String =
+            PipelineDefinition::load(Path::new("./no-such-pipeline.toml")).unwrap_err();
+        assert!(error.contains("Failed to read pipeline file ./no-such-pipeline.toml"));
+    }
+}
\ No newline at end of file
@@ -0,0 +1,86 @@
+use crate::models::general::llm::LlmUsage;
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+
+/// One version of its output an agent produced and how its build went
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct IterationSummary {
+    pub number: usize,
+    pub label: String,
+    /// None when the version was never built
+    pub compiled: Option<bool>,
+    pub error_count: usize,
+}
+
+/// How one pipeline stage went
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct StageReport {
+    pub stage: String,
+    /// Position of the agent that ran the stage
+    pub agent: String,
+    pub finished: bool,
+    /// Failures and FactSheet merge problems reported while the stage ran
+    pub issues: Vec<String>,
+    pub artifacts: Vec<PathBuf>,
+    #[serde(default)]
+    pub iterations: Vec<IterationSummary>,
+    /// LLM calls made while the stage ran
+    #[serde(default)]
+    pub usage: LlmUsage,
+    #[serde(default)]
+    pub duration_ms: u64,
+}
+
+impl StageReport {
+    /// Stage that did not get to finish, with the reason as its only issue
+    pub fn stopped(stage: &str, agent: &str, issue: String) -> Self {
+        Self {
+            stage: stage.to_string(),
+            agent: agent.to_string(),
+            finished: false,
+            issues: vec![issue],
+            artifacts: vec![],
+            iterations: vec![],
+            usage: LlmUsage::default(),
+            duration_ms: 0,
+        }
+    }
+}
+
+/// Outcome of every stage, in execution order
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Default)]
+pub struct ProjectReport {
+    pub stages: Vec<StageReport>,
+    #[serde(default)]
+    pub duration_ms: u64,
+    /// `REPORT.md` and `REPORT.html` written to the run directory
+    #[serde(default)]
+    pub report_files: Vec<PathBuf>,
+}
+
+impl ProjectReport {
+    /// True when every stage finished without issues
+    pub fn succeeded(&self) -> bool {
+        self.stages
+            .iter()
+            .all(|stage| stage.finished && stage.issues.is_empty())
+    }
+
+    /// Files produced by all stages and the report files
+    pub fn artifacts(&self) -> Vec<PathBuf> {
+        self.stages
+            .iter()
+            .flat_map(|stage| stage.artifacts.iter().cloned())
+            .chain(self.report_files.iter().cloned())
+            .collect()
+    }
+
+    /// LLM calls made by all stages
+    pub fn usage(&self) -> LlmUsage {
+        let mut usage: LlmUsage = LlmUsage::default();
+        for stage in &self.stages {
+            usage.add(&stage.usage);
+        }
+        usage
+    }
+}
\ No newline at end of file
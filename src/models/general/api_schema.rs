@@ -0,0 +1,289 @@
+use serde::de::{self, Deserializer};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::collections::BTreeMap;
+use std::str::FromStr;
+use strum_macros::{Display, EnumString};
+
+/// HTTP method of a generated endpoint
+#[derive(Debug, Serialize, Clone, Copy, PartialEq, Eq, Hash, Display, EnumString)]
+#[serde(rename_all = "lowercase")]
+#[strum(serialize_all = "lowercase", ascii_case_insensitive)]
+pub enum HttpMethod {
+    Get,
+    Post,
+    Put,
+    Patch,
+    Delete,
+    Head,
+    Options,
+}
+
+impl<'de> Deserialize<'de> for HttpMethod {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let raw: String = String::deserialize(deserializer)?;
+        HttpMethod::from_str(raw.trim()).map_err(|_| {
+            de::Error::unknown_variant(
+                &raw,
+                &["get", "post", "put", "patch", "delete", "head", "options"],
+            )
+        })
+    }
+}
+
+/// Typed description of a request or response body
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Default)]
+#[serde(from = "Value", into = "Value")]
+pub enum SchemaNode {
+    /// The endpoint takes or returns no body
+    #[default]
+    None,
+    /// The body exists but its shape could not be determined from the code
+    NotProvided,
+    String,
+    Number,
+    Bool,
+    Array(Box<SchemaNode>),
+    Object(BTreeMap<String, SchemaNode>),
+    /// Any type name the model printed that is not one of the above
+    Other(String),
+}
+
+impl SchemaNode {
+    pub fn is_none(&self) -> bool {
+        matches!(self, SchemaNode::None)
+    }
+}
+
+impl From<Value> for SchemaNode {
+    fn from(value: Value) -> Self {
+        match value {
+            Value::Null => SchemaNode::None,
+            Value::Bool(_) => SchemaNode::Bool,
+            Value::Number(_) => SchemaNode::Number,
+            Value::String(type_name) => SchemaNode::from_type_name(&type_name),
+            Value::Array(items) => SchemaNode::Array(Box::new(
+                items
+                    .into_iter()
+                    .next()
+                    .map(SchemaNode::from)
+                    .unwrap_or(SchemaNode::NotProvided),
+            )),
+            Value::Object(fields) => SchemaNode::Object(
+                fields
+                    .into_iter()
+                    .map(|(key, value)| (key, SchemaNode::from(value)))
+                    .collect(),
+            ),
+        }
+    }
+}
+
+impl From<SchemaNode> for Value {
+    fn from(node: SchemaNode) -> Self {
+        match node {
+            SchemaNode::None => Value::Null,
+            SchemaNode::NotProvided => Value::String("not_provided".to_string()),
+            SchemaNode::String => Value::String("string".to_string()),
+            SchemaNode::Number => Value::String("number".to_string()),
+            SchemaNode::Bool => Value::String("bool".to_string()),
+            SchemaNode::Array(item) => Value::Array(vec![Value::from(*item)]),
+            SchemaNode::Object(fields) => Value::Object(
+                fields
+                    .into_iter()
+                    .map(|(key, node)| (key, Value::from(node)))
+                    .collect::<Map<_, _>>(),
+            ),
+            SchemaNode::Other(type_name) => Value::String(type_name),
+        }
+    }
+}
+
+impl SchemaNode {
+    /// Maps the type names the LLM tends to print onto schema nodes
+    fn from_type_name(type_name: &str) -> Self {
+        let normalized: String = type_name.trim().to_lowercase();
+        match normalized.as_str() {
+            "" | "none" | "null" | "n/a" | "empty" => SchemaNode::None,
+            "not_provided" | "not provided" | "unknown" | "any" | "json" => SchemaNode::NotProvided,
+            "string" | "str" | "&str" | "text" | "date" | "datetime" | "uuid" => SchemaNode::String,
+            "number" | "integer" | "int" | "float" | "u8" | "u16" | "u32" | "u64" | "usize"
+            | "i8" | "i16" | "i32" | "i64" | "isize" | "f32" | "f64" => SchemaNode::Number,
+            "bool" | "boolean" => SchemaNode::Bool,
+            _ => {
+                // Nested JSON printed as a string, e.g. "{\"id\": \"number\"}"
+                if let Ok(value) = serde_json::from_str::<Value>(type_name)
+                    && (value.is_object() || value.is_array())
+                {
+                    return SchemaNode::from(value);
+                }
+                if let Some(inner) = normalized
+                    .strip_prefix("vec<")
+                    .and_then(|rest| rest.strip_suffix('>'))
+                {
+                    return SchemaNode::Array(Box::new(SchemaNode::from_type_name(inner)));
+                }
+                SchemaNode::Other(type_name.trim().to_string())
+            }
+        }
+    }
+}
+
+/// Accepts `true`, `"true"`, `"yes"`, `1` and their negative counterparts
+pub fn deserialize_lenient_bool<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<bool, D::Error> {
+    match Value::deserialize(deserializer)? {
+        Value::Bool(flag) => Ok(flag),
+        Value::Number(number) => Ok(number.as_f64().unwrap_or(0.0) != 0.0),
+        Value::String(text) => match text.trim().to_lowercase().as_str() {
+            "true" | "yes" | "y" | "1" => Ok(true),
+            "false" | "no" | "n" | "0" | "" | "none" => Ok(false),
+            other => Err(de::Error::custom(format!(
+                "expected a boolean, found '{}'",
+                other
+            ))),
+        },
+        Value::Null => Ok(false),
+        other => Err(de::Error::custom(format!(
+            "expected a boolean, found {}",
+            other
+        ))),
+    }
+}
+
+/// Accepts a list or a single value, with elements as strings or objects carrying a `name`
+pub fn deserialize_lenient_string_list<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Vec<String>, D::Error> {
+    let to_name = |value: Value| -> Option<String> {
+        match value {
+            Value::String(text)
+                if !text.trim().is_empty() && text.trim().to_lowercase() != "none" =>
+            {
+                Some(text.trim().to_string())
+            }
+            Value::Object(fields) => fields
+                .get("name")
+                .and_then(Value::as_str)
+                .map(str::to_string),
+            _ => None,
+        }
+    };
+
+    Ok(match Value::deserialize(deserializer)? {
+        Value::Array(items) => items.into_iter().filter_map(to_name).collect(),
+        Value::String(text) => text
+            .split(',')
+            .filter_map(|name| to_name(Value::String(name.to_string())))
+            .collect(),
+        other => to_name(other).into_iter().collect(),
+    })
+}
+
+/// Accepts status codes as numbers or strings, in a list or on their own
+pub fn deserialize_lenient_status_codes<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Vec<u16>, D::Error> {
+    let to_code = |value: &Value| -> Option<u16> {
+        match value {
+            Value::Number(number) => number.as_u64().and_then(|code| u16::try_from(code).ok()),
+            Value::String(text) => text.trim().get(..3).and_then(|code| code.parse().ok()),
+            _ => None,
+        }
+    };
+
+    Ok(match Value::deserialize(deserializer)? {
+        Value::Array(items) => items.iter().filter_map(to_code).collect(),
+        other => to_code(&other).into_iter().collect(),
+    })
+}
+
+/// Extracts `{param}` and `:param` segments from a route
+pub fn extract_path_params(route: &str) -> Vec<String> {
+    route
+        .split('/')
+        .filter_map(|segment| {
+            segment
+                .strip_prefix('{')
+                .and_then(|rest| rest.strip_suffix('}'))
+                .or_else(|| segment.strip_prefix(':'))
+                .or_else(|| {
+                    segment
+                        .strip_prefix('<')
+                        .and_then(|rest| rest.strip_suffix('>'))
+                })
+                .map(|name| name.split(':').next().unwrap_or(name).to_string())
+        })
+        .filter(|name| !name.is_empty())
+        .collect()
+}
+
+/// Strips markdown code fences the model sometimes wraps JSON in
+pub fn strip_code_fences(text: &str) -> &str {
+    let trimmed: &str = text.trim();
+    match trimmed.strip_prefix("```") {
+        Some(rest) => {
+            let body: &str = rest.split_once('\n').map(|(_, body)| body).unwrap_or(rest);
+            body.trim_end().strip_suffix("```").unwrap_or(body).trim()
+        }
+        None => trimmed,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tests_schema_node_from_legacy_values() {
+        let node: SchemaNode = SchemaNode::from(serde_json::json!({
+            "id": "number",
+            "name": "string",
+            "tags": ["string"],
+            "completed": "bool"
+        }));
+
+        let SchemaNode::Object(fields) = node else {
+            panic!("Expected object schema node");
+        };
+        assert_eq!(fields["id"], SchemaNode::Number);
+        assert_eq!(
+            fields["tags"],
+            SchemaNode::Array(Box::new(SchemaNode::String))
+        );
+        assert_eq!(
+            SchemaNode::from(Value::String("None".to_string())),
+            SchemaNode::None
+        );
+        assert_eq!(
+            SchemaNode::from(Value::String("not_provided".to_string())),
+            SchemaNode::NotProvided
+        );
+    }
+
+    #[test]
+    fn tests_http_method_is_case_insensitive() {
+        let method: HttpMethod = serde_json::from_str("\"GET\"").unwrap();
+        assert_eq!(method, HttpMethod::Get);
+        assert_eq!(
+            serde_json::to_string(&HttpMethod::Delete).unwrap(),
+            "\"delete\""
+        );
+    }
+
+    #[test]
+    fn tests_extract_path_params() {
+        assert_eq!(
+            extract_path_params("/item/{id}/tags/:tag"),
+            vec!["id", "tag"]
+        );
+        assert!(extract_path_params("/crypto").is_empty());
+    }
+
+    #[test]
+    fn tests_strip_code_fences() {
+        assert_eq!(strip_code_fences("```json\n[1, 2]\n```"), "[1, 2]");
+        assert_eq!(strip_code_fences("[1, 2]"), "[1, 2]");
+    }
+}
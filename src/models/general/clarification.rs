@@ -0,0 +1,127 @@
+use serde::{Deserialize, Serialize};
+use std::fs;
+
+/// Questions asked when MAX_CLARIFYING_QUESTIONS is not set, 0 disables the dialogue
+pub const DEFAULT_MAX_CLARIFYING_QUESTIONS: usize = 3;
+
+/// A clarifying question and the user's answer to it
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct Clarification {
+    pub question: String,
+    pub answer: String,
+}
+
+/// Goal and requirements printed by `print_clarified_goal`
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct ClarifiedGoal {
+    pub project_description: String,
+    #[serde(default)]
+    pub requirements: Vec<String>,
+}
+
+/// Request and answers for a non-interactive run, passed with `--spec <path>`
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct ProjectSpecFile {
+    pub request: String,
+    /// Answers given up front in place of the clarifying-question dialogue
+    #[serde(default)]
+    pub answers: Vec<Clarification>,
+}
+
+impl ProjectSpecFile {
+    pub fn parse(text: &str) -> Result<Self, toml::de::Error> {
+        toml::from_str(text)
+    }
+
+    /// Takes `--spec <path>` out of the CLI args and loads the file, None when not given
+    pub fn from_args(args: &mut Vec<String>) -> Result<Option<Self>, String> {
+        let Some(index) = args.iter().position(|arg| arg == "--spec") else {
+            return Ok(None);
+        };
+        if index + 1 >= args.len() {
+            return Err("--spec needs a path".to_string());
+        }
+
+        let path: String = args.drain(index..=index + 1).nth(1).unwrap_or_default();
+        let text: String = fs::read_to_string(&path)
+            .map_err(|e| format!("Failed to read spec file {}: {}", path, e))?;
+        Self::parse(&text)
+            .map(Some)
+            .map_err(|e| format!("Failed to parse spec file {}: {}", path, e))
+    }
+}
+
+/// Request and answers laid out for `print_clarified_goal`
+pub fn clarified_request(user_request: &str, clarifications: &[Clarification]) -> String {
+    let mut context: String = format!("USER_REQUEST: {}\nCLARIFICATIONS:\n", user_request);
+    for clarification in clarifications {
+        context.push_str(&format!(
+            "  Q: {}\n  A: {}\n",
+            clarification.question, clarification.answer
+        ));
+    }
+    context
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tests_parses_spec_file() {
+        let spec: ProjectSpecFile = ProjectSpecFile::parse(
+            r#"
+            request = "build a booking site for my gym"
+
+            [[answers]]
+            question = "Who can book classes?"
+            answer = "Only registered members"
+
+            [[answers]]
+            question = "Do bookings need payment?"
+            answer = "No"
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(spec.request, "build a booking site for my gym");
+        assert_eq!(spec.answers.len(), 2);
+        assert_eq!(spec.answers[0].answer, "Only registered members");
+        assert!(ProjectSpecFile::parse("answers = []").is_err());
+    }
+
+    #[test]
+    fn tests_takes_spec_from_args() {
+        let path = std::env::temp_dir().join("rust-backend-gen-agents-spec.toml");
+        fs::write(&path, "request = \"build a todo api\"").unwrap();
+        let mut args: Vec<String> = vec![
+            "agents".to_string(),
+            "--spec".to_string(),
+            path.display().to_string(),
+            "--tui".to_string(),
+        ];
+
+        let spec: Option<ProjectSpecFile> = ProjectSpecFile::from_args(&mut args).unwrap();
+
+        assert_eq!(spec.unwrap().answers, vec![]);
+        assert_eq!(args, vec!["agents", "--tui"]);
+        assert!(ProjectSpecFile::from_args(&mut vec!["--spec".to_string()]).is_err());
+        assert_eq!(ProjectSpecFile::from_args(&mut args), Ok(None));
+    }
+
+    #[test]
+    fn tests_lays_out_clarified_request() {
+        let context: String = clarified_request(
+            "build a todo api",
+            &[Clarification {
+                question: "Do todos have due dates?".to_string(),
+                answer: "Yes".to_string(),
+            }],
+        );
+
+        assert_eq!(
+            context,
+            "USER_REQUEST: build a todo api\nCLARIFICATIONS:\n  Q: Do todos have due dates?\n  A: Yes\n"
+        );
+    }
+}
\ No newline at end of file
@@ -0,0 +1,204 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+/// Allowlist shipped with the repo, replaced by the file in the DEPENDENCY_ALLOWLIST env var
+pub const DEFAULT_ALLOWLIST_PATH: &str = "./dependency_allowlist.toml";
+
+/// A crate the generated project may use, pinned to an exact version
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct AllowedCrate {
+    pub version: String,
+    #[serde(default)]
+    pub features: Vec<String>,
+    /// What the crate is for, shown to the model when it picks crates
+    #[serde(default)]
+    pub description: String,
+}
+
+/// Extra crate merged into the generated Cargo.toml
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct CrateDependency {
+    pub name: String,
+    pub version: String,
+    #[serde(default)]
+    pub features: Vec<String>,
+}
+
+impl CrateDependency {
+    /// Cargo.toml value with the version pinned exactly
+    pub fn manifest_spec(&self) -> String {
+        if self.features.is_empty() {
+            return format!("\"={}\"", self.version);
+        }
+        let features: Vec<String> = self
+            .features
+            .iter()
+            .map(|feature| format!("\"{}\"", feature))
+            .collect();
+        format!(
+            "{{ version = \"={}\", features = [{}] }}",
+            self.version,
+            features.join(", ")
+        )
+    }
+}
+
+/// A crate the model asked for, as printed by `print_crate_requests`
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct CrateRequest {
+    pub name: String,
+    #[serde(default)]
+    pub reason: String,
+}
+
+/// Crate requests split into the ones merged into the manifest and the refused ones
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct CrateReview {
+    pub approved: Vec<CrateDependency>,
+    pub rejected: Vec<String>,
+}
+
+/// Crates the backend developer may add on top of the framework dependencies
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Default)]
+pub struct DependencyAllowlist {
+    #[serde(flatten)]
+    pub crates: BTreeMap<String, AllowedCrate>,
+}
+
+impl DependencyAllowlist {
+    pub fn parse(text: &str) -> Result<Self, toml::de::Error> {
+        toml::from_str(text)
+    }
+
+    /// Loads the allowlist from DEPENDENCY_ALLOWLIST or the default file, empty when
+    /// DEPENDENCY_ALLOWLIST is unset and the default file is missing
+    pub fn from_env() -> Result<Self, String> {
+        match std::env::var("DEPENDENCY_ALLOWLIST") {
+            Ok(path) => Self::load(Path::new(&path)),
+            Err(_) if Path::new(DEFAULT_ALLOWLIST_PATH).exists() => {
+                Self::load(Path::new(DEFAULT_ALLOWLIST_PATH))
+            }
+            Err(_) => Ok(Self::default()),
+        }
+    }
+
+    /// Reads and parses an allowlist file, failing when it is missing
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let text: String = fs::read_to_string(path)
+            .map_err(|e| format!("Failed to read dependency allowlist {}: {}", path.display(), e))?;
+        Self::parse(&text)
+            .map_err(|e| format!("Failed to parse dependency allowlist {}: {}", path.display(), e))
+    }
+
+    /// Allowed crates with their descriptions, for the prompt
+    pub fn prompt_listing(&self) -> String {
+        self.crates
+            .iter()
+            .map(|(name, allowed)| format!("{}: {}", name, allowed.description))
+            .collect::<Vec<String>>()
+            .join("\n")
+    }
+
+    /// Pins allowed requests and refuses the rest, skipping crates already provided
+    pub fn review(&self, requests: &[CrateRequest], provided: &[&str]) -> CrateReview {
+        let mut review: CrateReview = CrateReview::default();
+        for request in requests {
+            let name: String = request.name.trim().to_lowercase();
+            if provided.contains(&name.as_str())
+                || review.approved.iter().any(|approved| approved.name == name)
+            {
+                continue;
+            }
+
+            match self.crates.get(&name) {
+                Some(allowed) => review.approved.push(CrateDependency {
+                    name,
+                    version: allowed.version.clone(),
+                    features: allowed.features.clone(),
+                }),
+                None => review.rejected.push(name),
+            }
+        }
+        review
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn allowlist() -> DependencyAllowlist {
+        DependencyAllowlist::parse(
+            r#"
+            [uuid]
+            version = "1.17.0"
+            features = ["v4", "serde"]
+            description = "Unique ids"
+
+            [argon2]
+            version = "0.5.3"
+            description = "Password hashing"
+            "#,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn tests_reviews_crate_requests() {
+        let requests: Vec<CrateRequest> = serde_json::from_str(
+            r#"[{"name": "UUID", "reason": "ids"}, {"name": "openssl"}, {"name": "serde"}, {"name": "uuid"}]"#,
+        )
+        .unwrap();
+
+        let review: CrateReview = allowlist().review(&requests, &["serde", "tokio"]);
+        assert_eq!(review.approved.len(), 1);
+        assert_eq!(review.approved[0].name, "uuid");
+        assert_eq!(review.rejected, vec!["openssl".to_string()]);
+    }
+
+    #[test]
+    fn tests_pins_manifest_specs() {
+        let review: CrateReview = allowlist().review(
+            &[
+                CrateRequest {
+                    name: "argon2".to_string(),
+                    reason: String::new(),
+                },
+                CrateRequest {
+                    name: "uuid".to_string(),
+                    reason: String::new(),
+                },
+            ],
+            &[],
+        );
+        assert_eq!(review.approved[0].manifest_spec(), "\"=0.5.3\"");
+        assert_eq!(
+            review.approved[1].manifest_spec(),
+            "{ version = \"=1.17.0\", features = [\"v4\", \"serde\"] }"
+        );
+    }
+
+    #[test]
+    fn tests_loads_default_allowlist() {
+        let text: String = fs::read_to_string(DEFAULT_ALLOWLIST_PATH).unwrap();
+        let allowlist: DependencyAllowlist = DependencyAllowlist::parse(&text).unwrap();
+        assert!(allowlist.crates.contains_key("chrono"));
+        assert!(allowlist.crates.values().all(|allowed| !allowed.description.is_empty()));
+    }
+
+    #[test]
+    fn tests_reports_broken_allowlist_files() {
+        let missing: String =
+            DependencyAllowlist::load(Path::new("./no-such-allowlist.toml")).unwrap_err();
+        assert!(missing.starts_with("Failed to read dependency allowlist ./no-such-allowlist.toml"));
+
+        let path: std::path::PathBuf =
+            std::env::temp_dir().join("rust-backend-gen-agents-broken-allowlist.toml");
+        fs::write(&path, "[chrono]\nversion = 4\n").unwrap();
+        let broken: String = DependencyAllowlist::load(&path).unwrap_err();
+        fs::remove_file(&path).unwrap();
+        assert!(broken.starts_with("Failed to parse dependency allowlist"));
+    }
+}
@@ -0,0 +1,168 @@
+use crate::models::general::api_schema::SchemaNode;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// Maximum number of characters kept from an external API response
+pub const MAX_SAMPLE_CHARS: usize = 2000;
+
+/// Number of array items kept when truncating a JSON list response
+const MAX_SAMPLE_ITEMS: usize = 2;
+
+/// Largest response body recorded for replaying the API offline
+pub const MAX_FIXTURE_BYTES: usize = 1_000_000;
+
+/// Number of array items inspected when inferring the schema of a list
+const MAX_SCHEMA_ITEMS: usize = 20;
+
+/// External URL the architect dropped and why
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct ExcludedUrl {
+    pub url: String,
+    pub reason: String,
+}
+
+/// Response recorded while checking an external URL
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct ExternalApiSample {
+    pub url: String,
+    pub status_code: u16,
+    pub content_type: Option<String>,
+    /// Truncated body, only meant to show the model what the data looks like
+    pub sample: String,
+    pub schema: Option<SchemaNode>,
+    /// Full body as received, replayed by the fixture servers during unit testing
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub body: Option<String>,
+    /// Time until the whole response was read, redirects included
+    #[serde(default)]
+    pub latency_ms: u64,
+}
+
+impl ExternalApiSample {
+    pub fn new(url: &str, status_code: u16, content_type: Option<String>, body: String) -> Self {
+        let parsed: Option<Value> = serde_json::from_str(&body).ok();
+        Self {
+            url: url.to_string(),
+            status_code,
+            content_type,
+            sample: truncate_sample(&body, parsed.as_ref()),
+            schema: parsed.as_ref().map(infer_schema),
+            body: (body.len() <= MAX_FIXTURE_BYTES).then_some(body),
+            latency_ms: 0,
+        }
+    }
+}
+
+/// Keeps the first items of a JSON list and cuts the text at a character limit
+pub fn truncate_sample(body: &str, parsed: Option<&Value>) -> String {
+    let shortened: String = match parsed {
+        Some(Value::Array(items)) if items.len() > MAX_SAMPLE_ITEMS => {
+            let kept: Vec<Value> = items.iter().take(MAX_SAMPLE_ITEMS).cloned().collect();
+            serde_json::to_string(&kept).unwrap_or_else(|_| body.to_string())
+        }
+        _ => body.to_string(),
+    };
+
+    match shortened.char_indices().nth(MAX_SAMPLE_CHARS) {
+        Some((cut, _)) => format!("{}...", &shortened[..cut]),
+        None => shortened,
+    }
+}
+
+/// Infers a schema tree from a real JSON document
+pub fn infer_schema(value: &Value) -> SchemaNode {
+    match value {
+        Value::Null => SchemaNode::NotProvided,
+        Value::Bool(_) => SchemaNode::Bool,
+        Value::Number(_) => SchemaNode::Number,
+        Value::String(_) => SchemaNode::String,
+        Value::Array(items) => {
+            let item_schema: SchemaNode = items
+                .iter()
+                .take(MAX_SCHEMA_ITEMS)
+                .map(infer_schema)
+                .reduce(merge_schemas)
+                .unwrap_or(SchemaNode::NotProvided);
+            SchemaNode::Array(Box::new(item_schema))
+        }
+        Value::Object(fields) => SchemaNode::Object(
+            fields
+                .iter()
+                .map(|(key, value)| (key.clone(), infer_schema(value)))
+                .collect(),
+        ),
+    }
+}
+
+/// Combines the schemas of two list items so optional keys are not lost
+fn merge_schemas(left: SchemaNode, right: SchemaNode) -> SchemaNode {
+    match (left, right) {
+        (SchemaNode::Object(mut left_fields), SchemaNode::Object(right_fields)) => {
+            for (key, right_node) in right_fields {
+                let merged: SchemaNode = match left_fields.remove(&key) {
+                    Some(left_node) => merge_schemas(left_node, right_node),
+                    None => right_node,
+                };
+                left_fields.insert(key, merged);
+            }
+            SchemaNode::Object(left_fields)
+        }
+        (SchemaNode::Array(left_item), SchemaNode::Array(right_item)) => {
+            SchemaNode::Array(Box::new(merge_schemas(*left_item, *right_item)))
+        }
+        (SchemaNode::NotProvided, other) | (other, SchemaNode::NotProvided) => other,
+        (left, _) => left,
+    }
+}
+
+/// Renders a schema as the compact JSON the prompts use
+pub fn schema_to_string(schema: &SchemaNode) -> String {
+    serde_json::to_string(&Value::from(schema.clone())).unwrap_or_default()
+}
+
+/// Field names at the top level of a schema, looking through lists
+pub fn schema_field_names(schema: &SchemaNode) -> Vec<String> {
+    match schema {
+        SchemaNode::Object(fields) => fields.keys().cloned().collect(),
+        SchemaNode::Array(item) => schema_field_names(item),
+        _ => vec![],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn tests_infers_schema_across_list_items() {
+        let body: Value = json!([
+            { "name": "Harry", "house": "Gryffindor" },
+            { "name": "Severus", "patronus": null, "wand": { "wood": "ebony" } }
+        ]);
+
+        let schema: SchemaNode = infer_schema(&body);
+
+        assert_eq!(
+            schema_to_string(&schema),
+            r#"[{"house":"string","name":"string","patronus":"not_provided","wand":{"wood":"string"}}]"#
+        );
+        assert_eq!(
+            schema_field_names(&schema),
+            vec!["house", "name", "patronus", "wand"]
+        );
+    }
+
+    #[test]
+    fn tests_truncates_sample() {
+        let body: String = json!([1, 2, 3, 4]).to_string();
+        let parsed: Value = serde_json::from_str(&body).unwrap();
+        assert_eq!(truncate_sample(&body, Some(&parsed)), "[1,2]");
+
+        let long_body: String = "x".repeat(MAX_SAMPLE_CHARS + 10);
+        assert_eq!(
+            truncate_sample(&long_body, None).len(),
+            MAX_SAMPLE_CHARS + 3
+        );
+    }
+}
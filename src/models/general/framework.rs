@@ -0,0 +1,165 @@
+use crate::models::general::dependencies::CrateDependency;
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+use strum_macros::{Display, EnumString};
+
+/// Dependencies every generated project gets regardless of the web framework
+const COMMON_DEPENDENCIES: [(&str, &str); 5] = [
+    ("async-trait", "\"0.1.88\""),
+    (
+        "reqwest",
+        "{ version = \"0.12.15\", features = [\"json\"] }",
+    ),
+    (
+        "serde",
+        "{ version = \"1.0.219\", features = [\"derive\"] }",
+    ),
+    ("serde_json", "\"1.0.140\""),
+    ("tokio", "{ version = \"1.44.2\", features = [\"full\"] }"),
+];
+
+/// Web framework the generated backend is written in
+#[derive(
+    Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Hash, Default, Display, EnumString,
+)]
+#[serde(rename_all = "kebab-case")]
+#[strum(serialize_all = "kebab-case", ascii_case_insensitive)]
+pub enum TargetFramework {
+    #[default]
+    #[strum(to_string = "actix-web", serialize = "actix")]
+    ActixWeb,
+    Axum,
+    Rocket,
+}
+
+impl TargetFramework {
+    /// Reads the TARGET_FRAMEWORK env var, defaulting to actix-web
+    pub fn from_env() -> Self {
+        std::env::var("TARGET_FRAMEWORK")
+            .ok()
+            .and_then(|name| TargetFramework::from_str(name.trim()).ok())
+            .unwrap_or_default()
+    }
+
+    /// Framework specific dependencies of the generated Cargo.toml
+    pub fn dependencies(&self) -> Vec<(&'static str, &'static str)> {
+        let framework_dependencies: Vec<(&str, &str)> = match self {
+            TargetFramework::ActixWeb => {
+                vec![("actix-cors", "\"0.7.1\""), ("actix-web", "\"4.11.0\"")]
+            }
+            TargetFramework::Axum => vec![
+                ("axum", "\"0.8.4\""),
+                (
+                    "tower-http",
+                    "{ version = \"0.6.4\", features = [\"cors\"] }",
+                ),
+            ],
+            TargetFramework::Rocket => {
+                vec![("rocket", "{ version = \"0.5.1\", features = [\"json\"] }")]
+            }
+        };
+
+        let mut dependencies: Vec<(&str, &str)> = COMMON_DEPENDENCIES
+            .iter()
+            .copied()
+            .chain(framework_dependencies)
+            .collect();
+        dependencies.sort_by_key(|(name, _)| *name);
+        dependencies
+    }
+
+    /// Renders the Cargo.toml of the generated project with any approved extra crates
+    pub fn cargo_manifest(
+        &self,
+        package_name: &str,
+        extra_dependencies: &[CrateDependency],
+    ) -> String {
+        let mut manifest: String = format!(
+            "[package]\nname = \"{}\"\nversion = \"0.1.0\"\nedition = \"2024\"\n\n[dependencies]\n",
+            package_name
+        );
+        for (name, version) in self.dependencies() {
+            manifest.push_str(&format!("{} = {}\n", name, version));
+        }
+        for dependency in extra_dependencies {
+            manifest.push_str(&format!(
+                "{} = {}\n",
+                dependency.name,
+                dependency.manifest_spec()
+            ));
+        }
+        manifest
+    }
+
+    /// Instructions the prompts need to write and read code for this framework
+    pub fn prompt_context(&self, extra_dependencies: &[CrateDependency]) -> String {
+        let libraries: Vec<&str> = self
+            .dependencies()
+            .into_iter()
+            .map(|(name, _)| name)
+            .chain(extra_dependencies.iter().map(|dependency| dependency.name.as_str()))
+            .collect();
+        let conventions: &str = match self {
+            TargetFramework::ActixWeb => {
+                "Routes are registered with App::new().route(\"/item/{id}\", web::get().to(handler)). \
+                 Path params use web::Path, bodies use web::Json and shared state uses web::Data. \
+                 The server binds 127.0.0.1 on the port in the PORT env var (default 8080) with #[actix_web::main]."
+            }
+            TargetFramework::Axum => {
+                "Routes are registered with Router::new().route(\"/item/{id}\", get(handler)). \
+                 Path params use axum::extract::Path, bodies use axum::Json and shared state uses axum::extract::State. \
+                 The server binds 127.0.0.1 on the port in the PORT env var (default 8080) with tokio::net::TcpListener and axum::serve under #[tokio::main]."
+            }
+            TargetFramework::Rocket => {
+                "Routes are declared with attributes such as #[get(\"/item/<id>\")] and mounted with .mount(\"/\", routes![...]). \
+                 Bodies use rocket::serde::json::Json and shared state uses &State<T> registered with .manage(). \
+                 The server must listen on the port in the PORT env var (default 8080), e.g. rocket::custom(rocket::Config::figment().merge((\"port\", port)))."
+            }
+        };
+
+        format!(
+            "TARGET_FRAMEWORK: {}\nAVAILABLE_LIBRARIES: {}\nFRAMEWORK_CONVENTIONS: {}\n",
+            self,
+            libraries.join(", "),
+            conventions
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tests_parses_framework_names() {
+        assert_eq!(
+            TargetFramework::from_str("Axum").unwrap(),
+            TargetFramework::Axum
+        );
+        assert_eq!(
+            TargetFramework::from_str("actix").unwrap(),
+            TargetFramework::ActixWeb
+        );
+        assert_eq!(TargetFramework::ActixWeb.to_string(), "actix-web");
+    }
+
+    #[test]
+    fn tests_renders_cargo_manifest() {
+        let extra_dependencies: Vec<CrateDependency> = vec![CrateDependency {
+            name: "argon2".to_string(),
+            version: "0.5.3".to_string(),
+            features: vec![],
+        }];
+        let manifest: String =
+            TargetFramework::Axum.cargo_manifest("web_template", &extra_dependencies);
+
+        assert!(manifest.starts_with("[package]\nname = \"web_template\""));
+        assert!(manifest.contains("axum = \"0.8.4\"\n"));
+        assert!(manifest.contains("tokio = { version"));
+        assert!(manifest.ends_with("argon2 = \"=0.5.3\"\n"));
+        assert!(!manifest.contains("actix-web"));
+
+        let prompt_context: String = TargetFramework::Axum.prompt_context(&extra_dependencies);
+        assert!(prompt_context.contains("tower-http, argon2\n"));
+    }
+}
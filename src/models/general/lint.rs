@@ -0,0 +1,126 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
+use strum_macros::{Display, EnumString};
+
+/// Severity of a compiler or clippy diagnostic, ordered from least to most severe
+#[derive(
+    Debug,
+    Serialize,
+    Deserialize,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Hash,
+    Default,
+    Display,
+    EnumString,
+)]
+#[serde(rename_all = "lowercase")]
+#[strum(serialize_all = "lowercase", ascii_case_insensitive)]
+pub enum LintLevel {
+    Help,
+    Note,
+    #[default]
+    Warning,
+    Error,
+}
+
+/// One diagnostic reported by `cargo clippy --message-format=json`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Lint {
+    pub level: LintLevel,
+    /// Lint name such as `clippy::needless_return`, None for plain compiler messages
+    pub code: Option<String>,
+    pub rendered: String,
+}
+
+impl Lint {
+    /// Reads a diagnostic from one line of cargo's JSON output, skipping summaries
+    fn from_json_line(line: &str) -> Option<Self> {
+        let value: Value = serde_json::from_str(line).ok()?;
+        if value["reason"] != "compiler-message" {
+            return None;
+        }
+
+        let message: &Value = &value["message"];
+        // "N warnings emitted" style summaries have no source location
+        if message["spans"].as_array().is_none_or(|spans| spans.is_empty()) {
+            return None;
+        }
+
+        let level: LintLevel = serde_json::from_value(message["level"].clone()).ok()?;
+        Some(Self {
+            level,
+            code: message["code"]["code"].as_str().map(str::to_string),
+            rendered: message["rendered"]
+                .as_str()
+                .or(message["message"].as_str())
+                .unwrap_or_default()
+                .to_string(),
+        })
+    }
+}
+
+/// Parses every diagnostic of a clippy run
+pub fn parse_clippy_output(stdout: &str) -> Vec<Lint> {
+    stdout.lines().filter_map(Lint::from_json_line).collect()
+}
+
+/// Lint totals of the final generated code, kept on the FactSheet
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Default)]
+pub struct LintCounts {
+    pub by_level: BTreeMap<LintLevel, usize>,
+    pub by_lint: BTreeMap<String, usize>,
+}
+
+impl LintCounts {
+    pub fn from_lints(lints: &[Lint]) -> Self {
+        let mut counts: LintCounts = LintCounts::default();
+        for lint in lints {
+            *counts.by_level.entry(lint.level).or_default() += 1;
+            if let Some(code) = &lint.code {
+                *counts.by_lint.entry(code.clone()).or_default() += 1;
+            }
+        }
+        counts
+    }
+
+    pub fn total(&self) -> usize {
+        self.by_level.values().sum()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tests_parses_clippy_json() {
+        let stdout: &str = r#"{"reason":"compiler-artifact","target":{"name":"serde"}}
+{"reason":"compiler-message","message":{"level":"warning","code":{"code":"clippy::needless_return"},"message":"unneeded `return` statement","rendered":"warning: unneeded `return` statement\n","spans":[{"file_name":"src/main.rs"}]}}
+{"reason":"compiler-message","message":{"level":"warning","code":null,"message":"unused variable: `x`","spans":[{"file_name":"src/main.rs"}]}}
+{"reason":"compiler-message","message":{"level":"warning","code":null,"message":"2 warnings emitted","rendered":"warning: 2 warnings emitted\n","spans":[]}}
+{"reason":"build-finished","success":true}"#;
+
+        let lints: Vec<Lint> = parse_clippy_output(stdout);
+        assert_eq!(lints.len(), 2);
+        assert_eq!(lints[0].code.as_deref(), Some("clippy::needless_return"));
+        assert_eq!(lints[1].rendered, "unused variable: `x`");
+
+        let counts: LintCounts = LintCounts::from_lints(&lints);
+        assert_eq!(counts.total(), 2);
+        assert_eq!(counts.by_level[&LintLevel::Warning], 2);
+        assert_eq!(counts.by_lint["clippy::needless_return"], 1);
+    }
+
+    #[test]
+    fn tests_orders_lint_levels() {
+        assert!(LintLevel::Error > LintLevel::Warning);
+        assert!(LintLevel::Note < LintLevel::Warning);
+        assert_eq!("WARNING".parse::<LintLevel>().unwrap(), LintLevel::Warning);
+    }
+}
@@ -23,7 +23,98 @@ pub struct APIChoice {
     pub message: APIMessage
 }
 
+/// Token counts the API reports for one completion
+#[derive(Debug, Deserialize, Clone, Copy, Default)]
+pub struct APIUsage {
+    pub prompt_tokens: u32,
+    pub completion_tokens: u32,
+    pub total_tokens: u32
+}
+
+/// Token spend of a group of LLM calls
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Default)]
+pub struct LlmUsage {
+    pub calls: usize,
+    pub prompt_tokens: u64,
+    pub completion_tokens: u64,
+    /// Cost in USD of the calls on models with a known price
+    pub cost_usd: f64,
+    /// Calls on models without a known price, left out of `cost_usd`
+    pub unpriced_calls: usize
+}
+
+impl LlmUsage {
+    /// Adds one completion, priced by the model it ran on
+    pub fn record(&mut self, model: &str, usage: Option<APIUsage>) {
+        self.calls += 1;
+        let Some(usage) = usage else {
+            return;
+        };
+        self.prompt_tokens += u64::from(usage.prompt_tokens);
+        self.completion_tokens += u64::from(usage.completion_tokens);
+        match model_price(model) {
+            Some((prompt_price, completion_price)) => {
+                self.cost_usd += (f64::from(usage.prompt_tokens) * prompt_price
+                    + f64::from(usage.completion_tokens) * completion_price)
+                    / 1_000_000.0;
+            }
+            None => self.unpriced_calls += 1,
+        }
+    }
+
+    pub fn add(&mut self, other: &LlmUsage) {
+        self.calls += other.calls;
+        self.prompt_tokens += other.prompt_tokens;
+        self.completion_tokens += other.completion_tokens;
+        self.cost_usd += other.cost_usd;
+        self.unpriced_calls += other.unpriced_calls;
+    }
+
+    pub fn total_tokens(&self) -> u64 {
+        self.prompt_tokens + self.completion_tokens
+    }
+}
+
+/// USD per million prompt and completion tokens of the models agents are usually run on
+pub fn model_price(model: &str) -> Option<(f64, f64)> {
+    match model {
+        "o4-mini" | "o3-mini" => Some((1.10, 4.40)),
+        "o3" => Some((2.00, 8.00)),
+        "gpt-4o" => Some((2.50, 10.00)),
+        "gpt-4o-mini" => Some((0.15, 0.60)),
+        "gpt-4.1" => Some((2.00, 8.00)),
+        "gpt-4.1-mini" => Some((0.40, 1.60)),
+        "gpt-4.1-nano" => Some((0.10, 0.40)),
+        _ => None
+    }
+}
+
 #[derive(Debug, Deserialize)]
 pub struct APIResponse {
-    pub choices: Vec<APIChoice>
+    pub choices: Vec<APIChoice>,
+    #[serde(default)]
+    pub usage: Option<APIUsage>
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tests_prices_usage_by_model() {
+        let usage: APIUsage = APIUsage {
+            prompt_tokens: 1_000_000,
+            completion_tokens: 500_000,
+            total_tokens: 1_500_000
+        };
+        let mut tally: LlmUsage = LlmUsage::default();
+        tally.record("o4-mini", Some(usage));
+        tally.record("my-local-model", Some(usage));
+        tally.record("o4-mini", None);
+
+        assert_eq!(tally.calls, 3);
+        assert_eq!(tally.total_tokens(), 3_000_000);
+        assert!((tally.cost_usd - 3.30).abs() < 1e-9);
+        assert_eq!(tally.unpriced_calls, 1);
+    }
 }
\ No newline at end of file
@@ -1 +1,10 @@
-pub mod llm;
\ No newline at end of file
+pub mod api_schema;
+pub mod clarification;
+pub mod dependencies;
+pub mod external_api;
+pub mod framework;
+pub mod lint;
+pub mod llm;
+pub mod project_spec;
+pub mod prompt_guard;
+pub mod settings;
\ No newline at end of file
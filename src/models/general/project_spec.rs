@@ -0,0 +1,216 @@
+use crate::models::general::api_schema::deserialize_lenient_bool;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::fmt;
+
+/// Type of a field on a domain entity, written as its type name such as `list<string>`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FieldType {
+    String,
+    Integer,
+    Float,
+    Bool,
+    DateTime,
+    Id,
+    List(Box<FieldType>),
+    Other(String),
+}
+
+impl FieldType {
+    /// Maps the type names the LLM tends to print onto field types
+    fn from_type_name(type_name: &str) -> Self {
+        let normalized: String = type_name.trim().to_lowercase();
+        match normalized.as_str() {
+            "string" | "str" | "text" | "email" | "url" => FieldType::String,
+            "integer" | "int" | "number" | "u32" | "u64" | "i32" | "i64" | "usize" => {
+                FieldType::Integer
+            }
+            "float" | "decimal" | "double" | "f32" | "f64" | "money" => FieldType::Float,
+            "bool" | "boolean" => FieldType::Bool,
+            "datetime" | "date" | "timestamp" | "time" => FieldType::DateTime,
+            "id" | "uuid" => FieldType::Id,
+            _ => match normalized
+                .strip_prefix("list<")
+                .or_else(|| normalized.strip_prefix("vec<"))
+                .and_then(|rest| rest.strip_suffix('>'))
+            {
+                Some(inner) => FieldType::List(Box::new(FieldType::from_type_name(inner))),
+                None => FieldType::Other(type_name.trim().to_string()),
+            },
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for FieldType {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let type_name: String = String::deserialize(deserializer)?;
+        Ok(FieldType::from_type_name(&type_name))
+    }
+}
+
+impl Serialize for FieldType {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl fmt::Display for FieldType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FieldType::String => write!(f, "string"),
+            FieldType::Integer => write!(f, "integer"),
+            FieldType::Float => write!(f, "float"),
+            FieldType::Bool => write!(f, "bool"),
+            FieldType::DateTime => write!(f, "datetime"),
+            FieldType::Id => write!(f, "id"),
+            FieldType::List(item) => write!(f, "list<{}>", item),
+            FieldType::Other(type_name) => write!(f, "{}", type_name),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct EntityField {
+    pub name: String,
+    #[serde(alias = "type")]
+    pub field_type: FieldType,
+    #[serde(default, deserialize_with = "deserialize_lenient_bool")]
+    pub is_optional: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum RelationKind {
+    #[serde(alias = "one-to-one", alias = "has_one")]
+    OneToOne,
+    #[serde(alias = "one-to-many", alias = "has_many")]
+    OneToMany,
+    #[serde(alias = "many-to-one", alias = "belongs_to")]
+    ManyToOne,
+    #[serde(alias = "many-to-many")]
+    ManyToMany,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct EntityRelation {
+    #[serde(alias = "entity")]
+    pub target: String,
+    pub kind: RelationKind,
+}
+
+/// Domain entity the generated backend has to store or expose
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct Entity {
+    pub name: String,
+    #[serde(default)]
+    pub fields: Vec<EntityField>,
+    #[serde(default)]
+    pub relations: Vec<EntityRelation>,
+}
+
+/// Cross-cutting features the generated backend has to provide
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Default)]
+pub struct ProjectFeatures {
+    #[serde(default, deserialize_with = "deserialize_lenient_bool")]
+    pub pagination: bool,
+    #[serde(default, deserialize_with = "deserialize_lenient_bool")]
+    pub search: bool,
+    #[serde(default, deserialize_with = "deserialize_lenient_bool")]
+    pub file_upload: bool,
+    #[serde(default, deserialize_with = "deserialize_lenient_bool")]
+    pub realtime: bool,
+    #[serde(default, deserialize_with = "deserialize_lenient_bool")]
+    pub admin_roles: bool,
+    #[serde(default, deserialize_with = "deserialize_lenient_bool")]
+    pub scheduled_jobs: bool,
+}
+
+impl ProjectFeatures {
+    /// Names of the features that are switched on
+    pub fn enabled(&self) -> Vec<&'static str> {
+        [
+            (self.pagination, "pagination"),
+            (self.search, "search"),
+            (self.file_upload, "file_upload"),
+            (self.realtime, "realtime"),
+            (self.admin_roles, "admin_roles"),
+            (self.scheduled_jobs, "scheduled_jobs"),
+        ]
+        .into_iter()
+        .filter_map(|(is_enabled, name)| is_enabled.then_some(name))
+        .collect()
+    }
+}
+
+impl fmt::Display for Entity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let fields: Vec<String> = self
+            .fields
+            .iter()
+            .map(|field| {
+                let optional: &str = if field.is_optional { "?" } else { "" };
+                format!("{}{}: {}", field.name, optional, field.field_type)
+            })
+            .collect();
+        write!(f, "{} {{ {} }}", self.name, fields.join(", "))?;
+
+        for relation in &self.relations {
+            write!(f, " {:?} {}", relation.kind, relation.target)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tests_decodes_entities_leniently() {
+        let entity: Entity = serde_json::from_str(
+            r#"{
+              "name": "Workout",
+              "fields": [
+                { "name": "id", "type": "uuid" },
+                { "name": "duration", "type": "INT" },
+                { "name": "tags", "type": "Vec<String>", "is_optional": "true" }
+              ],
+              "relations": [{ "entity": "User", "kind": "many-to-one" }]
+            }"#,
+        )
+        .unwrap();
+
+        assert_eq!(entity.fields[0].field_type, FieldType::Id);
+        assert_eq!(entity.fields[1].field_type, FieldType::Integer);
+        assert!(entity.fields[2].is_optional);
+        assert_eq!(entity.relations[0].kind, RelationKind::ManyToOne);
+        assert_eq!(
+            entity.to_string(),
+            "Workout { id: id, duration: integer, tags?: list<string> } ManyToOne User"
+        );
+    }
+
+    #[test]
+    fn tests_round_trips_field_types() {
+        let field_types: Vec<FieldType> = vec![
+            FieldType::String,
+            FieldType::DateTime,
+            FieldType::List(Box::new(FieldType::String)),
+            FieldType::List(Box::new(FieldType::List(Box::new(FieldType::DateTime)))),
+            FieldType::Other("GeoPoint".to_string()),
+        ];
+        let encoded: String = serde_json::to_string(&field_types).unwrap();
+        assert_eq!(
+            encoded,
+            r#"["string","datetime","list<string>","list<list<datetime>>","GeoPoint"]"#
+        );
+        let decoded: Vec<FieldType> = serde_json::from_str(&encoded).unwrap();
+        assert_eq!(decoded, field_types);
+    }
+
+    #[test]
+    fn tests_lists_enabled_features() {
+        let features: ProjectFeatures =
+            serde_json::from_str(r#"{ "pagination": "true", "realtime": true }"#).unwrap();
+        assert_eq!(features.enabled(), vec!["pagination", "realtime"]);
+    }
+}
@@ -0,0 +1,78 @@
+use crate::models::general::lint::LintLevel;
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::str::FromStr;
+use strum_macros::{Display, EnumString};
+
+/// How generated code gets approved before it is built and run
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Default, Display, EnumString)]
+#[serde(rename_all = "lowercase")]
+#[strum(serialize_all = "lowercase")]
+pub enum ApprovalPolicy {
+    /// Diff review with manual edits and instructions
+    #[default]
+    Review,
+    /// Plain yes/no confirmation
+    Confirm,
+    /// Runs the code without asking, for trusted sandboxes only
+    Auto,
+}
+
+/// Tunable behaviour of the backend developer
+#[derive(Debug, Clone, PartialEq)]
+pub struct BackendSettings {
+    /// Number of first versions generated and compared before improving one, 1 disables sampling
+    pub candidate_count: usize,
+    /// Clippy lints at or above this level trigger an improvement pass
+    pub lint_severity: LintLevel,
+    /// Improvement passes spent on lints, separate from the build bug budget
+    pub lint_budget: usize,
+    /// Failed builds in a row before rolling back or giving up
+    pub max_bug_fixes: usize,
+    pub approval: ApprovalPolicy,
+    /// Directories with extra code templates on top of the built-in ones
+    pub template_dirs: Vec<PathBuf>,
+}
+
+impl Default for BackendSettings {
+    fn default() -> Self {
+        Self {
+            candidate_count: 1,
+            lint_severity: LintLevel::Warning,
+            lint_budget: 2,
+            max_bug_fixes: 2,
+            approval: ApprovalPolicy::Review,
+            template_dirs: vec![],
+        }
+    }
+}
+
+impl BackendSettings {
+    /// Reads settings from env vars, keeping defaults for anything missing or invalid
+    pub fn from_env() -> Self {
+        let mut settings: BackendSettings = BackendSettings::default();
+        if let Some(candidate_count) = env_number::<usize>("CANDIDATE_COUNT") {
+            settings.candidate_count = candidate_count.max(1);
+        }
+        if let Some(lint_severity) = std::env::var("LINT_SEVERITY")
+            .ok()
+            .and_then(|level| LintLevel::from_str(level.trim()).ok())
+        {
+            settings.lint_severity = lint_severity;
+        }
+        if let Some(lint_budget) = env_number::<usize>("LINT_BUDGET") {
+            settings.lint_budget = lint_budget;
+        }
+        if let Some(template_dirs) = std::env::var_os("TEMPLATE_DIR") {
+            settings.template_dirs = std::env::split_paths(&template_dirs).collect();
+        }
+        settings
+    }
+}
+
+/// Parses a numeric env var
+pub fn env_number<T: FromStr>(name: &str) -> Option<T> {
+    std::env::var(name)
+        .ok()
+        .and_then(|value| value.trim().parse().ok())
+}
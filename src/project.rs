@@ -0,0 +1,187 @@
+use crate::apis::call_request::ProviderConfig;
+use crate::helpers::general::run_dir;
+use crate::models::agents::agent_traits::{FactSheet, SpecialFunctions};
+use crate::models::agents_manager::managing_agent::ManagingAgent;
+use crate::models::agents_manager::pipeline::{
+    AgentRegistry, PipelineDefinition, PipelineStage, StageDefinition,
+};
+use crate::models::agents_manager::report::ProjectReport;
+use crate::models::general::clarification::Clarification;
+use crate::models::general::dependencies::DependencyAllowlist;
+use crate::models::general::framework::TargetFramework;
+use crate::models::general::settings::BackendSettings;
+use std::path::PathBuf;
+
+/// Everything a finished project run produced
+#[derive(Debug, Clone, PartialEq)]
+pub struct ProjectResult {
+    pub factsheet: FactSheet,
+    /// Files written by the agents, such as the generated `main.rs` and the API schema
+    pub artifacts: Vec<PathBuf>,
+    pub report: ProjectReport,
+}
+
+/// Configures and runs a project, starting from the same env vars and files as the CLI
+///
+/// ```no_run
+/// use rust_backend_gen_agents::{ProjectBuilder, ProviderConfig, TargetFramework};
+///
+/// # async fn run() -> Result<(), Box<dyn std::error::Error>> {
+/// let result = ProjectBuilder::new("build a website that tracks my reading list")
+///     .provider(ProviderConfig::open_ai("sk-..."))
+///     .framework(TargetFramework::Axum)
+///     .template_dir("./my_templates")
+///     .run()
+///     .await?;
+/// println!("{}", result.factsheet.project_description);
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct ProjectBuilder {
+    request: String,
+    answers: Option<Vec<Clarification>>,
+    provider: Option<ProviderConfig>,
+    target_framework: TargetFramework,
+    backend_settings: BackendSettings,
+    pipeline: Option<PipelineDefinition>,
+    allowlist: Option<DependencyAllowlist>,
+    custom_agents: AgentRegistry,
+}
+
+impl ProjectBuilder {
+    pub fn new(request: &str) -> Self {
+        Self {
+            request: request.to_string(),
+            answers: None,
+            provider: None,
+            target_framework: TargetFramework::from_env(),
+            backend_settings: BackendSettings::from_env(),
+            pipeline: None,
+            allowlist: None,
+            custom_agents: AgentRegistry::default(),
+        }
+    }
+
+    /// Answers to use instead of asking clarifying questions on the console
+    pub fn answers(mut self, answers: Vec<Clarification>) -> Self {
+        self.answers = Some(answers);
+        self
+    }
+
+    /// LLM provider for every agent, OPEN_AI_KEY and OPEN_AI_ORG when not set
+    pub fn provider(mut self, provider: ProviderConfig) -> Self {
+        self.provider = Some(provider);
+        self
+    }
+
+    pub fn framework(mut self, target_framework: TargetFramework) -> Self {
+        self.target_framework = target_framework;
+        self
+    }
+
+    /// Adds a directory of code templates on top of the built-in ones
+    pub fn template_dir(mut self, template_dir: impl Into<PathBuf>) -> Self {
+        self.backend_settings.template_dirs.push(template_dir.into());
+        self
+    }
+
+    /// Settings the backend developer starts from before its stage settings apply
+    pub fn backend_settings(mut self, backend_settings: BackendSettings) -> Self {
+        self.backend_settings = backend_settings;
+        self
+    }
+
+    /// Pipeline to run instead of PIPELINE_FILE or `pipeline.toml`
+    pub fn pipeline(mut self, pipeline: PipelineDefinition) -> Self {
+        self.pipeline = Some(pipeline);
+        self
+    }
+
+    /// Crates the backend developer may add instead of DEPENDENCY_ALLOWLIST or `dependency_allowlist.toml`
+    pub fn allowlist(mut self, allowlist: DependencyAllowlist) -> Self {
+        self.allowlist = Some(allowlist);
+        self
+    }
+
+    /// Registers an agent that pipeline stages can use by name, replacing a built-in one with the same name
+    pub fn agent<F>(mut self, name: &str, constructor: F) -> Self
+    where
+        F: Fn(&StageDefinition) -> Box<dyn SpecialFunctions> + Send + Sync + 'static,
+    {
+        self.custom_agents.register(name, constructor);
+        self
+    }
+
+    /// Checks the pipeline and allowlist, creates its agents and turns the request into a goal
+    pub async fn build(self) -> Result<ManagingAgent, Box<dyn std::error::Error>> {
+        let pipeline: PipelineDefinition = match self.pipeline {
+            Some(pipeline) => pipeline,
+            None => PipelineDefinition::from_env()?,
+        };
+        let allowlist: DependencyAllowlist = match self.allowlist {
+            Some(allowlist) => allowlist,
+            None => DependencyAllowlist::from_env()?,
+        };
+        // The report and the code history of this run share one directory
+        let run_dir: PathBuf = run_dir();
+        let mut registry: AgentRegistry =
+            AgentRegistry::with_builtin_agents(self.backend_settings, allowlist, run_dir.clone());
+        registry.extend(self.custom_agents);
+
+        // Fail on a broken pipeline before asking the user anything
+        let agents: Vec<PipelineStage> = registry.create_stages(&pipeline)?;
+        ManagingAgent::new(
+            self.request,
+            self.answers,
+            agents,
+            self.target_framework,
+            run_dir,
+            self.provider,
+        )
+        .await
+    }
+
+    /// Builds the project and runs every pipeline stage
+    pub async fn run(self) -> Result<ProjectResult, Box<dyn std::error::Error>> {
+        let mut managing_agent: ManagingAgent = self.build().await?;
+        let report: ProjectReport = managing_agent.execute_project().await;
+
+        Ok(ProjectResult {
+            factsheet: managing_agent.into_factsheet(),
+            artifacts: report.artifacts(),
+            report,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::agents::agent_architect::AgentSolutionArchitect;
+
+    #[tokio::test]
+    async fn tests_rejects_unknown_agents_before_calling_the_model() {
+        let pipeline: PipelineDefinition = PipelineDefinition::parse(
+            r#"
+            [[stages]]
+            name = "frontend"
+            agent = "frontend_developer"
+            "#,
+        )
+        .unwrap();
+
+        let error: String = ProjectBuilder::new("build a todo api")
+            .pipeline(pipeline)
+            .agent("docs_writer", |_stage: &StageDefinition| {
+                Box::new(AgentSolutionArchitect::new()) as Box<dyn SpecialFunctions>
+            })
+            .build()
+            .await
+            .unwrap_err()
+            .to_string();
+
+        assert!(error.contains("unknown agent 'frontend_developer'"));
+        assert!(error.contains("backend_developer, docs_writer, solution_architect"));
+    }
+}
\ No newline at end of file
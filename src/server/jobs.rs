@@ -0,0 +1,486 @@
+use crate::models::agents_manager::pipeline::PipelineDefinition;
+use crate::models::general::clarification::ProjectSpecFile;
+use crate::models::general::settings::ApprovalPolicy;
+use crate::server::ServeSettings;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::fs::{self, File};
+use std::io::{Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use strum_macros::Display;
+use tokio::process::{Child, Command};
+use tokio::sync::{Notify, mpsc};
+
+/// Files and directories of the server's working directory each job starts with
+const WORKSPACE_INPUTS: [&str; 3] = [
+    "template",
+    "schemas",
+    "dependency_allowlist.toml",
+];
+
+/// JSON-lines agent log of a job, relative to its workspace
+pub const JOB_LOG_FILE: &str = "runs/agent_log.jsonl";
+
+/// Stdout and stderr of a job, relative to its workspace
+pub const JOB_CONSOLE_FILE: &str = "console.log";
+
+#[derive(Debug, Serialize, Clone, Copy, PartialEq, Eq, Display)]
+#[serde(rename_all = "lowercase")]
+#[strum(serialize_all = "lowercase")]
+pub enum JobStatus {
+    Queued,
+    Running,
+    Succeeded,
+    Failed,
+    Cancelled,
+}
+
+impl JobStatus {
+    pub fn is_finished(self) -> bool {
+        matches!(
+            self,
+            JobStatus::Succeeded | JobStatus::Failed | JobStatus::Cancelled
+        )
+    }
+}
+
+/// One submitted project run
+#[derive(Debug, Serialize, Clone)]
+pub struct Job {
+    pub id: String,
+    pub request: String,
+    pub status: JobStatus,
+    /// Unix timestamps in seconds
+    pub submitted_at: u64,
+    pub started_at: Option<u64>,
+    pub finished_at: Option<u64>,
+    /// Port the generated server is tested on, owned by the worker running the job
+    pub port: Option<u16>,
+    pub exit_code: Option<i32>,
+    /// Why the run could not be started
+    pub error: Option<String>,
+    /// Approval policy the run's backend stages use, None when no stage asks
+    pub approval: Option<ApprovalPolicy>,
+    #[serde(skip)]
+    pub workspace: PathBuf,
+    #[serde(skip)]
+    spec: ProjectSpecFile,
+    #[serde(skip)]
+    cancel: Arc<Notify>,
+}
+
+/// Returned by `JobQueue::submit` when every queue slot is taken
+#[derive(Debug, Clone, PartialEq)]
+pub struct QueueFull;
+
+/// Bounded queue of project runs, executed by a fixed number of workers
+#[derive(Debug)]
+pub struct JobQueue {
+    settings: ServeSettings,
+    /// Pipeline every job runs, with approvals switched to auto as nobody is at the console
+    pipeline: PipelineDefinition,
+    /// Approval policy every job runs with
+    approval: Option<ApprovalPolicy>,
+    jobs: Mutex<BTreeMap<String, Job>>,
+    sender: mpsc::Sender<String>,
+    next_id: AtomicU64,
+}
+
+impl JobQueue {
+    /// Creates the queue and starts `max_jobs` workers, worker N testing on `base_port + N`
+    ///
+    /// Nobody answers approval prompts in a job, so this errors unless the operator allowed
+    /// auto approval in the settings.
+    pub fn start(settings: ServeSettings, pipeline: PipelineDefinition) -> Result<Arc<Self>, String> {
+        if !settings.allow_auto_approval {
+            return Err(
+                "Nobody answers approval prompts in serve mode, so runs would build and run model-written code without review. Pass --allow-auto-approval to accept that"
+                    .to_string(),
+            );
+        }
+
+        let (sender, receiver) = mpsc::channel::<String>(settings.queue_size);
+        let receiver: Arc<tokio::sync::Mutex<mpsc::Receiver<String>>> =
+            Arc::new(tokio::sync::Mutex::new(receiver));
+
+        let mut pipeline: PipelineDefinition = pipeline;
+        pipeline.set_approval(ApprovalPolicy::Auto);
+        let approval: Option<ApprovalPolicy> =
+            pipeline.stages.iter().find_map(|stage| stage.approval);
+
+        let queue: Arc<JobQueue> = Arc::new(JobQueue {
+            pipeline,
+            approval,
+            jobs: Mutex::new(BTreeMap::new()),
+            sender,
+            next_id: AtomicU64::new(1),
+            settings,
+        });
+
+        for worker in 0..queue.settings.max_jobs {
+            let port: u16 = queue.settings.base_port + worker as u16;
+            let queue: Arc<JobQueue> = queue.clone();
+            let receiver = receiver.clone();
+            tokio::spawn(async move {
+                loop {
+                    let next: Option<String> = receiver.lock().await.recv().await;
+                    match next {
+                        Some(id) => queue.run_job(&id, port).await,
+                        None => break,
+                    }
+                }
+            });
+        }
+        Ok(queue)
+    }
+
+    /// Queues a run, failing right away when the queue is full
+    pub fn submit(&self, spec: ProjectSpecFile) -> Result<Job, QueueFull> {
+        let id: String = format!(
+            "{}-{}",
+            unix_now(),
+            self.next_id.fetch_add(1, Ordering::Relaxed)
+        );
+        let job: Job = Job {
+            id: id.clone(),
+            request: spec.request.clone(),
+            status: JobStatus::Queued,
+            submitted_at: unix_now(),
+            started_at: None,
+            finished_at: None,
+            port: None,
+            exit_code: None,
+            error: None,
+            approval: self.approval,
+            workspace: self.settings.jobs_dir.join(&id),
+            spec,
+            cancel: Arc::new(Notify::new()),
+        };
+
+        // Insert first so a worker picking the id up right away finds the job
+        self.jobs.lock().unwrap().insert(id.clone(), job.clone());
+        if self.sender.try_send(id.clone()).is_err() {
+            self.jobs.lock().unwrap().remove(&id);
+            return Err(QueueFull);
+        }
+        Ok(job)
+    }
+
+    pub fn list(&self) -> Vec<Job> {
+        self.jobs.lock().unwrap().values().cloned().collect()
+    }
+
+    pub fn get(&self, id: &str) -> Option<Job> {
+        self.jobs.lock().unwrap().get(id).cloned()
+    }
+
+    /// Cancels a queued or running job, returning it as it was before, or None when unknown
+    pub fn cancel(&self, id: &str) -> Option<Job> {
+        let mut jobs = self.jobs.lock().unwrap();
+        let job: &mut Job = jobs.get_mut(id)?;
+        let before: Job = job.clone();
+        match job.status {
+            JobStatus::Queued => {
+                job.status = JobStatus::Cancelled;
+                job.finished_at = Some(unix_now());
+            }
+            // The worker kills the run and records the status
+            JobStatus::Running => job.cancel.notify_one(),
+            _ => {}
+        }
+        Some(before)
+    }
+
+    async fn run_job(&self, id: &str, port: u16) {
+        let job: Job = {
+            let mut jobs = self.jobs.lock().unwrap();
+            let Some(job) = jobs.get_mut(id) else {
+                return;
+            };
+            // Cancelled while waiting in the queue
+            if job.status != JobStatus::Queued {
+                return;
+            }
+            job.status = JobStatus::Running;
+            job.started_at = Some(unix_now());
+            job.port = Some(port);
+            job.clone()
+        };
+
+        let (status, exit_code, error) = match self.run_process(&job, port).await {
+            Ok(Some(exit_code)) if exit_code == 0 => (JobStatus::Succeeded, Some(exit_code), None),
+            Ok(Some(exit_code)) => (JobStatus::Failed, Some(exit_code), None),
+            Ok(None) => (JobStatus::Cancelled, None, None),
+            Err(e) => (JobStatus::Failed, None, Some(e.to_string())),
+        };
+
+        if let Some(job) = self.jobs.lock().unwrap().get_mut(id) {
+            job.status = status;
+            job.exit_code = exit_code;
+            job.error = error;
+            job.finished_at = Some(unix_now());
+        }
+    }
+
+    /// Runs the CLI on the job's workspace, returning its exit code or None when cancelled
+    async fn run_process(&self, job: &Job, port: u16) -> std::io::Result<Option<i32>> {
+        prepare_workspace(&self.settings.source_dir, &job.workspace)?;
+        fs::write(
+            job.workspace.join("spec.toml"),
+            toml::to_string(&job.spec).map_err(std::io::Error::other)?,
+        )?;
+        fs::write(
+            job.workspace.join("pipeline.toml"),
+            toml::to_string(&self.pipeline).map_err(std::io::Error::other)?,
+        )?;
+
+        let console: File = File::create(job.workspace.join(JOB_CONSOLE_FILE))?;
+        let mut command: Command = Command::new(&self.settings.program);
+        command
+            .current_dir(&job.workspace)
+            .args([
+                "--spec",
+                "spec.toml",
+                "--log-format",
+                "json",
+                "--log-file",
+                JOB_LOG_FILE,
+            ])
+            .env("WEB_SERVER_PORT", port.to_string())
+            .env("PIPELINE_FILE", "pipeline.toml")
+            // Keeps candidate builds and review files apart from other jobs
+            .env("TMPDIR", job.workspace.join("tmp"))
+            .stdin(Stdio::null())
+            .stdout(console.try_clone()?)
+            .stderr(console)
+            .kill_on_drop(true);
+        // Own process group, so the generated server and builds the CLI starts go with it
+        #[cfg(unix)]
+        command.process_group(0);
+        let mut child: Child = command.spawn()?;
+        let pid: Option<u32> = child.id();
+
+        let exit_code: Option<i32> = tokio::select! {
+            status = child.wait() => Some(status?.code().unwrap_or(-1)),
+            _ = job.cancel.notified() => None,
+        };
+        // Also clears servers left behind by a finished run, they would keep the worker's port
+        kill_process_group(&mut child, pid).await?;
+        Ok(exit_code)
+    }
+}
+
+/// Kills the CLI and every process it started, such as the generated server run by `cargo run`
+async fn kill_process_group(child: &mut Child, pid: Option<u32>) -> std::io::Result<()> {
+    #[cfg(unix)]
+    if let Some(pid) = pid {
+        // The CLI leads its group, a negative pid signals every process in it
+        // SAFETY: kill only sends a signal, it does not touch memory of this process
+        let result: i32 = unsafe { libc::kill(-(pid as libc::pid_t), libc::SIGKILL) };
+        let error: std::io::Error = std::io::Error::last_os_error();
+        // ESRCH: every process of the group already exited
+        if result != 0 && error.raw_os_error() != Some(libc::ESRCH) {
+            return Err(error);
+        }
+    }
+    #[cfg(not(unix))]
+    let _ = pid;
+
+    if child.try_wait()?.is_none() {
+        child.kill().await?;
+    }
+    Ok(())
+}
+
+/// Copies the templates, web server and schemas a run works on into a fresh workspace
+fn prepare_workspace(source_dir: &Path, workspace: &Path) -> std::io::Result<()> {
+    fs::create_dir_all(workspace.join("runs"))?;
+    fs::create_dir_all(workspace.join("tmp"))?;
+    for input in WORKSPACE_INPUTS {
+        let source: PathBuf = source_dir.join(input);
+        if source.is_dir() {
+            copy_dir(&source, &workspace.join(input))?;
+        } else if source.is_file() {
+            fs::copy(&source, workspace.join(input))?;
+        }
+    }
+    Ok(())
+}
+
+/// Copies a directory tree, leaving out cargo `target` directories
+fn copy_dir(source: &Path, destination: &Path) -> std::io::Result<()> {
+    fs::create_dir_all(destination)?;
+    for entry in fs::read_dir(source)? {
+        let entry = entry?;
+        let path: PathBuf = entry.path();
+        if path.is_dir() {
+            if entry.file_name() != "target" {
+                copy_dir(&path, &destination.join(entry.file_name()))?;
+            }
+        } else {
+            fs::copy(&path, destination.join(entry.file_name()))?;
+        }
+    }
+    Ok(())
+}
+
+/// Files a job produced, relative to its workspace, leaving out the copied templates and build output
+pub fn list_artifacts(workspace: &Path) -> Vec<PathBuf> {
+    let mut artifacts: Vec<PathBuf> = vec![];
+    let mut pending: Vec<PathBuf> = vec![workspace.to_path_buf()];
+    while let Some(dir) = pending.pop() {
+        let Ok(entries) = fs::read_dir(&dir) else {
+            continue;
+        };
+        for entry in entries.flatten() {
+            let path: PathBuf = entry.path();
+            let relative: PathBuf = path.strip_prefix(workspace).unwrap_or(&path).to_path_buf();
+            if path.is_dir() {
+                if !matches!(relative.to_str(), Some("template" | "tmp"))
+                    && entry.file_name() != "target"
+                {
+                    pending.push(path);
+                }
+            } else {
+                artifacts.push(relative);
+            }
+        }
+    }
+    artifacts.sort();
+    artifacts
+}
+
+/// Resolves a requested artifact inside the workspace, None when the path could leave it
+pub fn artifact_path(workspace: &Path, requested: &str) -> Option<PathBuf> {
+    let relative: &Path = Path::new(requested);
+    let inside: bool = relative
+        .components()
+        .all(|component| matches!(component, std::path::Component::Normal(_)));
+    (inside && !requested.is_empty()).then(|| workspace.join(relative))
+}
+
+/// Reads the lines appended to a growing file since the last call
+#[derive(Debug)]
+pub struct LogTail {
+    path: PathBuf,
+    offset: u64,
+    /// Start of a line whose newline has not been written yet
+    partial: Vec<u8>,
+}
+
+impl LogTail {
+    pub fn new(path: PathBuf) -> Self {
+        Self {
+            path,
+            offset: 0,
+            partial: vec![],
+        }
+    }
+
+    /// Complete lines written since the last call, none while the file does not exist
+    pub fn read_lines(&mut self) -> std::io::Result<Vec<String>> {
+        let mut file: File = match File::open(&self.path) {
+            Ok(file) => file,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(vec![]),
+            Err(e) => return Err(e),
+        };
+        file.seek(SeekFrom::Start(self.offset))?;
+        let read: usize = file.read_to_end(&mut self.partial)?;
+        self.offset += read as u64;
+
+        let Some(last_newline) = self.partial.iter().rposition(|byte| *byte == b'\n') else {
+            return Ok(vec![]);
+        };
+        let rest: Vec<u8> = self.partial.split_off(last_newline + 1);
+        let complete: Vec<u8> = std::mem::replace(&mut self.partial, rest);
+        Ok(String::from_utf8_lossy(&complete)
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .map(str::to_string)
+            .collect())
+    }
+}
+
+fn unix_now() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_secs())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    #[test]
+    fn tests_tails_complete_lines() {
+        let path: PathBuf = std::env::temp_dir().join("rust-backend-gen-agents-log-tail.jsonl");
+        let _ = fs::remove_file(&path);
+        let mut tail: LogTail = LogTail::new(path.clone());
+        assert!(tail.read_lines().unwrap().is_empty());
+
+        let mut file: File = File::create(&path).unwrap();
+        write!(file, "{{\"a\":1}}\n{{\"b\":").unwrap();
+        assert_eq!(tail.read_lines().unwrap(), vec!["{\"a\":1}"]);
+
+        writeln!(file, "2}}").unwrap();
+        assert_eq!(tail.read_lines().unwrap(), vec!["{\"b\":2}"]);
+        assert!(tail.read_lines().unwrap().is_empty());
+    }
+
+    #[test]
+    fn tests_keeps_artifacts_inside_workspace() {
+        let workspace: &Path = Path::new("/srv/jobs/1");
+        assert_eq!(
+            artifact_path(workspace, "runs/factsheet.json"),
+            Some(workspace.join("runs/factsheet.json"))
+        );
+        assert_eq!(artifact_path(workspace, "../2/console.log"), None);
+        assert_eq!(artifact_path(workspace, "runs/../../2"), None);
+        assert_eq!(artifact_path(workspace, "/etc/passwd"), None);
+        assert_eq!(artifact_path(workspace, ""), None);
+    }
+
+    #[cfg(target_os = "linux")]
+    #[tokio::test]
+    async fn tests_kills_processes_started_by_the_job() {
+        let dir: PathBuf = std::env::temp_dir().join("rust-backend-gen-agents-process-group");
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+
+        // Stands in for the CLI leaving `cargo run` of the generated server behind
+        let mut command: Command = Command::new("sh");
+        command
+            .current_dir(&dir)
+            .args(["-c", "sleep 30 & echo $! > server.pid; wait"])
+            .process_group(0);
+        let mut child: Child = command.spawn().unwrap();
+        let pid: Option<u32> = child.id();
+
+        let pid_file: PathBuf = dir.join("server.pid");
+        while !fs::read_to_string(&pid_file).is_ok_and(|pid| pid.ends_with('\n')) {
+            tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+        }
+        let server_pid: String = fs::read_to_string(&pid_file).unwrap().trim().to_string();
+
+        kill_process_group(&mut child, pid).await.unwrap();
+
+        let is_running = || {
+            fs::read_to_string(format!("/proc/{}/stat", server_pid))
+                .is_ok_and(|stat| !stat.contains(") Z "))
+        };
+        for _ in 0..100 {
+            if !is_running() {
+                break;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+        }
+        assert!(!is_running());
+        assert!(child.try_wait().unwrap().is_some());
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,433 @@
+pub mod jobs;
+
+use crate::helpers::general::FACTSHEET_PATH;
+use crate::models::agents_manager::pipeline::PipelineDefinition;
+use crate::models::general::clarification::ProjectSpecFile;
+use crate::server::jobs::{JOB_LOG_FILE, Job, JobQueue, LogTail, artifact_path, list_artifacts};
+use axum::extract::{Path as UrlPath, State};
+use axum::http::{StatusCode, header};
+use axum::response::IntoResponse;
+use axum::response::sse::{Event, KeepAlive, Sse};
+use axum::routing::{get, post};
+use axum::{Json, Router};
+use futures_util::stream::{self, Stream};
+use serde::Serialize;
+use serde_json::Value;
+use std::convert::Infallible;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Address `serve` listens on unless `--addr` is given
+pub const DEFAULT_SERVE_ADDR: &str = "127.0.0.1:7878";
+
+/// How often the event stream checks a run's log for new lines
+const EVENT_POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+type ApiError = (StatusCode, String);
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ServeSettings {
+    pub addr: String,
+    /// Runs executed at the same time
+    pub max_jobs: usize,
+    /// Runs waiting for a worker before submissions are refused
+    pub queue_size: usize,
+    /// Port the first worker's generated servers are tested on, the next worker gets the one after
+    pub base_port: u16,
+    /// Each run gets its own workspace directory in here
+    pub jobs_dir: PathBuf,
+    /// Directory the templates and web server each workspace starts from are copied from
+    pub source_dir: PathBuf,
+    /// CLI executable started for every run
+    pub program: PathBuf,
+    /// Operator accepts that runs build and run model-written code without review
+    pub allow_auto_approval: bool,
+}
+
+impl Default for ServeSettings {
+    fn default() -> Self {
+        Self {
+            addr: DEFAULT_SERVE_ADDR.to_string(),
+            max_jobs: 2,
+            queue_size: 16,
+            base_port: 8100,
+            jobs_dir: PathBuf::from("./runs/jobs"),
+            source_dir: PathBuf::from("."),
+            program: std::env::current_exe()
+                .unwrap_or_else(|_| PathBuf::from("rust-backend-gen-agents")),
+            allow_auto_approval: false,
+        }
+    }
+}
+
+impl ServeSettings {
+    /// Takes `--addr`, `--max-jobs`, `--queue-size`, `--base-port`, `--jobs-dir` and
+    /// `--allow-auto-approval` out of the CLI args
+    pub fn from_args(args: &mut Vec<String>) -> Result<Self, String> {
+        let mut settings: ServeSettings = ServeSettings::default();
+        let mut remaining: Vec<String> = vec![];
+        let mut arg_iter = args.drain(..);
+
+        while let Some(arg) = arg_iter.next() {
+            match arg.as_str() {
+                "--addr" => settings.addr = arg_iter.next().ok_or("--addr needs host:port")?,
+                "--max-jobs" => settings.max_jobs = number_arg(&arg, arg_iter.next())?,
+                "--queue-size" => settings.queue_size = number_arg(&arg, arg_iter.next())?,
+                "--base-port" => settings.base_port = number_arg(&arg, arg_iter.next())?,
+                "--jobs-dir" => {
+                    settings.jobs_dir = arg_iter.next().ok_or("--jobs-dir needs a path")?.into();
+                }
+                "--allow-auto-approval" => settings.allow_auto_approval = true,
+                _ => remaining.push(arg),
+            }
+        }
+
+        drop(arg_iter);
+        *args = remaining;
+
+        if settings.max_jobs == 0 || settings.queue_size == 0 {
+            return Err("--max-jobs and --queue-size must be at least 1".to_string());
+        }
+        if usize::from(settings.base_port) + settings.max_jobs > usize::from(u16::MAX) {
+            return Err("--base-port leaves no port for every job".to_string());
+        }
+        Ok(settings)
+    }
+}
+
+fn number_arg<T: std::str::FromStr>(flag: &str, value: Option<String>) -> Result<T, String> {
+    value
+        .as_deref()
+        .and_then(|value| value.parse().ok())
+        .ok_or_else(|| format!("{} needs a number", flag))
+}
+
+/// Runs the HTTP service until the process is stopped
+pub async fn serve(settings: ServeSettings) -> Result<(), Box<dyn std::error::Error>> {
+    // Runs inherit the provider keys from the server's environment
+    dotenv::dotenv().ok();
+
+    let pipeline: PipelineDefinition = PipelineDefinition::from_env()?;
+    let queue: Arc<JobQueue> = JobQueue::start(settings.clone(), pipeline)?;
+    let listener: tokio::net::TcpListener = tokio::net::TcpListener::bind(&settings.addr).await?;
+    println!(
+        "Serving on http://{} with {} workers",
+        listener.local_addr()?,
+        settings.max_jobs
+    );
+
+    axum::serve(listener, router(queue)).await?;
+    Ok(())
+}
+
+pub fn router(queue: Arc<JobQueue>) -> Router {
+    Router::new()
+        .route("/runs", post(submit_run).get(list_runs))
+        .route("/runs/{id}", get(get_run))
+        .route("/runs/{id}/events", get(run_events))
+        .route("/runs/{id}/artifacts", get(list_run_artifacts))
+        .route("/runs/{id}/artifacts/{*path}", get(download_artifact))
+        .route("/runs/{id}/cancel", post(cancel_run))
+        .with_state(queue)
+}
+
+/// A run with the FactSheet it has written so far
+#[derive(Debug, Serialize)]
+struct RunDetails {
+    #[serde(flatten)]
+    job: Job,
+    factsheet: Option<Value>,
+}
+
+async fn submit_run(
+    State(queue): State<Arc<JobQueue>>,
+    Json(spec): Json<ProjectSpecFile>,
+) -> Result<(StatusCode, Json<Job>), ApiError> {
+    if spec.request.trim().is_empty() {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            "request must not be empty".to_string(),
+        ));
+    }
+    let job: Job = queue.submit(spec).map_err(|_| {
+        (
+            StatusCode::SERVICE_UNAVAILABLE,
+            "Job queue is full, try again later".to_string(),
+        )
+    })?;
+    Ok((StatusCode::ACCEPTED, Json(job)))
+}
+
+async fn list_runs(State(queue): State<Arc<JobQueue>>) -> Json<Vec<Job>> {
+    Json(queue.list())
+}
+
+async fn get_run(
+    State(queue): State<Arc<JobQueue>>,
+    UrlPath(id): UrlPath<String>,
+) -> Result<Json<RunDetails>, ApiError> {
+    let job: Job = find_job(&queue, &id)?;
+    let factsheet: Option<Value> = std::fs::read_to_string(job.workspace.join(FACTSHEET_PATH))
+        .ok()
+        .and_then(|factsheet| serde_json::from_str(&factsheet).ok());
+    Ok(Json(RunDetails { job, factsheet }))
+}
+
+/// Streams every line of the run's JSON agent log, then a `status` event once it finished
+async fn run_events(
+    State(queue): State<Arc<JobQueue>>,
+    UrlPath(id): UrlPath<String>,
+) -> Result<Sse<impl Stream<Item = Result<Event, Infallible>>>, ApiError> {
+    let job: Job = find_job(&queue, &id)?;
+    let tail: LogTail = LogTail::new(job.workspace.join(JOB_LOG_FILE));
+
+    let events = stream::unfold(Some((queue, tail)), move |state| {
+        let id: String = id.clone();
+        async move {
+            let (queue, mut tail) = state?;
+            loop {
+                // Check the status first so lines written just before the end are still sent
+                let finished: Option<Job> = queue.get(&id).filter(|job| job.status.is_finished());
+                let lines: Vec<String> = tail.read_lines().unwrap_or_default();
+                if !lines.is_empty() {
+                    let events: Vec<Result<Event, Infallible>> = lines
+                        .into_iter()
+                        .map(|line| Ok(Event::default().event("log").data(line)))
+                        .collect();
+                    return Some((stream::iter(events), Some((queue, tail))));
+                }
+                if let Some(job) = finished {
+                    let status: Event = Event::default()
+                        .event("status")
+                        .data(job.status.to_string());
+                    return Some((stream::iter(vec![Ok(status)]), None));
+                }
+                tokio::time::sleep(EVENT_POLL_INTERVAL).await;
+            }
+        }
+    });
+
+    Ok(Sse::new(futures_util::StreamExt::flatten(events)).keep_alive(KeepAlive::default()))
+}
+
+async fn list_run_artifacts(
+    State(queue): State<Arc<JobQueue>>,
+    UrlPath(id): UrlPath<String>,
+) -> Result<Json<Vec<PathBuf>>, ApiError> {
+    let job: Job = find_job(&queue, &id)?;
+    Ok(Json(list_artifacts(&job.workspace)))
+}
+
+async fn download_artifact(
+    State(queue): State<Arc<JobQueue>>,
+    UrlPath((id, path)): UrlPath<(String, String)>,
+) -> Result<impl IntoResponse, ApiError> {
+    let job: Job = find_job(&queue, &id)?;
+    let file: PathBuf = artifact_path(&job.workspace, &path).ok_or((
+        StatusCode::BAD_REQUEST,
+        format!("Invalid artifact path '{}'", path),
+    ))?;
+    let contents: Vec<u8> = tokio::fs::read(&file)
+        .await
+        .map_err(|_| (StatusCode::NOT_FOUND, format!("No artifact '{}'", path)))?;
+    Ok((
+        [(header::CONTENT_TYPE, "application/octet-stream")],
+        contents,
+    ))
+}
+
+async fn cancel_run(
+    State(queue): State<Arc<JobQueue>>,
+    UrlPath(id): UrlPath<String>,
+) -> Result<(StatusCode, Json<Job>), ApiError> {
+    let job: Job = queue.cancel(&id).ok_or_else(|| unknown_run(&id))?;
+    if job.status.is_finished() {
+        return Err((
+            StatusCode::CONFLICT,
+            format!("Run {} already {}", id, job.status),
+        ));
+    }
+    Ok((StatusCode::ACCEPTED, Json(job)))
+}
+
+fn find_job(queue: &JobQueue, id: &str) -> Result<Job, ApiError> {
+    queue.get(id).ok_or_else(|| unknown_run(id))
+}
+
+fn unknown_run(id: &str) -> ApiError {
+    (StatusCode::NOT_FOUND, format!("No run with id {}", id))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[cfg(unix)]
+    use crate::server::jobs::JobStatus;
+    #[cfg(unix)]
+    use std::os::unix::fs::PermissionsExt;
+
+    /// Stands in for the CLI: logs a line, writes a FactSheet and hangs for "slow" requests
+    #[cfg(unix)]
+    const FAKE_PROGRAM: &str = r#"#!/bin/sh
+echo '{"fields":{"message":"architect started"}}' >> runs/agent_log.jsonl
+if grep -q slow spec.toml; then sleep 30; fi
+echo "{\"project_description\":\"port $WEB_SERVER_PORT\"}" > runs/factsheet.json
+"#;
+
+    #[cfg(unix)]
+    async fn wait_for_status(client: &reqwest::Client, url: &str, status: &str) -> Value {
+        for _ in 0..100 {
+            let run: Value = client.get(url).send().await.unwrap().json().await.unwrap();
+            if run["status"] == status {
+                return run;
+            }
+            tokio::time::sleep(Duration::from_millis(100)).await;
+        }
+        panic!("{} never became {}", url, status);
+    }
+
+    #[test]
+    fn tests_refuses_auto_approval_without_opt_in() {
+        let error: String =
+            JobQueue::start(ServeSettings::default(), PipelineDefinition::default()).unwrap_err();
+        assert!(error.contains("--allow-auto-approval"));
+
+        let mut args: Vec<String> = vec!["serve".to_string(), "--allow-auto-approval".to_string()];
+        assert!(ServeSettings::from_args(&mut args).unwrap().allow_auto_approval);
+        assert_eq!(args, vec!["serve"]);
+    }
+
+    // The fake CLI is a shell script
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn tests_runs_submitted_projects_on_a_bounded_queue() {
+        let dir: PathBuf = std::env::temp_dir().join("rust-backend-gen-agents-serve-test");
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(dir.join("source/schemas")).unwrap();
+        std::fs::write(dir.join("source/schemas/api_schema.json"), "[]").unwrap();
+        let program: PathBuf = dir.join("fake_program.sh");
+        std::fs::write(&program, FAKE_PROGRAM).unwrap();
+        std::fs::set_permissions(&program, std::fs::Permissions::from_mode(0o755)).unwrap();
+
+        let settings: ServeSettings = ServeSettings {
+            max_jobs: 1,
+            queue_size: 1,
+            base_port: 9100,
+            jobs_dir: dir.join("jobs"),
+            source_dir: dir.join("source"),
+            program,
+            allow_auto_approval: true,
+            ..ServeSettings::default()
+        };
+        let queue: Arc<JobQueue> =
+            JobQueue::start(settings, PipelineDefinition::default()).unwrap();
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base: String = format!("http://{}/runs", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, router(queue)).await });
+        let client: reqwest::Client = reqwest::Client::new();
+
+        let submit = |request: &str| {
+            client
+                .post(&base)
+                .json(&serde_json::json!({ "request": request }))
+                .send()
+        };
+
+        let slow: Value = submit("a slow todo api")
+            .await
+            .unwrap()
+            .json()
+            .await
+            .unwrap();
+        let slow_url: String = format!("{}/{}", base, slow["id"].as_str().unwrap());
+        wait_for_status(&client, &slow_url, "running").await;
+
+        // The only worker is busy and the queue has one slot
+        let fast = submit("a fast todo api").await.unwrap();
+        assert_eq!(fast.status(), StatusCode::ACCEPTED);
+        let fast: Value = fast.json().await.unwrap();
+        let fast_url: String = format!("{}/{}", base, fast["id"].as_str().unwrap());
+        assert_eq!(fast["status"], "queued");
+        assert_eq!(
+            submit("one too many").await.unwrap().status(),
+            StatusCode::SERVICE_UNAVAILABLE
+        );
+        assert_eq!(submit(" ").await.unwrap().status(), StatusCode::BAD_REQUEST);
+
+        let cancel = client
+            .post(format!("{}/cancel", slow_url))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(cancel.status(), StatusCode::ACCEPTED);
+        wait_for_status(&client, &slow_url, "cancelled").await;
+
+        let run: Value = wait_for_status(&client, &fast_url, "succeeded").await;
+        assert_eq!(run["port"], 9100);
+        assert_eq!(run["approval"], "auto");
+        assert_eq!(run["factsheet"]["project_description"], "port 9100");
+        let runs: Value = client
+            .get(&base)
+            .send()
+            .await
+            .unwrap()
+            .json()
+            .await
+            .unwrap();
+        assert_eq!(runs.as_array().unwrap().len(), 2);
+
+        let events: String = client
+            .get(format!("{}/events", fast_url))
+            .send()
+            .await
+            .unwrap()
+            .text()
+            .await
+            .unwrap();
+        assert!(
+            events.contains("event: log\ndata: {\"fields\":{\"message\":\"architect started\"}}")
+        );
+        assert!(events.contains(&format!("event: status\ndata: {}", JobStatus::Succeeded)));
+
+        let artifacts: Vec<String> = client
+            .get(format!("{}/artifacts", fast_url))
+            .send()
+            .await
+            .unwrap()
+            .json()
+            .await
+            .unwrap();
+        assert!(artifacts.contains(&"runs/factsheet.json".to_string()));
+        assert!(artifacts.contains(&"schemas/api_schema.json".to_string()));
+        let spec: String = client
+            .get(format!("{}/artifacts/spec.toml", fast_url))
+            .send()
+            .await
+            .unwrap()
+            .text()
+            .await
+            .unwrap();
+        assert!(spec.contains("a fast todo api"));
+        let pipeline: String = std::fs::read_to_string(
+            dir.join("jobs")
+                .join(fast["id"].as_str().unwrap())
+                .join("pipeline.toml"),
+        )
+        .unwrap();
+        assert!(pipeline.contains("approval = \"auto\""));
+
+        let cancel = client
+            .post(format!("{}/cancel", fast_url))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(cancel.status(), StatusCode::CONFLICT);
+        let missing = client
+            .get(format!("{}/unknown", base))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(missing.status(), StatusCode::NOT_FOUND);
+    }
+}
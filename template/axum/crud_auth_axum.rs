@@ -0,0 +1,130 @@
+// template-tags: crud, auth
+// template-framework: axum
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    response::IntoResponse,
+    routing::{get, post},
+    Json, Router,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::io::Write;
+use std::sync::{Arc, Mutex};
+use tower_http::cors::CorsLayer;
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+struct Task {
+    id: u64,
+    name: String,
+    completed: bool
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+struct User {
+    id: u64,
+    username: String,
+    password: String
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+struct Database {
+    tasks: HashMap<u64, Task>,
+    users: HashMap<u64, User>
+}
+
+impl Database {
+    fn new() -> Self {
+        Self {
+            tasks: HashMap::new(),
+            users: HashMap::new()
+        }
+    }
+
+    // DATABASE SAVING
+    fn save_to_file(&self) -> std::io::Result<()> {
+        let data = serde_json::to_string(&self)?;
+        let mut file = fs::File::create("database.json")?;
+        file.write_all(data.as_bytes())?;
+        Ok(())
+    }
+
+    fn load_from_file() -> std::io::Result<Self> {
+        let file_content = fs::read_to_string("database.json")?;
+        let db: Database = serde_json::from_str(&file_content)?;
+        Ok(db)
+    }
+}
+
+type AppState = Arc<Mutex<Database>>;
+
+async fn create_task(State(state): State<AppState>, Json(task): Json<Task>) -> impl IntoResponse {
+    let mut db = state.lock().unwrap();
+    db.tasks.insert(task.id, task);
+    let _ = db.save_to_file();
+    StatusCode::OK
+}
+
+async fn read_task(State(state): State<AppState>, Path(id): Path<u64>) -> impl IntoResponse {
+    let db = state.lock().unwrap();
+    match db.tasks.get(&id) {
+        Some(task) => Json(task.clone()).into_response(),
+        None => StatusCode::NOT_FOUND.into_response()
+    }
+}
+
+async fn read_all_tasks(State(state): State<AppState>) -> impl IntoResponse {
+    let db = state.lock().unwrap();
+    let tasks: Vec<Task> = db.tasks.values().cloned().collect();
+    Json(tasks)
+}
+
+async fn update_task(State(state): State<AppState>, Json(task): Json<Task>) -> impl IntoResponse {
+    let mut db = state.lock().unwrap();
+    db.tasks.insert(task.id, task);
+    let _ = db.save_to_file();
+    StatusCode::OK
+}
+
+async fn delete_task(State(state): State<AppState>, Path(id): Path<u64>) -> impl IntoResponse {
+    let mut db = state.lock().unwrap();
+    db.tasks.remove(&id);
+    let _ = db.save_to_file();
+    StatusCode::OK
+}
+
+async fn register_user(State(state): State<AppState>, Json(user): Json<User>) -> impl IntoResponse {
+    let mut db = state.lock().unwrap();
+    db.users.insert(user.id, user);
+    let _ = db.save_to_file();
+    StatusCode::OK
+}
+
+async fn login(State(state): State<AppState>, Json(user): Json<User>) -> impl IntoResponse {
+    let db = state.lock().unwrap();
+    match db.users.values().find(|u| u.username == user.username) {
+        Some(stored_user) if stored_user.password == user.password => {
+            (StatusCode::OK, "Logged in!")
+        },
+        _ => (StatusCode::BAD_REQUEST, "Invalid username or password")
+    }
+}
+
+#[tokio::main]
+async fn main() -> std::io::Result<()> {
+    let db = Database::load_from_file().unwrap_or_else(|_| Database::new());
+    let state: AppState = Arc::new(Mutex::new(db));
+
+    let app = Router::new()
+        .route("/task", post(create_task).get(read_all_tasks).put(update_task))
+        .route("/task/{id}", get(read_task).delete(delete_task))
+        .route("/register", post(register_user))
+        .route("/login", post(login))
+        .layer(CorsLayer::permissive())
+        .with_state(state);
+
+    let port: u16 = std::env::var("PORT").ok().and_then(|port| port.parse().ok()).unwrap_or(8080);
+    let listener = tokio::net::TcpListener::bind(("127.0.0.1", port)).await?;
+    axum::serve(listener, app).await
+}
@@ -0,0 +1,39 @@
+// template-tags: file-upload
+// template-kind: fragment
+// template-framework: axum
+use axum::{body::Bytes, extract::Path, http::StatusCode, response::IntoResponse, Json};
+use std::fs;
+
+const UPLOAD_DIR: &str = "./uploads";
+
+// Accepts the raw request body as the file contents, e.g.
+//   curl --data-binary @photo.png http://localhost:8080/upload/photo.png
+async fn upload_file(Path(file_name): Path<String>, body: Bytes) -> impl IntoResponse {
+    if file_name.contains("..") || file_name.contains('/') {
+        return (StatusCode::BAD_REQUEST, "Invalid file name").into_response();
+    }
+
+    if fs::create_dir_all(UPLOAD_DIR).is_err() {
+        return (StatusCode::INTERNAL_SERVER_ERROR, "Failed to create upload directory").into_response();
+    }
+
+    match fs::write(format!("{}/{}", UPLOAD_DIR, file_name), &body) {
+        Ok(_) => (StatusCode::CREATED, Json(serde_json::json!({ "file_name": file_name, "size": body.len() }))).into_response(),
+        Err(_) => (StatusCode::INTERNAL_SERVER_ERROR, "Failed to store file").into_response(),
+    }
+}
+
+async fn download_file(Path(file_name): Path<String>) -> impl IntoResponse {
+    if file_name.contains("..") || file_name.contains('/') {
+        return (StatusCode::BAD_REQUEST, "Invalid file name").into_response();
+    }
+
+    match fs::read(format!("{}/{}", UPLOAD_DIR, file_name)) {
+        Ok(contents) => (StatusCode::OK, contents).into_response(),
+        Err(_) => (StatusCode::NOT_FOUND, "File not found").into_response(),
+    }
+}
+
+// Register on the Router:
+//   .route("/upload/{file_name}", post(upload_file).get(download_file))
+//   .layer(axum::extract::DefaultBodyLimit::max(10 * 1024 * 1024))
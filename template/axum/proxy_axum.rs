@@ -0,0 +1,86 @@
+// template-tags: proxy
+// template-framework: axum
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    routing::get,
+    Json, Router,
+};
+use reqwest::Client as HttpClient;
+use serde_json::Value;
+use std::sync::Arc;
+use tower_http::cors::CorsLayer;
+
+struct AppState {
+    client: HttpClient,
+}
+
+// Overridable so tests can point the server at a local fixture server
+fn hp_api_base_url() -> String {
+    std::env::var("HP_API_ONRENDER_COM_BASE_URL")
+        .unwrap_or_else(|_| "https://hp-api.onrender.com".to_string())
+}
+
+async fn fetch_and_respond(url: &str, state: &AppState) -> Response {
+    match state.client.get(url).send().await {
+        Ok(resp) => match resp.json::<Value>().await {
+            Ok(json) => Json(json).into_response(),
+            Err(_) => (StatusCode::INTERNAL_SERVER_ERROR, "Failed to parse JSON").into_response(),
+        },
+        Err(_) => (StatusCode::INTERNAL_SERVER_ERROR, "Failed to fetch data").into_response(),
+    }
+}
+
+async fn get_all_characters(State(state): State<Arc<AppState>>) -> Response {
+    fetch_and_respond(&format!("{}/api/characters", hp_api_base_url()), &state).await
+}
+
+async fn get_students(State(state): State<Arc<AppState>>) -> Response {
+    fetch_and_respond(&format!("{}/api/characters/students", hp_api_base_url()), &state).await
+}
+
+async fn get_character_by_name(
+    State(state): State<Arc<AppState>>,
+    Path(name): Path<String>,
+) -> Response {
+    let url = format!("{}/api/characters", hp_api_base_url());
+    match state.client.get(&url).send().await {
+        Ok(resp) => match resp.json::<Vec<Value>>().await {
+            Ok(list) => {
+                let filtered: Vec<Value> = list
+                    .into_iter()
+                    .filter(|c| {
+                        c.get("name")
+                            .and_then(Value::as_str)
+                            .map(|n| n.eq_ignore_ascii_case(&name))
+                            .unwrap_or(false)
+                    })
+                    .collect();
+                if !filtered.is_empty() {
+                    Json(filtered).into_response()
+                } else {
+                    (StatusCode::NOT_FOUND, "Character not found").into_response()
+                }
+            }
+            Err(_) => (StatusCode::INTERNAL_SERVER_ERROR, "Failed to parse JSON").into_response(),
+        },
+        Err(_) => (StatusCode::INTERNAL_SERVER_ERROR, "Failed to fetch characters").into_response(),
+    }
+}
+
+#[tokio::main]
+async fn main() -> std::io::Result<()> {
+    let state = Arc::new(AppState { client: HttpClient::new() });
+
+    let app = Router::new()
+        .route("/characters", get(get_all_characters))
+        .route("/characters/students", get(get_students))
+        .route("/characters/{name}", get(get_character_by_name))
+        .layer(CorsLayer::permissive())
+        .with_state(state);
+
+    let port: u16 = std::env::var("PORT").ok().and_then(|port| port.parse().ok()).unwrap_or(8080);
+    let listener = tokio::net::TcpListener::bind(("127.0.0.1", port)).await?;
+    axum::serve(listener, app).await
+}
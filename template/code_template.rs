@@ -1,3 +1,4 @@
+// template-tags: crud, auth
 use actix_cors::Cors;
 use actix_web::{http::header, web, App, HttpServer, Responder, HttpResponse};
 
@@ -153,6 +154,8 @@ async fn main() -> std::io::Result<()> {
         db: Mutex::new(db)
     });
 
+    let port: u16 = std::env::var("PORT").ok().and_then(|port| port.parse().ok()).unwrap_or(8080);
+
     HttpServer::new(move || {
         App::new()
             .wrap(
@@ -175,7 +178,7 @@ async fn main() -> std::io::Result<()> {
             .route("/register", web::post().to(register_user))
             .route("/login", web::post().to(login))
     })
-    .bind("127.0.0.1:8080")?
+    .bind(("127.0.0.1", port))?
     .run()
     .await
 }
\ No newline at end of file
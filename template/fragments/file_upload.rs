@@ -0,0 +1,41 @@
+// template-tags: file-upload
+// template-kind: fragment
+use actix_web::{web, HttpResponse, Responder};
+use std::fs;
+
+const UPLOAD_DIR: &str = "./uploads";
+
+// Accepts the raw request body as the file contents, e.g.
+//   curl --data-binary @photo.png http://localhost:8080/upload/photo.png
+async fn upload_file(file_name: web::Path<String>, body: web::Bytes) -> impl Responder {
+    let file_name = file_name.into_inner();
+    if file_name.contains("..") || file_name.contains('/') {
+        return HttpResponse::BadRequest().body("Invalid file name");
+    }
+
+    if fs::create_dir_all(UPLOAD_DIR).is_err() {
+        return HttpResponse::InternalServerError().body("Failed to create upload directory");
+    }
+
+    match fs::write(format!("{}/{}", UPLOAD_DIR, file_name), &body) {
+        Ok(_) => HttpResponse::Created().json(serde_json::json!({ "file_name": file_name, "size": body.len() })),
+        Err(_) => HttpResponse::InternalServerError().body("Failed to store file"),
+    }
+}
+
+async fn download_file(file_name: web::Path<String>) -> impl Responder {
+    let file_name = file_name.into_inner();
+    if file_name.contains("..") || file_name.contains('/') {
+        return HttpResponse::BadRequest().body("Invalid file name");
+    }
+
+    match fs::read(format!("{}/{}", UPLOAD_DIR, file_name)) {
+        Ok(contents) => HttpResponse::Ok().body(contents),
+        Err(_) => HttpResponse::NotFound().body("File not found"),
+    }
+}
+
+// Register inside App::new():
+//   .app_data(web::PayloadConfig::new(10 * 1024 * 1024))
+//   .route("/upload/{file_name}", web::post().to(upload_file))
+//   .route("/upload/{file_name}", web::get().to(download_file))
@@ -1,3 +1,4 @@
+// template-tags: proxy
 use actix_cors::Cors;
 use actix_web::{http::header, web, App, HttpServer, Responder, HttpResponse};
 use reqwest::Client as HttpClient;
@@ -8,6 +9,12 @@ struct AppState {
     client: HttpClient,
 }
 
+// Overridable so tests can point the server at a local fixture server
+fn hp_api_base_url() -> String {
+    std::env::var("HP_API_ONRENDER_COM_BASE_URL")
+        .unwrap_or_else(|_| "https://hp-api.onrender.com".to_string())
+}
+
 async fn fetch_and_respond(url: &str, data: &web::Data<Arc<AppState>>) -> HttpResponse {
     match data.client.get(url).send().await {
         Ok(resp) => match resp.json::<Value>().await {
@@ -19,15 +26,15 @@ async fn fetch_and_respond(url: &str, data: &web::Data<Arc<AppState>>) -> HttpRe
 }
 
 async fn get_all_characters(data: web::Data<Arc<AppState>>) -> impl Responder {
-    fetch_and_respond("https://hp-api.onrender.com/api/characters", &data).await
+    fetch_and_respond(&format!("{}/api/characters", hp_api_base_url()), &data).await
 }
 
 async fn get_students(data: web::Data<Arc<AppState>>) -> impl Responder {
-    fetch_and_respond("https://hp-api.onrender.com/api/characters/students", &data).await
+    fetch_and_respond(&format!("{}/api/characters/students", hp_api_base_url()), &data).await
 }
 
 async fn get_staff(data: web::Data<Arc<AppState>>) -> impl Responder {
-    fetch_and_respond("https://hp-api.onrender.com/api/characters/staff", &data).await
+    fetch_and_respond(&format!("{}/api/characters/staff", hp_api_base_url()), &data).await
 }
 
 async fn get_character_by_name(
@@ -35,8 +42,8 @@ async fn get_character_by_name(
     name: web::Path<String>,
 ) -> impl Responder {
     let name_ref = name.into_inner();
-    let url = "https://hp-api.onrender.com/api/characters";
-    match data.client.get(url).send().await {
+    let url = format!("{}/api/characters", hp_api_base_url());
+    match data.client.get(&url).send().await {
         Ok(resp) => match resp.json::<Vec<Value>>().await {
             Ok(list) => {
                 let filtered: Vec<Value> = list
@@ -65,6 +72,8 @@ async fn main() -> std::io::Result<()> {
     let client = HttpClient::new();
     let state = Arc::new(AppState { client });
 
+    let port: u16 = std::env::var("PORT").ok().and_then(|port| port.parse().ok()).unwrap_or(8080);
+
     HttpServer::new(move || {
         App::new()
             .wrap(
@@ -82,7 +91,7 @@ async fn main() -> std::io::Result<()> {
             .route("/characters/staff", web::get().to(get_staff))
             .route("/characters/{name}", web::get().to(get_character_by_name))
     })
-    .bind("127.0.0.1:8080")?
+    .bind(("127.0.0.1", port))?
     .run()
     .await
 }
\ No newline at end of file
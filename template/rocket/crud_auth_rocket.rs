@@ -0,0 +1,129 @@
+// template-tags: crud, auth
+// template-framework: rocket
+#[macro_use]
+extern crate rocket;
+
+use rocket::http::Status;
+use rocket::serde::json::Json;
+use rocket::State;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::io::Write;
+use std::sync::Mutex;
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+struct Task {
+    id: u64,
+    name: String,
+    completed: bool
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+struct User {
+    id: u64,
+    username: String,
+    password: String
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+struct Database {
+    tasks: HashMap<u64, Task>,
+    users: HashMap<u64, User>
+}
+
+impl Database {
+    fn new() -> Self {
+        Self {
+            tasks: HashMap::new(),
+            users: HashMap::new()
+        }
+    }
+
+    // DATABASE SAVING
+    fn save_to_file(&self) -> std::io::Result<()> {
+        let data = serde_json::to_string(&self)?;
+        let mut file = fs::File::create("database.json")?;
+        file.write_all(data.as_bytes())?;
+        Ok(())
+    }
+
+    fn load_from_file() -> std::io::Result<Self> {
+        let file_content = fs::read_to_string("database.json")?;
+        let db: Database = serde_json::from_str(&file_content)?;
+        Ok(db)
+    }
+}
+
+struct AppState {
+    db: Mutex<Database>
+}
+
+#[post("/task", data = "<task>")]
+fn create_task(state: &State<AppState>, task: Json<Task>) -> Status {
+    let mut db = state.db.lock().unwrap();
+    let task = task.into_inner();
+    db.tasks.insert(task.id, task);
+    let _ = db.save_to_file();
+    Status::Ok
+}
+
+#[get("/task/<id>")]
+fn read_task(state: &State<AppState>, id: u64) -> Option<Json<Task>> {
+    let db = state.db.lock().unwrap();
+    db.tasks.get(&id).cloned().map(Json)
+}
+
+#[get("/task")]
+fn read_all_tasks(state: &State<AppState>) -> Json<Vec<Task>> {
+    let db = state.db.lock().unwrap();
+    Json(db.tasks.values().cloned().collect())
+}
+
+#[put("/task", data = "<task>")]
+fn update_task(state: &State<AppState>, task: Json<Task>) -> Status {
+    let mut db = state.db.lock().unwrap();
+    let task = task.into_inner();
+    db.tasks.insert(task.id, task);
+    let _ = db.save_to_file();
+    Status::Ok
+}
+
+#[delete("/task/<id>")]
+fn delete_task(state: &State<AppState>, id: u64) -> Status {
+    let mut db = state.db.lock().unwrap();
+    db.tasks.remove(&id);
+    let _ = db.save_to_file();
+    Status::Ok
+}
+
+#[post("/register", data = "<user>")]
+fn register_user(state: &State<AppState>, user: Json<User>) -> Status {
+    let mut db = state.db.lock().unwrap();
+    let user = user.into_inner();
+    db.users.insert(user.id, user);
+    let _ = db.save_to_file();
+    Status::Ok
+}
+
+#[post("/login", data = "<user>")]
+fn login(state: &State<AppState>, user: Json<User>) -> (Status, &'static str) {
+    let db = state.db.lock().unwrap();
+    match db.users.values().find(|u| u.username == user.username) {
+        Some(stored_user) if stored_user.password == user.password => (Status::Ok, "Logged in!"),
+        _ => (Status::BadRequest, "Invalid username or password")
+    }
+}
+
+#[launch]
+fn rocket() -> _ {
+    let db = Database::load_from_file().unwrap_or_else(|_| Database::new());
+    let port: u16 = std::env::var("PORT").ok().and_then(|port| port.parse().ok()).unwrap_or(8080);
+    let figment = rocket::Config::figment()
+        .merge(("address", "127.0.0.1"))
+        .merge(("port", port));
+
+    rocket::custom(figment)
+        .manage(AppState { db: Mutex::new(db) })
+        .mount("/", routes![create_task, read_task, read_all_tasks, update_task, delete_task, register_user, login])
+}
@@ -0,0 +1,43 @@
+// template-tags: file-upload
+// template-kind: fragment
+// template-framework: rocket
+use rocket::data::{Data, ToByteUnit};
+use rocket::http::Status;
+use rocket::serde::json::{json, Value};
+use std::fs;
+
+const UPLOAD_DIR: &str = "./uploads";
+
+// Accepts the raw request body as the file contents, e.g.
+//   curl --data-binary @photo.png http://localhost:8080/upload/photo.png
+#[post("/upload/<file_name>", data = "<body>")]
+async fn upload_file(file_name: &str, body: Data<'_>) -> Result<(Status, Value), (Status, &'static str)> {
+    if file_name.contains("..") || file_name.contains('/') {
+        return Err((Status::BadRequest, "Invalid file name"));
+    }
+
+    let body = body
+        .open(10.mebibytes())
+        .into_bytes()
+        .await
+        .map_err(|_| (Status::BadRequest, "Failed to read file"))?;
+    if !body.is_complete() {
+        return Err((Status::PayloadTooLarge, "File too large"));
+    }
+
+    fs::create_dir_all(UPLOAD_DIR).map_err(|_| (Status::InternalServerError, "Failed to create upload directory"))?;
+    fs::write(format!("{}/{}", UPLOAD_DIR, file_name), body.as_slice())
+        .map_err(|_| (Status::InternalServerError, "Failed to store file"))?;
+    Ok((Status::Created, json!({ "file_name": file_name, "size": body.len() })))
+}
+
+#[get("/upload/<file_name>")]
+fn download_file(file_name: &str) -> Result<Vec<u8>, (Status, &'static str)> {
+    if file_name.contains("..") || file_name.contains('/') {
+        return Err((Status::BadRequest, "Invalid file name"));
+    }
+
+    fs::read(format!("{}/{}", UPLOAD_DIR, file_name)).map_err(|_| (Status::NotFound, "File not found"))
+}
+
+// Add upload_file and download_file to the routes! mounted at "/"